@@ -184,7 +184,7 @@ fn interact_benchmark(c: &mut Criterion) {
         || {
           let mut net = NetWithData::new(1 << 4);
           let book = run::Book::new();
-          ast::net_to_runtime(&mut net.0, &ast::Net { root: Era, rdex: vec![redex.clone()] });
+          ast::net_to_runtime(&mut net.0, &ast::Net::new(Era, vec![redex.clone()]));
           let (rdx_a, rdx_b) = net.0.rdex[0];
           (book, net, rdx_a, rdx_b)
         },
@@ -9,8 +9,8 @@ use crate::run;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::iter::Peekable;
-use std::str::Chars;
+use std::hash::Hash;
+use std::hash::Hasher;
 
 // AST
 // ---
@@ -31,19 +31,144 @@ pub enum Tree {
 
 type Redex = Vec<(Tree, Tree)>;
 
-#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+#[derive(Clone, Debug)]
 pub struct Net {
   pub root: Tree,
   pub rdex: Redex,
+  pub meta: Option<Box<Meta>>, // where it was parsed from, if it was
 }
 
 pub type Book = BTreeMap<String, Net>;
 
+// An 'import "path" [as Name]' directive.
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub struct Import {
+  pub path: String,
+  pub name: Option<String>,
+  pub span: Span,
+}
+
+// A top-level item of a file.
+#[derive(Clone, Debug)]
+pub enum Item {
+  Import(Import),
+  Def(String, Net),
+}
+
+// Where a piece of source code is: its byte range, and the line and column it starts at.
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug, Default)]
+pub struct Span {
+  pub init: usize, // first byte
+  pub done: usize, // byte after the last
+  pub line: usize, // from 1
+  pub col : usize, // from 1, in characters
+}
+
+// A '//' comment.
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub struct Comment {
+  pub span: Span,
+  pub text: String, // including the '//'
+}
+
+// Where a parsed net's parts are, where each of its variables occurs, and the names of the
+// interned references it has.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Meta {
+  pub span: Span, // the whole net, or definition
+  pub root: Span,
+  pub rdex: Vec<Span>, // from each '&'
+  pub vars: BTreeMap<String, Vec<Span>>,
+  pub refs: run::Names,
+}
+
+// Nets are compared by their structure, regardless of where they were parsed from.
+impl PartialEq for Net {
+  fn eq(&self, other: &Self) -> bool {
+    self.root == other.root && self.rdex == other.rdex
+  }
+}
+
+impl Eq for Net {}
+
+impl Hash for Net {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.root.hash(state);
+    self.rdex.hash(state);
+  }
+}
+
+impl Net {
+  // A net that wasn't parsed from source.
+  pub fn new(root: Tree, rdex: Redex) -> Self {
+    Net { root, rdex, meta: None }
+  }
+}
+
+impl Span {
+  // The span from the start of this one to the end of another.
+  pub fn to(self, other: Span) -> Span {
+    Span { done: other.done, ..self }
+  }
+
+  pub fn show(&self) -> String {
+    format!("{}:{}", self.line, self.col)
+  }
+}
+
 // Parser
 // ------
 
+// The parser's input: the characters of a string, along with the position reached, and the
+// comments, variables and interned references found so far.
+pub struct Cursor<'a> {
+  code: &'a str,
+  next: Option<char>,
+  pub pos : usize, // byte offset
+  pub line: usize, // from 1
+  pub col : usize, // from 1, in characters
+  pub comments: Vec<Comment>,
+  pub vars: Vec<(String, Span)>,
+  pub refs: Vec<(run::Val, String)>,
+}
+
+impl<'a> Cursor<'a> {
+  pub fn new(code: &'a str) -> Self {
+    Cursor { code, next: code.chars().next(), pos: 0, line: 1, col: 1, comments: vec![], vars: vec![], refs: vec![] }
+  }
+
+  pub fn peek(&mut self) -> Option<&char> {
+    self.next.as_ref()
+  }
+
+  // An empty span at the current position, to be extended with 'span_from'.
+  pub fn mark(&self) -> Span {
+    Span { init: self.pos, done: self.pos, line: self.line, col: self.col }
+  }
+
+  pub fn span_from(&self, mark: Span) -> Span {
+    Span { done: self.pos, ..mark }
+  }
+}
+
+impl<'a> Iterator for Cursor<'a> {
+  type Item = char;
+  fn next(&mut self) -> Option<char> {
+    let c = self.next?;
+    self.pos += c.len_utf8();
+    if c == '\n' {
+      self.line += 1;
+      self.col = 1;
+    } else {
+      self.col += 1;
+    }
+    self.next = self.code[self.pos..].chars().next();
+    Some(c)
+  }
+}
+
 // FIXME: remove after skip is fixed
-fn skip_spaces(chars: &mut Peekable<Chars>) {
+fn skip_spaces(chars: &mut Cursor) {
   while let Some(c) = chars.peek() {
     if !c.is_ascii_whitespace() {
       break;
@@ -54,16 +179,19 @@ fn skip_spaces(chars: &mut Peekable<Chars>) {
 }
 
 // FIXME: detect two '/' for comments, allowing us to remove 'skip_spaces'
-fn skip(chars: &mut Peekable<Chars>) {
+fn skip(chars: &mut Cursor) {
   while let Some(c) = chars.peek() {
     if *c == '/' {
-      chars.next();
+      let mark = chars.mark();
+      let mut text = String::new();
       while let Some(c) = chars.peek() {
         if *c == '\n' {
           break;
         }
+        text.push(*c);
         chars.next();
       }
+      chars.comments.push(Comment { span: chars.span_from(mark), text: text.trim_end().to_string() });
     } else if !c.is_ascii_whitespace() {
       break;
     } else {
@@ -72,7 +200,7 @@ fn skip(chars: &mut Peekable<Chars>) {
   }
 }
 
-pub fn consume(chars: &mut Peekable<Chars>, text: &str) -> Result<(), String> {
+pub fn consume(chars: &mut Cursor, text: &str) -> Result<(), String> {
   skip(chars);
   for c in text.chars() {
     if chars.next() != Some(c) {
@@ -82,7 +210,7 @@ pub fn consume(chars: &mut Peekable<Chars>, text: &str) -> Result<(), String> {
   return Ok(());
 }
 
-pub fn parse_decimal(chars: &mut Peekable<Chars>) -> Result<u64, String> {
+pub fn parse_decimal(chars: &mut Cursor) -> Result<u64, String> {
   let mut num: u64 = 0;
   skip(chars);
   if !chars.peek().map_or(false, |c| c.is_digit(10)) {
@@ -98,7 +226,7 @@ pub fn parse_decimal(chars: &mut Peekable<Chars>) -> Result<u64, String> {
   Ok(num)
 }
 
-pub fn parse_name(chars: &mut Peekable<Chars>) -> Result<String, String> {
+pub fn parse_name(chars: &mut Cursor) -> Result<String, String> {
   let mut txt = String::new();
   skip(chars);
   if !chars.peek().map_or(false, |c| c.is_alphanumeric() || *c == '_' || *c == '.') {
@@ -114,7 +242,7 @@ pub fn parse_name(chars: &mut Peekable<Chars>) -> Result<String, String> {
   Ok(txt)
 }
 
-pub fn parse_opx_lit(chars: &mut Peekable<Chars>) -> Result<String, String> {
+pub fn parse_opx_lit(chars: &mut Cursor) -> Result<String, String> {
   let mut opx = String::new();
   skip_spaces(chars);
   while let Some(c) = chars.peek() {
@@ -127,7 +255,7 @@ pub fn parse_opx_lit(chars: &mut Peekable<Chars>) -> Result<String, String> {
   Ok(opx)
 }
 
-fn parse_opr(chars: &mut Peekable<Chars>) -> Result<run::Lab, String> {
+fn parse_opr(chars: &mut Cursor) -> Result<run::Lab, String> {
   let opx = parse_opx_lit(chars)?;
   match opx.as_str() {
     "+"  => Ok(run::ADD),
@@ -151,7 +279,7 @@ fn parse_opr(chars: &mut Peekable<Chars>) -> Result<run::Lab, String> {
   }
 }
 
-pub fn parse_tree(chars: &mut Peekable<Chars>) -> Result<Tree, String> {
+pub fn parse_tree(chars: &mut Cursor) -> Result<Tree, String> {
   skip(chars);
   match chars.peek() {
     Some('*') => {
@@ -185,7 +313,11 @@ pub fn parse_tree(chars: &mut Peekable<Chars>) -> Result<Tree, String> {
       chars.next();
       skip(chars);
       let name = parse_name(chars)?;
-      Ok(Tree::Ref { nam: name_to_val(&name) })
+      let nam  = name_to_val(&name);
+      if is_interned(nam) && parse_interned(&name).is_none() {
+        chars.refs.push((nam, name));
+      }
+      Ok(Tree::Ref { nam })
     }
     Some('#') => {
       chars.next();
@@ -216,36 +348,63 @@ pub fn parse_tree(chars: &mut Peekable<Chars>) -> Result<Tree, String> {
       Ok(Tree::Mat { sel, ret })
     }
     _ => {
-      Ok(Tree::Var { nam: parse_name(chars)? })
+      let mark = chars.mark();
+      let nam = parse_name(chars)?;
+      chars.vars.push((nam.clone(), chars.span_from(mark)));
+      Ok(Tree::Var { nam })
     },
   }
 }
 
-pub fn parse_net(chars: &mut Peekable<Chars>) -> Result<Net, String> {
+pub fn parse_net(chars: &mut Cursor) -> Result<Net, String> {
   let mut rdex = Vec::new();
+  let mut meta = Meta::default();
+  let vars = chars.vars.len();
+  let refs = chars.refs.len();
+  skip(chars);
+  let mark = chars.mark();
   let root = parse_tree(chars)?;
+  meta.root = chars.span_from(mark);
+  meta.span = meta.root;
   while let Some(c) = { skip(chars); chars.peek() } {
     if *c == '&' {
+      let mark = chars.mark();
       chars.next();
       let tree1 = parse_tree(chars)?;
       consume(chars, "~")?;
       let tree2 = parse_tree(chars)?;
       rdex.push((tree1, tree2));
+      meta.rdex.push(chars.span_from(mark));
+      meta.span = meta.span.to(chars.mark());
     } else {
       break;
     }
   }
-  Ok(Net { root, rdex })
+  for (nam, span) in chars.vars.drain(vars ..) {
+    meta.vars.entry(nam).or_default().push(span);
+  }
+  meta.refs.extend(chars.refs.drain(refs ..));
+  Ok(Net { root, rdex, meta: Some(Box::new(meta)) })
 }
 
-pub fn parse_book(chars: &mut Peekable<Chars>) -> Result<Book, String> {
+// Parses a definition, after its '@'.
+fn parse_def(chars: &mut Cursor, mark: Span) -> Result<(String, Net), String> {
+  let name = parse_name(chars)?;
+  consume(chars, "=")?;
+  let mut net = parse_net(chars)?;
+  if let Some(meta) = &mut net.meta {
+    meta.span = mark.to(meta.span);
+  }
+  Ok((name, net))
+}
+
+pub fn parse_book(chars: &mut Cursor) -> Result<Book, String> {
   let mut book = BTreeMap::new();
   while let Some(c) = { skip(chars); chars.peek() } {
     if *c == '@' {
+      let mark = chars.mark();
       chars.next();
-      let name = parse_name(chars)?;
-      consume(chars, "=")?;
-      let net = parse_net(chars)?;
+      let (name, net) = parse_def(chars, mark)?;
       book.insert(name, net);
     } else {
       break;
@@ -254,17 +413,68 @@ pub fn parse_book(chars: &mut Peekable<Chars>) -> Result<Book, String> {
   Ok(book)
 }
 
-fn do_parse<T>(code: &str, parse_fn: impl Fn(&mut Peekable<Chars>) -> Result<T, String>) -> T {
-  let chars = &mut code.chars().peekable();
-  match parse_fn(chars) {
-    Ok(result) => {
-      if chars.next().is_none() {
-        result
-      } else {
-        eprintln!("Unable to parse the whole input. Is this not an hvmc file?");
-        std::process::exit(1);
+pub fn parse_string(chars: &mut Cursor) -> Result<String, String> {
+  consume(chars, "\"")?;
+  let mut txt = String::new();
+  while let Some(c) = chars.next() {
+    if c == '"' {
+      return Ok(txt);
+    }
+    txt.push(c);
+  }
+  Err(format!("Unterminated string \"{}", txt))
+}
+
+pub fn parse_import(chars: &mut Cursor) -> Result<Import, String> {
+  skip(chars);
+  let mark = chars.mark();
+  consume(chars, "import")?;
+  let path = parse_string(chars)?;
+  let name = if { skip(chars); chars.peek() == Some(&'a') } {
+    consume(chars, "as")?;
+    Some(parse_name(chars)?)
+  } else {
+    None
+  };
+  Ok(Import { path, name, span: chars.span_from(mark) })
+}
+
+// Parses the items of a file, in order.
+pub fn parse_items(chars: &mut Cursor) -> Result<Vec<Item>, String> {
+  let mut items = Vec::new();
+  while let Some(c) = { skip(chars); chars.peek() } {
+    if *c == '@' {
+      let mark = chars.mark();
+      chars.next();
+      let (name, net) = parse_def(chars, mark)?;
+      items.push(Item::Def(name, net));
+    } else if *c == 'i' {
+      items.push(Item::Import(parse_import(chars)?));
+    } else {
+      break;
+    }
+  }
+  Ok(items)
+}
+
+// Parses a file: a book, which can also import definitions from other files.
+pub fn parse_module(chars: &mut Cursor) -> Result<(Vec<Import>, Book), String> {
+  let mut imports = Vec::new();
+  let mut book = BTreeMap::new();
+  for item in parse_items(chars)? {
+    match item {
+      Item::Import(import) => imports.push(import),
+      Item::Def(name, net) => {
+        book.insert(name, net);
       }
     }
+  }
+  Ok((imports, book))
+}
+
+fn do_parse<T>(code: &str, parse_fn: impl Fn(&mut Cursor) -> Result<T, String>) -> T {
+  match try_parse(code, parse_fn) {
+    Ok(result) => result,
     Err(err) => {
       eprintln!("{}", err);
       std::process::exit(1);
@@ -272,6 +482,16 @@ fn do_parse<T>(code: &str, parse_fn: impl Fn(&mut Peekable<Chars>) -> Result<T,
   }
 }
 
+fn try_parse<T>(code: &str, parse_fn: impl Fn(&mut Cursor) -> Result<T, String>) -> Result<T, String> {
+  let chars = &mut Cursor::new(code);
+  let result = parse_fn(chars).map_err(|err| format!("At {}: {}", chars.mark().show(), err))?;
+  if chars.peek().is_none() {
+    Ok(result)
+  } else {
+    Err(format!("At {}: Unable to parse the whole input. Is this not an hvmc file?", chars.mark().show()))
+  }
+}
+
 pub fn do_parse_tree(code: &str) -> Tree {
   do_parse(code, parse_tree)
 }
@@ -284,6 +504,25 @@ pub fn do_parse_book(code: &str) -> Book {
   do_parse(code, parse_book)
 }
 
+// Like the functions above, but returning errors instead of exiting.
+
+pub fn try_parse_net(code: &str) -> Result<Net, String> {
+  try_parse(code, parse_net)
+}
+
+pub fn try_parse_book(code: &str) -> Result<Book, String> {
+  try_parse(code, parse_book)
+}
+
+pub fn try_parse_module(code: &str) -> Result<(Vec<Import>, Book), String> {
+  try_parse(code, parse_module)
+}
+
+// Parses a file's items, in order, along with its comments.
+pub fn try_parse_source(code: &str) -> Result<(Vec<Item>, Vec<Comment>), String> {
+  try_parse(code, |chars| Ok((parse_items(chars)?, std::mem::take(&mut chars.comments))))
+}
+
 // Stringifier
 // -----------
 
@@ -311,67 +550,256 @@ pub fn show_opr(opr: run::Lab) -> String {
 }
 
 pub fn show_tree(tree: &Tree) -> String {
+  show_tree_go(tree, &[])
+}
+
+// Shows a tree, with the names of its interned references looked up on a table.
+pub fn show_tree_in(tree: &Tree, names: &run::Names) -> String {
+  show_tree_go(tree, &[names])
+}
+
+fn show_tree_go(tree: &Tree, names: &[&run::Names]) -> String {
   match tree {
     Tree::Era => {
       "*".to_string()
     }
     Tree::Con { lft, rgt } => {
-      format!("({} {})", show_tree(&*lft), show_tree(&*rgt))
+      format!("({} {})", show_tree_go(&*lft, names), show_tree_go(&*rgt, names))
     }
     Tree::Tup { lft, rgt } => {
-      format!("[{} {}]", show_tree(&*lft), show_tree(&*rgt))
+      format!("[{} {}]", show_tree_go(&*lft, names), show_tree_go(&*rgt, names))
     }
     Tree::Dup { lab, lft, rgt } => {
-      format!("{{{} {} {}}}", lab, show_tree(&*lft), show_tree(&*rgt))
+      format!("{{{} {} {}}}", lab, show_tree_go(&*lft, names), show_tree_go(&*rgt, names))
     }
     Tree::Var { nam } => {
       nam.clone()
     }
     Tree::Ref { nam } => {
-      format!("@{}", val_to_name(*nam))
+      format!("@{}", show_name(*nam, names))
     }
     Tree::Num { val } => {
       format!("#{}", (*val).to_string())
     }
     Tree::Op1 { opr, lft, rgt } => {
-      format!("<{}{} {}>", lft, show_opr(*opr), show_tree(rgt))
+      format!("<{}{} {}>", lft, show_opr(*opr), show_tree_go(rgt, names))
     }
     Tree::Op2 { opr, lft, rgt } => {
-      format!("<{} {} {}>", show_opr(*opr), show_tree(&*lft), show_tree(&*rgt))
+      format!("<{} {} {}>", show_opr(*opr), show_tree_go(&*lft, names), show_tree_go(&*rgt, names))
     }
     Tree::Mat { sel, ret } => {
-      format!("?<{} {}>", show_tree(&*sel), show_tree(&*ret))
+      format!("?<{} {}>", show_tree_go(&*sel, names), show_tree_go(&*ret, names))
     }
   }
 }
 
+// Shows a net. Parsed nets know the names of their interned references.
 pub fn show_net(net: &Net) -> String {
+  show_net_go(net, &[])
+}
+
+// Shows a net, such as a readback, with the names of its interned references looked up on a
+// table, usually the book's.
+pub fn show_net_in(net: &Net, names: &run::Names) -> String {
+  show_net_go(net, &[names])
+}
+
+fn show_net_go(net: &Net, names: &[&run::Names]) -> String {
+  let mut names = names.to_vec();
+  if let Some(meta) = &net.meta {
+    names.push(&meta.refs);
+  }
   let mut result = String::new();
-  result.push_str(&format!("{}", show_tree(&net.root)));
+  result.push_str(&format!("{}", show_tree_go(&net.root, &names)));
   for (a, b) in &net.rdex {
-    result.push_str(&format!("\n& {} ~ {}", show_tree(a), show_tree(b)));
+    result.push_str(&format!("\n& {} ~ {}", show_tree_go(a, &names), show_tree_go(b, &names)));
   }
   return result;
 }
 
+// Shows a book. References to its own definitions are shown by name.
 pub fn show_book(book: &Book) -> String {
+  show_book_go(book, &[])
+}
+
+fn show_book_go(book: &Book, names: &[&run::Names]) -> String {
+  let defs = book.keys().map(|name| (name_to_val(name), name.clone())).collect::<run::Names>();
+  let mut names = names.to_vec();
+  names.push(&defs);
   let mut result = String::new();
   for (name, net) in book {
-    result.push_str(&format!("@{} = {}\n", name, show_net(net)));
+    result.push_str(&format!("@{} = {}\n", name, show_net_go(net, &names)));
   }
   return result;
 }
 
-pub fn show_runtime_tree<const LAZY: bool>(rt_net: &run::NetFields<LAZY>, ptr: run::Ptr) -> String where [(); LAZY as usize]:{
-  show_tree(&tree_from_runtime_go(rt_net, ptr, PARENT_ROOT, &mut HashMap::new(), &mut 0))
+pub fn show_runtime_tree<const LAZY: bool>(rt_net: &run::NetFields<LAZY>, ptr: run::Ptr, names: &run::Names) -> String where [(); LAZY as usize]:{
+  show_tree_in(&tree_from_runtime_go(rt_net, ptr, PARENT_ROOT, &mut HashMap::new(), &mut 0), names)
 }
 
-pub fn show_runtime_net<const LAZY: bool>(rt_net: &run::NetFields<LAZY>) -> String where [(); LAZY as usize]:{
-  show_net(&net_from_runtime(rt_net))
+pub fn show_runtime_net<const LAZY: bool>(rt_net: &run::NetFields<LAZY>, names: &run::Names) -> String where [(); LAZY as usize]:{
+  show_net_in(&net_from_runtime(rt_net), names)
 }
 
 pub fn show_runtime_book(book: &run::Book) -> String {
-  show_book(&book_from_runtime(book))
+  show_book_go(&book_from_runtime(book), &[&book.names])
+}
+
+// Graphviz
+// --------
+
+// Nets are rendered as undirected DOT graphs. Each node is a record with its principal port on top,
+// its kind in the middle and its auxiliary ports below. Tree edges link an auxiliary port to the
+// principal port of a child, variables are dashed wires between two ports, and active pairs are
+// red edges between two principal ports.
+
+// A wire endpoint: a node port, or a variable, linked when its other occurrence is found.
+enum DotEnd {
+  Port(String),
+  Var(String),
+}
+
+struct Dot {
+  text: String, // graph body
+  pfix: String, // node id prefix
+  next: usize, // next node id
+  vars: HashMap<String, String>, // variables seen once, and their ports
+  names: run::Names, // names of interned references
+}
+
+fn dot_escape(text: &str) -> String {
+  text.chars().flat_map(|c| if "{}|<>\"\\".contains(c) { vec!['\\', c] } else { vec![c] }).collect()
+}
+
+fn dot_node(dot: &mut Dot, kind: &str, ports: &[&str], active: bool) -> String {
+  let id = format!("{}n{}", dot.pfix, dot.next);
+  dot.next += 1;
+  let auxs = ports.iter().map(|port| format!("<{}> ", port)).collect::<Vec<_>>().join("|");
+  let auxs = if ports.is_empty() { String::new() } else { format!("|{{{}}}", auxs) };
+  let attr = if active { ", color=red, penwidth=2" } else { "" };
+  dot.text.push_str(&format!("  {} [label=\"{{<p0> |{}{}}}\"{}];\n", id, dot_escape(kind), auxs, attr));
+  return id;
+}
+
+fn dot_edge(dot: &mut Dot, a: &str, b: &str, attr: &str) {
+  dot.text.push_str(&format!("  {} -- {}{};\n", a, b, attr));
+}
+
+fn dot_bind(dot: &mut Dot, nam: String, port: String) {
+  if let Some(other) = dot.vars.remove(&nam) {
+    dot_edge(dot, &other, &port, &format!(" [style=dashed, label=\"{}\"]", nam));
+  } else {
+    dot.vars.insert(nam, port);
+  }
+}
+
+fn dot_link(dot: &mut Dot, a: DotEnd, b: DotEnd, active: bool) {
+  match (a, b) {
+    (DotEnd::Port(a), DotEnd::Port(b)) => {
+      dot_edge(dot, &a, &b, if active { " [color=red, penwidth=2]" } else { "" });
+    }
+    (DotEnd::Var(nam), DotEnd::Port(port)) | (DotEnd::Port(port), DotEnd::Var(nam)) => {
+      dot_bind(dot, nam, port);
+    }
+    (DotEnd::Var(a), DotEnd::Var(b)) => {
+      let id = format!("{}n{}", dot.pfix, dot.next);
+      dot.next += 1;
+      dot.text.push_str(&format!("  {} [shape=point];\n", id));
+      dot_bind(dot, a, id.clone());
+      dot_bind(dot, b, id);
+    }
+  }
+}
+
+fn tree_to_dot(dot: &mut Dot, tree: &Tree, active: bool) -> DotEnd {
+  let (id, kids) = match tree {
+    Tree::Var { nam } => {
+      return DotEnd::Var(nam.clone());
+    }
+    Tree::Era => {
+      (dot_node(dot, "ERA", &[], active), vec![])
+    }
+    Tree::Ref { nam } => {
+      let name = show_name(*nam, &[&dot.names]);
+      (dot_node(dot, &format!("@{}", name), &[], active), vec![])
+    }
+    Tree::Num { val } => {
+      (dot_node(dot, &format!("#{}", val), &[], active), vec![])
+    }
+    Tree::Con { lft, rgt } => {
+      (dot_node(dot, "CON", &["p1", "p2"], active), vec![("p1", lft), ("p2", rgt)])
+    }
+    Tree::Tup { lft, rgt } => {
+      (dot_node(dot, "TUP", &["p1", "p2"], active), vec![("p1", lft), ("p2", rgt)])
+    }
+    Tree::Dup { lab, lft, rgt } => {
+      (dot_node(dot, &format!("DUP {}", lab), &["p1", "p2"], active), vec![("p1", lft), ("p2", rgt)])
+    }
+    Tree::Op1 { opr, lft, rgt } => {
+      (dot_node(dot, &format!("OP1 {}{}", lft, show_opr(*opr)), &["p2"], active), vec![("p2", rgt)])
+    }
+    Tree::Op2 { opr, lft, rgt } => {
+      (dot_node(dot, &format!("OP2 {}", show_opr(*opr)), &["p1", "p2"], active), vec![("p1", lft), ("p2", rgt)])
+    }
+    Tree::Mat { sel, ret } => {
+      (dot_node(dot, "MAT", &["p1", "p2"], active), vec![("p1", sel), ("p2", ret)])
+    }
+  };
+  for (port, kid) in kids {
+    let kid = tree_to_dot(dot, kid, false);
+    dot_link(dot, DotEnd::Port(format!("{}:{}", id, port)), kid, false);
+  }
+  return DotEnd::Port(format!("{}:p0", id));
+}
+
+fn net_to_dot_go(dot: &mut Dot, net: &Net) {
+  if let Some(meta) = &net.meta {
+    dot.names.extend(meta.refs.iter().map(|(nam, name)| (*nam, name.clone())));
+  }
+  let root = format!("{}root", dot.pfix);
+  dot.text.push_str(&format!("  {} [label=\"ROOT\", shape=plaintext];\n", root));
+  let tree = tree_to_dot(dot, &net.root, false);
+  dot_link(dot, DotEnd::Port(root), tree, false);
+  for (a, b) in &net.rdex {
+    let a = tree_to_dot(dot, a, true);
+    let b = tree_to_dot(dot, b, true);
+    dot_link(dot, a, b, true);
+  }
+  // Free variables are shown as dangling names
+  let mut free = dot.vars.drain().collect::<Vec<_>>();
+  free.sort();
+  for (nam, port) in free {
+    let id = format!("{}free_{}", dot.pfix, nam);
+    dot.text.push_str(&format!("  \"{}\" [label=\"{}\", shape=plaintext];\n", id, nam));
+    dot_edge(dot, &format!("\"{}\"", id), &port, " [style=dashed]");
+  }
+}
+
+const DOT_HEADER: &str = "graph {\n  node [shape=record, fontname=monospace];\n";
+
+pub fn net_to_dot(net: &Net) -> String {
+  net_to_dot_in(net, &run::Names::default())
+}
+
+// Renders a net, such as a readback, with the names of its interned references looked up on a table.
+pub fn net_to_dot_in(net: &Net, names: &run::Names) -> String {
+  let mut dot = Dot { text: DOT_HEADER.to_string(), pfix: String::new(), next: 0, vars: HashMap::new(), names: names.clone() };
+  net_to_dot_go(&mut dot, net);
+  dot.text.push_str("}\n");
+  return dot.text;
+}
+
+// Renders each definition on its own cluster.
+pub fn book_to_dot(book: &Book) -> String {
+  let mut text = DOT_HEADER.to_string();
+  let names = book.keys().map(|name| (name_to_val(name), name.clone())).collect::<run::Names>();
+  for (i, (name, net)) in book.iter().enumerate() {
+    let mut dot = Dot { text: String::new(), pfix: format!("d{}_", i), next: 0, vars: HashMap::new(), names: names.clone() };
+    net_to_dot_go(&mut dot, net);
+    text.push_str(&format!("  subgraph cluster_{} {{\n    label=\"@{}\";\n{}  }}\n", i, name, dot.text));
+  }
+  text.push_str("}\n");
+  return text;
 }
 
 // Conversion
@@ -454,12 +882,251 @@ pub fn letters_to_val(letters: Vec<u8>) -> run::Val {
   return num;
 }
 
+// Names are packed into ids as base-64 digits when they fit: up to 9 characters in [0-9A-Za-z_.],
+// not starting with '0' (which would be dropped). Other names are interned: their id is a hash of
+// the name, above the packed range, and 'run::Book::names' maps it back to the name. Since REFs
+// only have 60 bits, 10-character names, which used to be packed, are now interned too. Without a
+// table, an interned id is shown in hex, as '0x...', and that name parses back to the same id.
+
+const PACK_LIMIT: usize = 9; // max characters of a packed name
+const INTERN_INIT: run::Val = 1 << (6 * PACK_LIMIT); // first interned id
+const INTERN_SIZE: run::Val = (1 << 60) - INTERN_INIT; // interned ids, fitting a REF
+
+fn is_packable(name: &str) -> bool {
+  name.len() <= PACK_LIMIT && !name.starts_with('0') && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+// The id of an interned name: its hash (FNV-1a), moved above the packed range.
+fn intern(name: &str) -> run::Val {
+  let mut hash: u64 = 0xcbf29ce484222325;
+  for byte in name.bytes() {
+    hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+  }
+  INTERN_INIT + hash % INTERN_SIZE
+}
+
+pub fn is_interned(num: run::Val) -> bool {
+  num >= INTERN_INIT
+}
+
 pub fn name_to_val(name: &str) -> run::Val {
-  letters_to_val(name_to_letters(name))
+  if is_packable(name) {
+    letters_to_val(name_to_letters(name))
+  } else if let Some(num) = parse_interned(name) {
+    num
+  } else {
+    intern(name)
+  }
+}
+
+// The id of an interned name shown in hex, as 'val_to_name' does.
+fn parse_interned(name: &str) -> Option<run::Val> {
+  let num = run::Val::from_str_radix(name.strip_prefix("0x")?, 16).ok()?;
+  Some(num).filter(|num| is_interned(*num) && format!("0x{:x}", num) == name)
 }
 
+// The name of a packed id. Interned ids can't be decoded, so they are shown in hex: as packed
+// names never start with '0', this can't be mistaken for one.
 pub fn val_to_name(num: run::Val) -> String {
-  letters_to_name(val_to_letters(num))
+  if is_interned(num) {
+    format!("0x{:x}", num)
+  } else {
+    letters_to_name(val_to_letters(num))
+  }
+}
+
+// The name of an id, looked up on the given tables if it is interned.
+pub fn show_name(num: run::Val, names: &[&run::Names]) -> String {
+  match names.iter().find_map(|names| names.get(&num)) {
+    Some(name) => name.clone(),
+    None       => val_to_name(num),
+  }
+}
+
+// A name usable as an identifier in generated code.
+pub fn val_to_ident(num: run::Val) -> String {
+  let name = val_to_name(num);
+  if !is_interned(num) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+    name
+  } else {
+    format!("_{:x}", num)
+  }
+}
+
+fn tree_refs_go(tree: &Tree, refs: &mut Vec<run::Val>) {
+  match tree {
+    Tree::Ref { nam } => {
+      if !refs.contains(nam) {
+        refs.push(*nam);
+      }
+    }
+    Tree::Con { lft, rgt } | Tree::Tup { lft, rgt } | Tree::Dup { lft, rgt, .. } | Tree::Op2 { lft, rgt, .. } => {
+      tree_refs_go(lft, refs);
+      tree_refs_go(rgt, refs);
+    }
+    Tree::Mat { sel, ret } => {
+      tree_refs_go(sel, refs);
+      tree_refs_go(ret, refs);
+    }
+    Tree::Op1 { rgt, .. } => tree_refs_go(rgt, refs),
+    Tree::Era | Tree::Var { .. } | Tree::Num { .. } => {}
+  }
+}
+
+// The definitions referenced by a net, in order of appearance.
+pub fn net_refs(net: &Net) -> Vec<run::Val> {
+  let mut refs = vec![];
+  tree_refs_go(&net.root, &mut refs);
+  for (a, b) in &net.rdex {
+    tree_refs_go(a, &mut refs);
+    tree_refs_go(b, &mut refs);
+  }
+  return refs;
+}
+
+fn tree_vars_go<'a>(tree: &'a Tree, vars: &mut BTreeMap<&'a str, usize>) {
+  match tree {
+    Tree::Var { nam } => *vars.entry(nam).or_insert(0) += 1,
+    Tree::Con { lft, rgt } | Tree::Tup { lft, rgt } | Tree::Dup { lft, rgt, .. } | Tree::Op2 { lft, rgt, .. } => {
+      tree_vars_go(lft, vars);
+      tree_vars_go(rgt, vars);
+    }
+    Tree::Mat { sel, ret } => {
+      tree_vars_go(sel, vars);
+      tree_vars_go(ret, vars);
+    }
+    Tree::Op1 { rgt, .. } => tree_vars_go(rgt, vars),
+    Tree::Era | Tree::Ref { .. } | Tree::Num { .. } => {}
+  }
+}
+
+// Checks that a net can be converted to the runtime: every variable occurs exactly twice, and no
+// active pair has a variable.
+pub fn check_net(net: &Net) -> Result<(), String> {
+  let mut vars = BTreeMap::new();
+  tree_vars_go(&net.root, &mut vars);
+  for (a, b) in &net.rdex {
+    if let Tree::Var { nam } = a { return Err(format!("variable '{}' on an active pair{}", nam, show_var_spans(net, nam))); }
+    if let Tree::Var { nam } = b { return Err(format!("variable '{}' on an active pair{}", nam, show_var_spans(net, nam))); }
+    tree_vars_go(a, &mut vars);
+    tree_vars_go(b, &mut vars);
+  }
+  if let Some((nam, count)) = vars.iter().find(|(_, count)| **count != 2) {
+    return Err(format!("variable '{}' occurs {} time{}, instead of twice{}", nam, count, if *count == 1 { "" } else { "s" }, show_var_spans(net, nam)));
+  }
+  return Ok(());
+}
+
+// Where a variable occurs, as ' (at line:col, ...)', if the net was parsed.
+fn show_var_spans(net: &Net, nam: &str) -> String {
+  match net.meta.as_ref().and_then(|meta| meta.vars.get(nam)) {
+    Some(spans) => format!(" (at {})", spans.iter().map(|span| span.show()).collect::<Vec<_>>().join(", ")),
+    None        => String::new(),
+  }
+}
+
+// Equivalence
+// -----------
+
+// A bijection between the variables of two nets, with a log of the pairs added, to undo them.
+#[derive(Default)]
+struct VarMap<'a> {
+  ab: HashMap<&'a str, &'a str>,
+  ba: HashMap<&'a str, &'a str>,
+  log: Vec<&'a str>,
+}
+
+impl<'a> VarMap<'a> {
+  fn undo(&mut self, len: usize) {
+    for a in self.log.drain(len ..) {
+      if let Some(b) = self.ab.remove(a) {
+        self.ba.remove(b);
+      }
+    }
+  }
+}
+
+// Checks if two trees are equal up to variable names, extending the bijection between them.
+fn tree_equiv<'a>(a: &'a Tree, b: &'a Tree, vars: &mut VarMap<'a>) -> bool {
+  match (a, b) {
+    (Tree::Era, Tree::Era) => true,
+    (Tree::Con { lft: a_lft, rgt: a_rgt }, Tree::Con { lft: b_lft, rgt: b_rgt })
+    | (Tree::Tup { lft: a_lft, rgt: a_rgt }, Tree::Tup { lft: b_lft, rgt: b_rgt })
+    | (Tree::Mat { sel: a_lft, ret: a_rgt }, Tree::Mat { sel: b_lft, ret: b_rgt }) => {
+      tree_equiv(a_lft, b_lft, vars) && tree_equiv(a_rgt, b_rgt, vars)
+    }
+    (Tree::Dup { lab: a_lab, lft: a_lft, rgt: a_rgt }, Tree::Dup { lab: b_lab, lft: b_lft, rgt: b_rgt })
+    | (Tree::Op2 { opr: a_lab, lft: a_lft, rgt: a_rgt }, Tree::Op2 { opr: b_lab, lft: b_lft, rgt: b_rgt }) => {
+      a_lab == b_lab && tree_equiv(a_lft, b_lft, vars) && tree_equiv(a_rgt, b_rgt, vars)
+    }
+    (Tree::Op1 { opr: a_opr, lft: a_lft, rgt: a_rgt }, Tree::Op1 { opr: b_opr, lft: b_lft, rgt: b_rgt }) => {
+      a_opr == b_opr && a_lft == b_lft && tree_equiv(a_rgt, b_rgt, vars)
+    }
+    (Tree::Var { nam: a_nam }, Tree::Var { nam: b_nam }) => {
+      match (vars.ab.get(a_nam.as_str()), vars.ba.get(b_nam.as_str())) {
+        (None, None) => {
+          vars.ab.insert(a_nam, b_nam);
+          vars.ba.insert(b_nam, a_nam);
+          vars.log.push(a_nam);
+          true
+        }
+        (Some(b), Some(a)) => a == a_nam && b == b_nam,
+        _ => false,
+      }
+    }
+    (Tree::Ref { nam: a_nam }, Tree::Ref { nam: b_nam }) => a_nam == b_nam,
+    (Tree::Num { val: a_val }, Tree::Num { val: b_val }) => a_val == b_val,
+    _ => false,
+  }
+}
+
+// Counts the variables of a tree that are already mapped.
+fn tree_mapped(tree: &Tree, vars: &VarMap) -> usize {
+  match tree {
+    Tree::Var { nam } => vars.ab.contains_key(nam.as_str()) as usize,
+    Tree::Con { lft, rgt } | Tree::Tup { lft, rgt } | Tree::Dup { lft, rgt, .. } | Tree::Op2 { lft, rgt, .. } => tree_mapped(lft, vars) + tree_mapped(rgt, vars),
+    Tree::Mat { sel, ret } => tree_mapped(sel, vars) + tree_mapped(ret, vars),
+    Tree::Op1 { rgt, .. } => tree_mapped(rgt, vars),
+    Tree::Era | Tree::Ref { .. } | Tree::Num { .. } => 0,
+  }
+}
+
+// Matches the remaining redexes of 'a' to the unused ones of 'b', in either orientation,
+// backtracking when a match conflicts with the variables mapped so far.
+fn rdex_equiv<'a>(a: &'a Redex, b: &'a Redex, done: &mut Vec<bool>, used: &mut Vec<bool>, vars: &mut VarMap<'a>) -> bool {
+  // Picks the redex with the most mapped variables, as it has the fewest possible matches.
+  let next = (0 .. a.len()).filter(|i| !done[*i]).max_by_key(|i| tree_mapped(&a[*i].0, vars) + tree_mapped(&a[*i].1, vars));
+  let Some(i) = next else {
+    return true;
+  };
+  done[i] = true;
+  for j in 0 .. b.len() {
+    if used[j] {
+      continue;
+    }
+    for (x, y) in [(&b[j].0, &b[j].1), (&b[j].1, &b[j].0)] {
+      let len = vars.log.len();
+      if tree_equiv(&a[i].0, x, vars) && tree_equiv(&a[i].1, y, vars) {
+        used[j] = true;
+        if rdex_equiv(a, b, done, used, vars) {
+          return true;
+        }
+        used[j] = false;
+      }
+      vars.undo(len);
+    }
+  }
+  done[i] = false;
+  return false;
+}
+
+// Checks if two nets are the same graph: equal up to variable names, the order of their redexes,
+// and the order of the two sides of each redex.
+pub fn net_equiv(a: &Net, b: &Net) -> bool {
+  let mut vars = VarMap::default();
+  a.rdex.len() == b.rdex.len()
+    && tree_equiv(&a.root, &b.root, &mut vars)
+    && rdex_equiv(&a.rdex, &b.rdex, &mut vec![false; a.rdex.len()], &mut vec![false; b.rdex.len()], &mut vars)
 }
 
 // Injection and Readback
@@ -561,7 +1228,8 @@ pub fn tree_to_runtime<const LAZY: bool>(rt_net: &mut run::NetFields<LAZY>, tree
   tree_to_runtime_go(rt_net, tree, &mut HashMap::new(), PARENT_ROOT)
 }
 
-pub fn net_to_runtime<const LAZY: bool>(rt_net: &mut run::NetFields<LAZY>, net: &Net) where [(); LAZY as usize]: {
+// Returns where the first end of each variable was placed.
+pub fn net_to_runtime<const LAZY: bool>(rt_net: &mut run::NetFields<LAZY>, net: &Net) -> HashMap<String, Parent> where [(); LAZY as usize]: {
   let mut vars = HashMap::new();
   let root = tree_to_runtime_go(rt_net, &net.root, &mut vars, PARENT_ROOT);
   rt_net.heap.set_root(root);
@@ -570,6 +1238,7 @@ pub fn net_to_runtime<const LAZY: bool>(rt_net: &mut run::NetFields<LAZY>, net:
     let ptr2 = tree_to_runtime_go(rt_net, tree2, &mut vars, Parent::Redex);
     rt_net.rdex.push((ptr1, ptr2));
   }
+  vars
 }
 
 // Holds dup labels and ref ids used by a definition
@@ -577,8 +1246,8 @@ type InsideLabs = HashSet<run::Lab, nohash_hasher::BuildNoHashHasher<run::Lab>>;
 type InsideRefs = HashSet<run::Val>;
 #[derive(Debug)]
 pub struct Inside {
-  labs: InsideLabs,
-  refs: InsideRefs,
+  pub labs: InsideLabs,
+  pub refs: InsideRefs,
 }
 
 // Collects dup labels and ref ids used by a definition
@@ -624,19 +1293,82 @@ pub fn runtime_def_get_all_labs(labs: &mut InsideLabs, insides: &HashMap<run::Va
   }
 }
 
+// Adds the names of a definition, and of the references it has, to a book's table. Interned ids
+// are hashes, so two names may collide, although it is unlikely enough that we just report it.
+pub fn add_def_names(names: &mut run::Names, name: &str, net: &Net) -> Result<(), String> {
+  let mut add = |nam: run::Val, name: &str| {
+    // A name written as its id, in hex, can be shown without the table
+    if parse_interned(name).is_some() {
+      return Ok(());
+    }
+    match names.get(&nam) {
+      Some(other) if other != name => {
+        Err(format!("Names @{} and @{} have the same id 0x{:x}. Please rename one of them.", other, name, nam))
+      }
+      _ => {
+        names.insert(nam, name.to_string());
+        Ok(())
+      }
+    }
+  };
+  add(name_to_val(name), name)?;
+  if let Some(meta) = &net.meta {
+    for (nam, name) in &meta.refs {
+      add(*nam, name)?;
+    }
+  }
+  return Ok(());
+}
+
+// The names table of a book, or an error if two of its names have the same id.
+pub fn book_names(book: &Book) -> Result<run::Names, String> {
+  let mut names = run::Names::default();
+  let mut defs : HashMap<run::Val, &str> = HashMap::new();
+  for (name, net) in book {
+    // A definition named by its id, in hex, defines the same id as the name it stands for
+    if let Some(other) = defs.insert(name_to_val(name), name) {
+      return Err(format!("Names @{} and @{} have the same id 0x{:x}. Please rename one of them.", other, name, name_to_val(name)));
+    }
+    add_def_names(&mut names, name, net)?;
+  }
+  return Ok(names);
+}
+
 // Converts a book from the pure AST representation to the runtime representation.
 pub fn book_to_runtime(book: &Book) -> run::Book {
   let mut rt_book = run::Book::new();
 
   // Convert each net in 'book' to a runtime net and add to 'rt_book'
   for (name, net) in book {
-    let fid = name_to_val(name);
-    let nodes = run::Heap::<false>::init(1 << 16);
-    let mut rt = run::NetFields::new(&nodes);
-    net_to_runtime(&mut rt, net);
-    rt_book.def(fid, runtime_net_to_runtime_def(&rt));
+    rt_book.def(name_to_val(name), net_to_runtime_def(net));
+  }
+
+  // Books read by 'module::load_book' are checked for colliding names already
+  rt_book.names = book_names(book).unwrap_or_else(|err| panic!("{}", err));
+
+  runtime_book_set_labs(&mut rt_book);
+  rt_book
+}
+
+// Converts a net to a runtime definition, without its 'labs'.
+pub fn net_to_runtime_def(net: &Net) -> run::Def {
+  let nodes = run::Heap::<false>::init(1 << 16);
+  let mut rt = run::NetFields::new(&nodes);
+  let vars = net_to_runtime(&mut rt, net);
+  let mut def = runtime_net_to_runtime_def(&rt);
+  // Keeps the variables' names, by the ports both of their ends are at
+  for (nam, parent) in vars {
+    if let Parent::Node { loc, port } = parent {
+      let other = rt.heap.get(loc, port);
+      def.vars.insert((other.loc(), tag_to_port(other.tag())), nam.clone());
+      def.vars.insert((loc, port), nam);
+    }
   }
+  def
+}
 
+// Recomputes the 'labs' of every definition, as changing one may affect the ones referring to it.
+pub fn runtime_book_set_labs(rt_book: &mut run::Book) {
   // Calculate the 'insides' of each runtime definition
   let mut insides = HashMap::new();
   for (fid, def) in &rt_book.defs {
@@ -657,8 +1389,6 @@ pub fn book_to_runtime(book: &Book) -> run::Book {
     def.labs = labs_by_fid.get(fid).unwrap().clone();
     //println!("{} {:?}", val_to_name(*fid), def.labs);
   }
-
-  rt_book
 }
 
 // Converts to a def.
@@ -681,7 +1411,7 @@ pub fn runtime_net_to_runtime_def<const LAZY: bool>(net: &run::NetFields<LAZY>)
     let p2 = net.rdex[i].1;
     rdex.push((p1, p2));
   }
-  return run::Def { labs, rdex, node };
+  return run::Def { labs, rdex, node, vars: HashMap::new() };
 }
 
 // Reads back from a def.
@@ -773,7 +1503,11 @@ pub fn tree_from_runtime<const LAZY: bool>(rt_net: &run::NetFields<LAZY>, ptr: r
 }
 
 pub fn net_from_runtime<const LAZY: bool>(rt_net: &run::NetFields<LAZY>) -> Net where [(); LAZY as usize]: {
-  let mut vars = HashMap::new();
+  net_from_runtime_go(rt_net, HashMap::new())
+}
+
+// Reads back a net, with the given names for the variables at some ports.
+fn net_from_runtime_go<const LAZY: bool>(rt_net: &run::NetFields<LAZY>, mut vars: HashMap<Parent, String>) -> Net where [(); LAZY as usize]: {
   let mut fresh = 0;
   let mut rdex = Vec::new();
   let root = tree_from_runtime_go(rt_net, rt_net.heap.get_root(), PARENT_ROOT, &mut vars, &mut fresh);
@@ -782,18 +1516,29 @@ pub fn net_from_runtime<const LAZY: bool>(rt_net: &run::NetFields<LAZY>) -> Net
     let tree_b = tree_from_runtime_go(rt_net, b, Parent::Redex, &mut vars, &mut fresh);
     rdex.push((tree_a, tree_b));
   }
-  Net { root, rdex }
+  Net::new(root, rdex)
 }
 
 pub fn book_from_runtime(rt_book: &run::Book) -> Book {
   let mut book = BTreeMap::new();
   for (fid, def) in rt_book.defs.iter() {
     if def.node.len() > 0 {
-      let name  = val_to_name(*fid);
-      let nodes = run::Heap::<false>::init(def.node.len());
-      let net   = net_from_runtime(&runtime_def_to_runtime_net(&nodes, &def));
-      book.insert(name, net);
+      let name  = rt_book.names.get(fid).cloned().unwrap_or_else(|| val_to_name(*fid));
+      book.insert(name, runtime_def_to_net(def));
     }
   }
   book
 }
+
+// Reads back a definition, with its variables' original names, if it has all of them.
+pub fn runtime_def_to_net(def: &run::Def) -> Net {
+  let nodes = run::Heap::<false>::init(def.node.len());
+  let ends  = def.node.iter().flat_map(|(_, p1, p2)| [p1, p2]).filter(|ptr| ptr.is_var()).count();
+  let mut vars = HashMap::new();
+  if def.vars.len() == ends {
+    for ((loc, port), nam) in &def.vars {
+      vars.insert(Parent::Node { loc: *loc, port: *port }, nam.clone());
+    }
+  }
+  net_from_runtime_go(&runtime_def_to_runtime_net(&nodes, def), vars)
+}
@@ -0,0 +1,737 @@
+// Compiles a book to a standalone C11 program.
+// --------------------------------------------
+// The generated program embeds a C port of the sequential evaluator in 'run.rs' (same pointer
+// layout, same allocator and same interaction rules), specializes each definition into a function
+// that expands it in place, and adds a 'main' that normalizes '@main' and prints it in the textual
+// syntax. Since both runtimes perform the same rewrites in the same order, the result and the
+// RWTS lines printed with '-s' can be compared against 'hvmc run -1 -s'. The C runtime doesn't
+// track live or peak nodes, so it doesn't print the LIVE and PEAK lines.
+
+use crate::run;
+use crate::ast;
+
+pub fn compile_book(book: &run::Book) -> Result<String, String> {
+  if !book.defs.contains_key(&ast::name_to_val("main")) {
+    return Err(format!("the book has no @main to run"));
+  }
+
+  let mut code = String::new();
+
+  code.push_str(RUNTIME);
+  code.push_str("\n");
+
+  // Sorts the definitions, so that the output is deterministic
+  let mut defs = book.defs.iter().filter(|(_, def)| def.node.len() > 0).collect::<Vec<_>>();
+  defs.sort_by_key(|(fid, _)| **fid);
+
+  code.push_str("// Book\n");
+  code.push_str("// ----\n\n");
+
+  for (fid, _) in &defs {
+    code.push_str(&format!("#define ID_{} 0x{:x}ull\n", ast::val_to_ident(**fid), fid));
+  }
+  code.push_str("\n");
+
+  for (fid, _) in &defs {
+    code.push_str(&format!("static void F_{}(Net* net, Ptr ptr, Ptr trg);\n", ast::val_to_ident(**fid)));
+  }
+  code.push_str("\n");
+
+  // Maps ids back to names, as interned ones can't be unpacked
+  code.push_str("static const char* def_name(Val val) {\n");
+  code.push_str(&format!("{}switch (val) {{\n", ident(1)));
+  for (fid, _) in &defs {
+    if let Some(name) = book.names.get(fid) {
+      code.push_str(&format!("{}case ID_{}: return \"{}\";\n", ident(2), ast::val_to_ident(**fid), name));
+    }
+  }
+  code.push_str(&format!("{}default: return NULL;\n", ident(2)));
+  code.push_str(&format!("{}}}\n", ident(1)));
+  code.push_str("}\n\n");
+
+  // Dispatches a dereference to the specialized function of a definition
+  code.push_str("static void call_ref(Net* net, Ptr ptr, Ptr trg) {\n");
+  code.push_str(&format!("{}switch (ptr_val(ptr)) {{\n", ident(1)));
+  for (fid, _) in &defs {
+    let fun = ast::val_to_ident(**fid);
+    code.push_str(&format!("{}case ID_{}: F_{}(net, ptr, trg); return;\n", ident(2), fun, fun));
+  }
+  code.push_str(&format!("{}default: fprintf(stderr, \"Undefined reference: @%s\\n\", val_to_name(ptr_val(ptr))); exit(1);\n", ident(2)));
+  code.push_str(&format!("{}}}\n", ident(1)));
+  code.push_str("}\n\n");
+
+  for (fid, def) in &defs {
+    code.push_str(&compile_def(**fid, def));
+    code.push_str("\n");
+  }
+
+  code.push_str(MAIN);
+
+  return Ok(code);
+}
+
+pub fn ident(tab: usize) -> String {
+  return "  ".repeat(tab);
+}
+
+// Converts a definition pointer to a C expression, adjusting locations to the allocated ones.
+fn adjust(ptr: run::Ptr) -> String {
+  if ptr.has_loc() {
+    let loc = if ptr.loc() == 0 { "0".to_string() } else { format!("l{}", ptr.loc()) };
+    return format!("ptr_new({}, 0x{:x}, {})", crate::jit::tag(ptr.tag()), ptr.lab(), loc);
+  } else {
+    return format!("0x{:x}ull", ptr.0);
+  }
+}
+
+// Compiles a definition to a function that expands it, mirroring 'NetFields::call'.
+pub fn compile_def(fid: run::Val, def: &run::Def) -> String {
+  let fun = ast::val_to_ident(fid);
+  let mut code = String::new();
+
+  // Given a label, returns true if the definition contains that dup label, directly or not
+  let mut labs = def.labs.iter().collect::<Vec<_>>();
+  labs.sort();
+  code.push_str(&format!("static bool L_{}(Lab lab) {{\n", fun));
+  for lab in labs {
+    code.push_str(&format!("{}if (lab == 0x{:x}) {{ return true; }}\n", ident(1), lab));
+  }
+  code.push_str(&format!("{}return false;\n", ident(1)));
+  code.push_str("}\n\n");
+
+  code.push_str(&format!("static void F_{}(Net* net, Ptr ptr, Ptr trg) {{\n", fun));
+  code.push_str(&format!("{}if (is_dup(trg) && !L_{}(ptr_lab(trg))) {{\n", ident(1), fun));
+  code.push_str(&format!("{}copy(net, trg, ptr);\n", ident(2)));
+  code.push_str(&format!("{}return;\n", ident(2)));
+  code.push_str(&format!("{}}}\n", ident(1)));
+  // Allocate space.
+  for i in 1 .. def.node.len() {
+    code.push_str(&format!("{}Loc l{} = alloc_node(net);\n", ident(1), i));
+  }
+  // Load nodes, adjusted.
+  for i in 1 .. def.node.len() {
+    let (_, p1, p2) = def.node[i];
+    if p1 != run::ROOT {
+      code.push_str(&format!("{}link(net, ptr_new(VR1, 0, l{}), {});\n", ident(1), i, adjust(p1)));
+    }
+    if p2 != run::ROOT {
+      code.push_str(&format!("{}link(net, ptr_new(VR2, 0, l{}), {});\n", ident(1), i, adjust(p2)));
+    }
+  }
+  // Load redexes, adjusted.
+  for (a, b) in &def.rdex {
+    code.push_str(&format!("{}redux(net, {}, {});\n", ident(1), adjust(*a), adjust(*b)));
+  }
+  // Load root, adjusted.
+  code.push_str(&format!("{}link(net, {}, trg);\n", ident(1), adjust(def.node[0].2)));
+  code.push_str("}\n");
+
+  return code;
+}
+
+const RUNTIME: &str = r##"// Generated by hvmc. Build with: cc -O3 -std=c11 file.c -o file
+
+#include <stdatomic.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+// Runtime
+// -------
+
+typedef uint8_t  Tag;
+typedef uint32_t Lab;
+typedef uint32_t Loc;
+typedef uint64_t Val;
+typedef uint64_t Ptr;
+typedef uint64_t Port;
+
+#define VR1 0x0
+#define VR2 0x1
+#define RD1 0x2
+#define RD2 0x3
+#define REF 0x4
+#define ERA 0x5
+#define NUM 0x6
+#define OP2 0x7
+#define OP1 0x8
+#define MAT 0x9
+#define LAM 0xA
+#define TUP 0xB
+#define DUP 0xC
+#define END 0xE
+
+#define ADD 0x00
+#define SUB 0x01
+#define MUL 0x02
+#define DIV 0x03
+#define MOD 0x04
+#define EQ  0x05
+#define NE  0x06
+#define LT  0x07
+#define GT  0x08
+#define LTE 0x09
+#define GTE 0x0A
+#define AND 0x0B
+#define OR  0x0C
+#define XOR 0x0D
+#define LSH 0x0E
+#define RSH 0x0F
+#define NOT 0x10
+
+#define P1 0
+#define P2 1
+
+#define ERAS ((Ptr)0x0000000000000005ull)
+#define ROOT ((Ptr)0x0000000000000001ull)
+#define NUL  ((Ptr)0x0000000000000000ull)
+#define GONE ((Ptr)0xFFFFFFFFFFFFFFEFull)
+#define LOCK ((Ptr)0xFFFFFFFFFFFFFFFFull)
+
+#define U60_MASK 0xFFFFFFFFFFFFFFFull
+
+#ifndef HVMC_HEAP_SIZE
+#define HVMC_HEAP_SIZE (1ull << 28)
+#endif
+
+static inline Ptr ptr_new(Tag tag, Lab lab, Loc loc) { return ((Ptr)loc << 32) | ((Ptr)lab << 4) | (Ptr)tag; }
+static inline Ptr ptr_big(Tag tag, Val val) { return (val << 4) | (Ptr)tag; }
+static inline Tag ptr_tag(Ptr ptr) { return (Tag)(ptr & 0xF); }
+static inline Lab ptr_lab(Ptr ptr) { return ((Lab)ptr) >> 4; }
+static inline Loc ptr_loc(Ptr ptr) { return (Loc)(ptr >> 32); }
+static inline Val ptr_val(Ptr ptr) { return ptr >> 4; }
+
+static inline bool is_var(Ptr ptr) { return ptr_tag(ptr) <= VR2 && ptr != NUL; }
+static inline bool is_red(Ptr ptr) { return ptr_tag(ptr) >= RD1 && ptr_tag(ptr) <= RD2 && ptr != NUL; }
+static inline bool is_era(Ptr ptr) { return ptr_tag(ptr) == ERA; }
+static inline bool is_ctr(Ptr ptr) { return ptr_tag(ptr) >= LAM && ptr_tag(ptr) <= END; }
+static inline bool is_dup(Ptr ptr) { return ptr_tag(ptr) == DUP; }
+static inline bool is_ref(Ptr ptr) { return ptr_tag(ptr) == REF; }
+static inline bool is_pri(Ptr ptr) { return ptr_tag(ptr) >= REF && ptr_tag(ptr) <= END; }
+static inline bool is_num(Ptr ptr) { return ptr_tag(ptr) == NUM; }
+static inline bool is_nod(Ptr ptr) { return ptr_tag(ptr) >= OP2 && ptr_tag(ptr) <= END; }
+static inline bool can_skip(Ptr a, Ptr b) {
+  return (ptr_tag(a) == ERA || ptr_tag(a) == REF) && (ptr_tag(b) == ERA || ptr_tag(b) == REF);
+}
+static inline Ptr redirect(Ptr ptr) { return ptr_new(ptr_tag(ptr) + RD2 - VR2, 0, ptr_loc(ptr)); }
+
+typedef struct {
+  uint64_t anni;
+  uint64_t comm;
+  uint64_t eras;
+  uint64_t dref;
+  uint64_t oper;
+} Rewrites;
+
+typedef struct {
+  Ptr a;
+  Ptr b;
+} Redex;
+
+typedef struct {
+  _Atomic(Ptr)* heap; // two ports per node
+  uint64_t size; // total nodes
+  uint64_t next; // next allocation index
+  Redex* rdex; // redex stack
+  uint64_t rlen; // redex count
+  uint64_t rcap; // redex capacity
+  Rewrites rwts; // rewrite count
+} Net;
+
+static void call_ref(Net* net, Ptr ptr, Ptr trg);
+
+static inline Ptr heap_get(Net* net, Loc loc, Port port) {
+  return atomic_load_explicit(&net->heap[loc * 2 + port], memory_order_relaxed);
+}
+
+static inline void heap_set(Net* net, Loc loc, Port port, Ptr val) {
+  atomic_store_explicit(&net->heap[loc * 2 + port], val, memory_order_relaxed);
+}
+
+static inline Ptr heap_swap(Net* net, Loc loc, Port port, Ptr val) {
+  return atomic_exchange_explicit(&net->heap[loc * 2 + port], val, memory_order_relaxed);
+}
+
+static inline bool heap_cas(Net* net, Loc loc, Port port, Ptr expected, Ptr val) {
+  return atomic_compare_exchange_strong_explicit(&net->heap[loc * 2 + port], &expected, val, memory_order_relaxed, memory_order_relaxed);
+}
+
+static inline Ptr get_target(Net* net, Ptr ptr) { return heap_get(net, ptr_loc(ptr), ptr & 1); }
+static inline void set_target(Net* net, Ptr ptr, Ptr val) { heap_set(net, ptr_loc(ptr), ptr & 1, val); }
+static inline Ptr swap_target(Net* net, Ptr ptr, Ptr val) { return heap_swap(net, ptr_loc(ptr), ptr & 1, val); }
+static inline bool cas_target(Net* net, Ptr ptr, Ptr expected, Ptr val) { return heap_cas(net, ptr_loc(ptr), ptr & 1, expected, val); }
+
+static Ptr take_target(Net* net, Ptr ptr) {
+  for (;;) {
+    Ptr got = swap_target(net, ptr, LOCK);
+    if (got != LOCK && got != NUL) {
+      return got;
+    }
+  }
+}
+
+static Loc alloc_node(Net* net) {
+  Loc index;
+  if (net->next < net->size - 1) {
+    net->next += 1;
+    index = (Loc)net->next;
+  } else {
+    // Searches for a free slot, giving up after a whole pass over the heap.
+    for (uint64_t seen = 0;; ++seen) {
+      if (seen == net->size) {
+        fprintf(stderr, "Out of memory: the heap is full (%llu nodes)\n", (unsigned long long)net->size);
+        exit(1);
+      }
+      net->next += 1;
+      index = (Loc)(net->next % net->size);
+      if (heap_get(net, index, P1) == NUL && heap_get(net, index, P2) == NUL) {
+        break;
+      }
+    }
+  }
+  heap_set(net, index, P1, LOCK);
+  heap_set(net, index, P2, LOCK);
+  return index;
+}
+
+static void redux(Net* net, Ptr a, Ptr b) {
+  if (can_skip(a, b)) {
+    net->rwts.eras += 1;
+  } else {
+    if (net->rlen == net->rcap) {
+      net->rcap = net->rcap * 2 + 256;
+      net->rdex = realloc(net->rdex, net->rcap * sizeof(Redex));
+    }
+    net->rdex[net->rlen++] = (Redex){ a, b };
+  }
+}
+
+static void linker(Net* net, Ptr a_ptr, Ptr b_ptr) {
+  if (is_var(a_ptr)) {
+    set_target(net, a_ptr, b_ptr);
+  }
+}
+
+static void link(Net* net, Ptr a_ptr, Ptr b_ptr) {
+  if (is_pri(a_ptr) && is_pri(b_ptr)) {
+    redux(net, a_ptr, b_ptr);
+  } else {
+    linker(net, a_ptr, b_ptr);
+    linker(net, b_ptr, a_ptr);
+  }
+}
+
+static void atomic_linker_pri(Net* net, Ptr a_ptr, Ptr a_dir, Ptr b_ptr) {
+  for (;;) {
+    Ptr t_dir = a_ptr;
+    Ptr t_ptr = get_target(net, t_dir);
+    if (is_red(t_ptr)) {
+      set_target(net, t_dir, NUL);
+      a_ptr = t_ptr;
+      continue;
+    }
+    if (is_var(t_ptr)) {
+      if (cas_target(net, t_dir, t_ptr, b_ptr)) {
+        set_target(net, a_dir, NUL);
+        t_dir = t_ptr;
+        t_ptr = get_target(net, t_ptr);
+        while (is_red(t_ptr)) {
+          swap_target(net, t_dir, NUL);
+          t_dir = t_ptr;
+          t_ptr = get_target(net, t_dir);
+        }
+        return;
+      }
+      continue;
+    }
+    if (is_pri(t_ptr) || t_ptr == GONE) {
+      Ptr x_dir = a_dir < t_dir ? a_dir : t_dir;
+      Ptr y_dir = a_dir < t_dir ? t_dir : a_dir;
+      Ptr x_ptr = swap_target(net, x_dir, GONE);
+      if (x_ptr != GONE) {
+        Ptr y_ptr = swap_target(net, y_dir, GONE);
+        redux(net, x_ptr, y_ptr);
+      } else {
+        swap_target(net, x_dir, NUL);
+        while (!cas_target(net, y_dir, GONE, NUL)) {}
+      }
+      return;
+    }
+    if (t_ptr == LOCK || t_ptr == NUL) {
+      continue;
+    }
+    fprintf(stderr, "Invalid link target: %016llx\n", (unsigned long long)t_ptr);
+    exit(1);
+  }
+}
+
+static void atomic_linker(Net* net, Ptr a_ptr, Ptr a_dir, Ptr b_ptr) {
+  if (is_var(a_ptr)) {
+    if (cas_target(net, a_ptr, a_dir, b_ptr)) {
+      set_target(net, a_dir, NUL);
+    } else if (is_var(b_ptr)) {
+      set_target(net, a_dir, redirect(b_ptr));
+    } else if (is_pri(b_ptr)) {
+      set_target(net, a_dir, b_ptr);
+      atomic_linker_pri(net, a_ptr, a_dir, b_ptr);
+    } else {
+      fprintf(stderr, "Invalid link source: %016llx\n", (unsigned long long)b_ptr);
+      exit(1);
+    }
+  } else {
+    set_target(net, a_dir, NUL);
+  }
+}
+
+static void atomic_link(Net* net, Ptr a_dir, Ptr b_dir) {
+  Ptr a_ptr = take_target(net, a_dir);
+  Ptr b_ptr = take_target(net, b_dir);
+  if (is_pri(a_ptr) && is_pri(b_ptr)) {
+    set_target(net, a_dir, NUL);
+    set_target(net, b_dir, NUL);
+    redux(net, a_ptr, b_ptr);
+  } else {
+    atomic_linker(net, a_ptr, a_dir, b_ptr);
+    atomic_linker(net, b_ptr, b_dir, a_ptr);
+  }
+}
+
+static void half_atomic_link(Net* net, Ptr a_dir, Ptr b_ptr) {
+  Ptr a_ptr = take_target(net, a_dir);
+  if (is_pri(a_ptr) && is_pri(b_ptr)) {
+    set_target(net, a_dir, NUL);
+    redux(net, a_ptr, b_ptr);
+  } else {
+    atomic_linker(net, a_ptr, a_dir, b_ptr);
+    linker(net, b_ptr, a_ptr);
+  }
+}
+
+static Val u60_op(Lab op, Val a, Val b) {
+  switch (op) {
+    case ADD: return (a + b) & U60_MASK;
+    case SUB: return a >= b ? a - b : 0x1000000000000000ull - (b - a);
+    case MUL: return (Val)(((unsigned __int128)a * (unsigned __int128)b) & U60_MASK);
+    case DIV: return a / b;
+    case MOD: return a % b;
+    case EQ:  return a == b;
+    case NE:  return a != b;
+    case LT:  return a < b;
+    case GT:  return a > b;
+    case LTE: return a <= b;
+    case GTE: return a >= b;
+    case AND: return a & b;
+    case OR:  return a | b;
+    case XOR: return a ^ b;
+    case NOT: return ~a & U60_MASK;
+    case LSH: return (a << (b & 63)) & U60_MASK;
+    case RSH: return a >> (b & 63);
+    default:  fprintf(stderr, "Unknown operator: %u\n", op); exit(1);
+  }
+}
+
+static void anni(Net* net, Ptr a, Ptr b) {
+  net->rwts.anni += 1;
+  atomic_link(net, ptr_new(VR1, 0, ptr_loc(a)), ptr_new(VR1, 0, ptr_loc(b)));
+  atomic_link(net, ptr_new(VR2, 0, ptr_loc(a)), ptr_new(VR2, 0, ptr_loc(b)));
+}
+
+static void comm(Net* net, Ptr a, Ptr b) {
+  net->rwts.comm += 1;
+  Loc loc0 = alloc_node(net);
+  Loc loc1 = alloc_node(net);
+  Loc loc2 = alloc_node(net);
+  Loc loc3 = alloc_node(net);
+  heap_set(net, loc0, P1, ptr_new(VR1, 0, loc2));
+  heap_set(net, loc0, P2, ptr_new(VR1, 0, loc3));
+  heap_set(net, loc1, P1, ptr_new(VR2, 0, loc2));
+  heap_set(net, loc1, P2, ptr_new(VR2, 0, loc3));
+  heap_set(net, loc2, P1, ptr_new(VR1, 0, loc0));
+  heap_set(net, loc2, P2, ptr_new(VR1, 0, loc1));
+  heap_set(net, loc3, P1, ptr_new(VR2, 0, loc0));
+  heap_set(net, loc3, P2, ptr_new(VR2, 0, loc1));
+  half_atomic_link(net, ptr_new(VR1, 0, ptr_loc(a)), ptr_new(ptr_tag(b), ptr_lab(b), loc0));
+  half_atomic_link(net, ptr_new(VR1, 0, ptr_loc(b)), ptr_new(ptr_tag(a), ptr_lab(a), loc2));
+  half_atomic_link(net, ptr_new(VR2, 0, ptr_loc(a)), ptr_new(ptr_tag(b), ptr_lab(b), loc1));
+  half_atomic_link(net, ptr_new(VR2, 0, ptr_loc(b)), ptr_new(ptr_tag(a), ptr_lab(a), loc3));
+}
+
+static void era2(Net* net, Ptr a) {
+  net->rwts.eras += 1;
+  half_atomic_link(net, ptr_new(VR1, 0, ptr_loc(a)), ERAS);
+  half_atomic_link(net, ptr_new(VR2, 0, ptr_loc(a)), ERAS);
+}
+
+static void era1(Net* net, Ptr a) {
+  net->rwts.eras += 1;
+  heap_set(net, ptr_loc(a), P1, NUL);
+  half_atomic_link(net, ptr_new(VR2, 0, ptr_loc(a)), ERAS);
+}
+
+static void pass(Net* net, Ptr a, Ptr b) {
+  net->rwts.comm += 1;
+  Loc loc0 = alloc_node(net);
+  Loc loc1 = alloc_node(net);
+  Loc loc2 = alloc_node(net);
+  heap_set(net, loc0, P1, ptr_new(VR2, 0, loc1));
+  heap_set(net, loc0, P2, ptr_new(VR2, 0, loc2));
+  heap_set(net, loc1, P1, heap_get(net, ptr_loc(a), P1));
+  heap_set(net, loc1, P2, ptr_new(VR1, 0, loc0));
+  heap_set(net, loc2, P1, heap_get(net, ptr_loc(a), P1));
+  heap_set(net, loc2, P2, ptr_new(VR2, 0, loc0));
+  heap_set(net, ptr_loc(a), P1, NUL);
+  half_atomic_link(net, ptr_new(VR2, 0, ptr_loc(a)), ptr_new(ptr_tag(b), ptr_lab(b), loc0));
+  half_atomic_link(net, ptr_new(VR1, 0, ptr_loc(b)), ptr_new(ptr_tag(a), ptr_lab(a), loc1));
+  half_atomic_link(net, ptr_new(VR2, 0, ptr_loc(b)), ptr_new(ptr_tag(a), ptr_lab(a), loc2));
+}
+
+static void copy(Net* net, Ptr a, Ptr b) {
+  net->rwts.comm += 1;
+  half_atomic_link(net, ptr_new(VR1, 0, ptr_loc(a)), b);
+  half_atomic_link(net, ptr_new(VR2, 0, ptr_loc(a)), b);
+}
+
+static void mtch(Net* net, Ptr a, Ptr b) {
+  net->rwts.oper += 1;
+  Ptr a1 = ptr_new(VR1, 0, ptr_loc(a));
+  Ptr a2 = ptr_new(VR2, 0, ptr_loc(a));
+  if (ptr_val(b) == 0) {
+    Loc loc0 = alloc_node(net);
+    link(net, ptr_new(VR2, 0, loc0), ERAS);
+    half_atomic_link(net, a1, ptr_new(LAM, 0, loc0));
+    half_atomic_link(net, a2, ptr_new(VR1, 0, loc0));
+  } else {
+    Loc loc0 = alloc_node(net);
+    Loc loc1 = alloc_node(net);
+    link(net, ptr_new(VR1, 0, loc0), ERAS);
+    link(net, ptr_new(VR2, 0, loc0), ptr_new(LAM, 0, loc1));
+    link(net, ptr_new(VR1, 0, loc1), ptr_big(NUM, ptr_val(b) - 1));
+    half_atomic_link(net, a1, ptr_new(LAM, 0, loc0));
+    half_atomic_link(net, a2, ptr_new(VR2, 0, loc1));
+  }
+}
+
+static void op2n(Net* net, Ptr a, Ptr b) {
+  net->rwts.oper += 1;
+  Loc loc0 = alloc_node(net);
+  heap_set(net, loc0, P1, b);
+  half_atomic_link(net, ptr_new(VR2, 0, ptr_loc(a)), ptr_new(VR2, 0, loc0));
+  half_atomic_link(net, ptr_new(VR1, 0, ptr_loc(a)), ptr_new(OP1, ptr_lab(a), loc0));
+}
+
+static void op1n(Net* net, Ptr a, Ptr b) {
+  net->rwts.oper += 1;
+  Val v0 = ptr_val(heap_get(net, ptr_loc(a), P1));
+  Val v1 = ptr_val(b);
+  Val v2 = u60_op(ptr_lab(a), v0, v1);
+  heap_set(net, ptr_loc(a), P1, NUL);
+  half_atomic_link(net, ptr_new(VR2, 0, ptr_loc(a)), ptr_big(NUM, v2));
+}
+
+static void call(Net* net, Ptr ptr, Ptr trg) {
+  net->rwts.dref += 1;
+  call_ref(net, ptr, trg);
+}
+
+static void interact(Net* net, Ptr a, Ptr b) {
+  Tag at = ptr_tag(a);
+  Tag bt = ptr_tag(b);
+  if      (at == REF && bt >= OP2) { call(net, a, b); }
+  else if (at >= OP2 && bt == REF) { call(net, b, a); }
+  else if (at >= LAM && bt >= LAM) { if (ptr_lab(a) == ptr_lab(b)) { anni(net, a, b); } else { comm(net, a, b); } }
+  else if (at >= LAM && bt == ERA) { era2(net, a); }
+  else if (at == ERA && bt >= LAM) { era2(net, b); }
+  else if (at >= LAM && bt == NUM) { copy(net, a, b); }
+  else if (at == NUM && bt >= LAM) { copy(net, b, a); }
+  else if (at == OP2 && bt == NUM) { op2n(net, a, b); }
+  else if (at == NUM && bt == OP2) { op2n(net, b, a); }
+  else if (at == OP1 && bt == NUM) { op1n(net, a, b); }
+  else if (at == NUM && bt == OP1) { op1n(net, b, a); }
+  else if (at == OP2 && bt >= LAM) { comm(net, a, b); }
+  else if (at >= LAM && bt == OP2) { comm(net, b, a); }
+  else if (at == OP1 && bt >= LAM) { pass(net, a, b); }
+  else if (at >= LAM && bt == OP1) { pass(net, b, a); }
+  else if (at == OP2 && bt == ERA) { era2(net, a); }
+  else if (at == ERA && bt == OP2) { era2(net, b); }
+  else if (at == OP1 && bt == ERA) { era1(net, a); }
+  else if (at == ERA && bt == OP1) { era1(net, b); }
+  else if (at == MAT && bt == NUM) { mtch(net, a, b); }
+  else if (at == NUM && bt == MAT) { mtch(net, b, a); }
+  else if (at == MAT && bt >= LAM) { comm(net, a, b); }
+  else if (at >= LAM && bt == MAT) { comm(net, b, a); }
+  else if (at == MAT && bt == ERA) { era2(net, a); }
+  else if (at == ERA && bt == MAT) { era2(net, b); }
+  else if ((at == REF || at == ERA || at == NUM) && (bt == REF || bt == ERA || bt == NUM)) { net->rwts.eras += 1; }
+  else {
+    fprintf(stderr, "Invalid interaction: %016llx ~ %016llx\n", (unsigned long long)a, (unsigned long long)b);
+    exit(1);
+  }
+}
+
+static void expand_go(Net* net, Ptr dir) {
+  Ptr ptr = get_target(net, dir);
+  if (is_ctr(ptr)) {
+    expand_go(net, ptr_new(VR1, 0, ptr_loc(ptr)));
+    expand_go(net, ptr_new(VR2, 0, ptr_loc(ptr)));
+  } else if (is_ref(ptr)) {
+    Ptr got = swap_target(net, dir, LOCK);
+    if (got != LOCK) {
+      call(net, ptr, dir);
+    }
+  }
+}
+
+static void normal(Net* net) {
+  expand_go(net, ROOT);
+  while (net->rlen > 0) {
+    while (net->rlen > 0) {
+      Redex r = net->rdex[--net->rlen];
+      interact(net, r.a, r.b);
+    }
+    expand_go(net, ROOT);
+  }
+}
+
+// Readback
+// --------
+
+static const char* def_name(Val val); // generated with the book
+
+static const char* val_to_name(Val val) {
+  const char* name = def_name(val);
+  if (name) {
+    return name;
+  }
+  static char buf[16];
+  char tmp[16];
+  int len = 0;
+  while (val > 0) {
+    uint8_t d = val % 64;
+    tmp[len++] = d < 10 ? '0' + d : d < 36 ? 'A' + d - 10 : d < 62 ? 'a' + d - 36 : d == 62 ? '_' : '.';
+    val /= 64;
+  }
+  for (int i = 0; i < len; ++i) {
+    buf[i] = tmp[len - 1 - i];
+  }
+  buf[len] = 0;
+  return buf;
+}
+
+static const char* show_opr(Lab opr) {
+  static const char* OPRS[] = { "+", "-", "*", "/", "%", "==", "!=", "<", ">", "<=", ">=", "&&", "||", "^", "<<", ">>", "!" };
+  return opr <= NOT ? OPRS[opr] : "?";
+}
+
+typedef struct {
+  uint32_t* vars; // fresh name + 1 of each port, 0 if unnamed
+  uint64_t fresh;
+} Readback;
+
+static void show_var(uint64_t num) {
+  char tmp[16];
+  int len = 0;
+  num += 1;
+  while (num > 0) {
+    num -= 1;
+    tmp[len++] = 'a' + num % 26;
+    num /= 26;
+  }
+  while (len > 0) {
+    putchar(tmp[--len]);
+  }
+}
+
+static void show_tree(Net* net, Readback* rb, Ptr ptr, uint64_t parent) {
+  Loc loc = ptr_loc(ptr);
+  switch (ptr_tag(ptr)) {
+    case ERA: printf("*"); break;
+    case REF: printf("@%s", val_to_name(ptr_val(ptr))); break;
+    case NUM: printf("#%llu", (unsigned long long)ptr_val(ptr)); break;
+    case VR1: case VR2: {
+      uint64_t key = (uint64_t)loc * 2 + (ptr_tag(ptr) == VR1 ? P1 : P2);
+      if (rb->vars[key] == 0) {
+        rb->vars[parent] = (uint32_t)(++rb->fresh);
+        show_var(rb->fresh - 1);
+      } else {
+        show_var(rb->vars[key] - 1);
+      }
+      break;
+    }
+    case OP1: {
+      printf("<%llu%s ", (unsigned long long)ptr_val(heap_get(net, loc, P1)), show_opr(ptr_lab(ptr)));
+      show_tree(net, rb, heap_get(net, loc, P2), (uint64_t)loc * 2 + P2);
+      printf(">");
+      break;
+    }
+    case OP2: case MAT: case LAM: case TUP: case DUP: {
+      Tag tag = ptr_tag(ptr);
+      if (tag == OP2) { printf("<%s ", show_opr(ptr_lab(ptr))); }
+      if (tag == MAT) { printf("?<"); }
+      if (tag == LAM) { printf("("); }
+      if (tag == TUP) { printf("["); }
+      if (tag == DUP) { printf("{%u ", ptr_lab(ptr)); }
+      show_tree(net, rb, heap_get(net, loc, P1), (uint64_t)loc * 2 + P1);
+      printf(" ");
+      show_tree(net, rb, heap_get(net, loc, P2), (uint64_t)loc * 2 + P2);
+      printf(tag == LAM ? ")" : tag == TUP ? "]" : tag == DUP ? "}" : ">");
+      break;
+    }
+    default: printf("???"); break;
+  }
+}
+
+static void show_net(Net* net) {
+  Readback rb = { calloc(net->size * 2, sizeof(uint32_t)), 0 };
+  show_tree(net, &rb, heap_get(net, 0, P2), P2);
+  for (uint64_t i = 0; i < net->rlen; ++i) {
+    printf("\n& ");
+    show_tree(net, &rb, net->rdex[i].a, 0);
+    printf(" ~ ");
+    show_tree(net, &rb, net->rdex[i].b, 0);
+  }
+  printf("\n");
+  free(rb.vars);
+}
+"##;
+
+const MAIN: &str = r##"// Main
+// ----
+
+int main(int argc, char** argv) {
+  bool stats = false;
+  for (int i = 1; i < argc; ++i) {
+    if (strcmp(argv[i], "-s") == 0) { stats = true; }
+  }
+  Net net = { 0 };
+  net.size = HVMC_HEAP_SIZE;
+  net.heap = calloc(net.size * 2, sizeof(Ptr));
+  if (!net.heap) {
+    fprintf(stderr, "Failed to allocate the heap\n");
+    return 1;
+  }
+  heap_set(&net, 0, P2, ptr_big(REF, ID_main));
+  struct timespec begin, end;
+  timespec_get(&begin, TIME_UTC);
+  normal(&net);
+  timespec_get(&end, TIME_UTC);
+  show_net(&net);
+  if (stats) {
+    double time = (double)(end.tv_sec - begin.tv_sec) + (double)(end.tv_nsec - begin.tv_nsec) / 1e9;
+    uint64_t total = net.rwts.anni + net.rwts.comm + net.rwts.eras + net.rwts.dref + net.rwts.oper;
+    printf("RWTS   : %llu\n", (unsigned long long)total);
+    printf("- ANNI : %llu\n", (unsigned long long)net.rwts.anni);
+    printf("- COMM : %llu\n", (unsigned long long)net.rwts.comm);
+    printf("- ERAS : %llu\n", (unsigned long long)net.rwts.eras);
+    printf("- DREF : %llu\n", (unsigned long long)net.rwts.dref);
+    printf("- OPER : %llu\n", (unsigned long long)net.rwts.oper);
+    printf("TIME   : %.3f s\n", time);
+    printf("RPS    : %.3f m\n", (double)total / time / 1000000.0);
+  }
+  free(net.heap);
+  free(net.rdex);
+  return 0;
+}
+"##;
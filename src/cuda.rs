@@ -0,0 +1,233 @@
+// Book tables for the CUDA runtime
+// --------------------------------
+// The CUDA runtime ('cuda/runtime.cu') uses 32-bit pointers (4-bit tag + 28-bit val) and loads
+// definitions from two flat tables: BOOK_DATA, holding each definition's nodes and redexes, and
+// JUMP_DATA, mapping each definition id to its offset on BOOK_DATA. This file re-encodes a runtime
+// book into these tables, rejecting books that don't fit the runtime's limits, and emits them as C
+// source, JSON or a binary blob, so that the GPU host can load them without recompiling.
+
+use crate::run;
+use crate::ast;
+
+pub type Tag = u8;
+pub type Val = u32;
+pub type Ptr = u32;
+
+// Pointer tags, as defined on 'runtime.cu'.
+pub const VR1: Tag = 0x0; // variable to aux port 1
+pub const VR2: Tag = 0x1; // variable to aux port 2
+pub const RD1: Tag = 0x2; // redirect to aux port 1
+pub const RD2: Tag = 0x3; // redirect to aux port 2
+pub const REF: Tag = 0x4; // lazy closed net
+pub const ERA: Tag = 0x5; // unboxed eraser
+pub const NUM: Tag = 0x6; // unboxed number
+pub const OP2: Tag = 0x7; // numeric operation binary
+pub const OP1: Tag = 0x8; // numeric operation unary
+pub const ITE: Tag = 0x9; // numeric if-then-else
+pub const CT0: Tag = 0xA; // main port of con node 0
+pub const CT5: Tag = 0xF; // main port of con node 5
+
+// Runtime limits, as defined on 'runtime.cu'.
+pub const VAL_SIZE_L2 : u32 = 28; // pointer val width
+pub const HEAP_SIZE_L2: u32 = 28; // max total nodes (256m)
+pub const JUMP_SIZE_L2: u32 = 24; // max book entries (16m)
+pub const VAL_SIZE    : u64 = 1 << VAL_SIZE_L2;
+pub const HEAP_SIZE   : u64 = 1 << HEAP_SIZE_L2;
+pub const JUMP_SIZE   : u64 = 1 << JUMP_SIZE_L2;
+
+// Magic number of the binary format ("HVMC", little-endian).
+pub const MAGIC: u32 = 0x434D5648;
+
+// A definition entry of the jump table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Jump {
+  pub name: String, // definition name
+  pub fid : Val, // definition id
+  pub init: Val, // offset on the book data
+}
+
+// The book tables, in the format expected by the CUDA runtime.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Book {
+  pub book: Vec<u32>, // BOOK_DATA
+  pub jump: Vec<Jump>, // JUMP_DATA
+}
+
+#[inline(always)]
+pub const fn mkptr(tag: Tag, val: Val) -> Ptr {
+  (val << 4) | (tag as Val)
+}
+
+#[inline(always)]
+pub const fn tag(ptr: Ptr) -> Tag {
+  (ptr & 0xF) as Tag
+}
+
+#[inline(always)]
+pub const fn val(ptr: Ptr) -> Val {
+  ptr >> 4
+}
+
+// Re-encodes a runtime pointer as a CUDA pointer.
+pub fn encode_ptr(ptr: run::Ptr, names: &run::Names) -> Result<Ptr, String> {
+  let loc = || -> Result<Val, String> {
+    if (ptr.loc() as u64) < VAL_SIZE {
+      Ok(ptr.loc())
+    } else {
+      Err(format!("location 0x{:x} doesn't fit in {} bits", ptr.loc(), VAL_SIZE_L2))
+    }
+  };
+  match ptr.tag() {
+    run::VR1 => Ok(mkptr(VR1, loc()?)),
+    run::VR2 => Ok(mkptr(VR2, loc()?)),
+    run::RD1 => Ok(mkptr(RD1, loc()?)),
+    run::RD2 => Ok(mkptr(RD2, loc()?)),
+    run::REF => {
+      if ptr.val() < JUMP_SIZE {
+        Ok(mkptr(REF, ptr.val() as Val))
+      } else {
+        Err(format!("reference @{} has id 0x{:x}, which doesn't fit the {}-bit jump table", ast::show_name(ptr.val(), &[names]), ptr.val(), JUMP_SIZE_L2))
+      }
+    }
+    run::ERA => Ok(mkptr(ERA, 0)),
+    run::NUM => {
+      if ptr.val() < VAL_SIZE {
+        Ok(mkptr(NUM, ptr.val() as Val))
+      } else {
+        Err(format!("number #{} doesn't fit in {} bits", ptr.val(), VAL_SIZE_L2))
+      }
+    }
+    run::OP2 | run::OP1 => {
+      Err(format!("numeric operation '{}' can't be encoded, since CUDA pointers have no room for operators", ast::show_opr(ptr.lab())))
+    }
+    run::MAT => Ok(mkptr(ITE, loc()?)),
+    run::LAM | run::TUP | run::DUP => {
+      // On CUDA, the constructor label is stored on the tag. CT5 isn't treated as a constructor.
+      if (ptr.lab() as u64) < (CT5 - CT0) as u64 {
+        Ok(mkptr(CT0 + ptr.lab() as Tag, loc()?))
+      } else {
+        Err(format!("constructor label {} is too large, since only labels 0 to {} are supported", ptr.lab(), CT5 - CT0 - 1))
+      }
+    }
+    _ => Err(format!("invalid pointer {}", ptr.view())),
+  }
+}
+
+impl Book {
+  // Builds the CUDA tables of a runtime book.
+  pub fn new(book: &run::Book) -> Result<Self, String> {
+    // Sort the book.defs by key
+    let mut defs = book.defs.iter().filter(|(_, def)| def.node.len() > 0).collect::<Vec<_>>();
+    defs.sort_by_key(|(fid, _)| **fid);
+
+    if defs.len() as u64 > JUMP_SIZE {
+      return Err(format!("book has {} definitions, but the jump table holds at most {}", defs.len(), JUMP_SIZE));
+    }
+
+    let mut data = vec![];
+    let mut jump = vec![];
+    for (fid, def) in defs {
+      let name = ast::show_name(*fid, &[&book.names]);
+      if *fid >= JUMP_SIZE {
+        return Err(format!("@{}: id 0x{:x} doesn't fit the {}-bit jump table", name, fid, JUMP_SIZE_L2));
+      }
+      if def.node.len() as u64 > HEAP_SIZE {
+        return Err(format!("@{}: has {} nodes, but the heap holds at most {}", name, def.node.len(), HEAP_SIZE));
+      }
+      if data.len() as u64 > u32::MAX as u64 {
+        return Err(format!("@{}: book data exceeds {} words", name, u32::MAX));
+      }
+      let init = data.len() as Val;
+      let ptrs = def.node.iter().flat_map(|(_, p1, p2)| [*p1, *p2]).chain(def.rdex.iter().flat_map(|(a, b)| [*a, *b]));
+      data.push(def.node.len() as u32);
+      data.push(def.rdex.len() as u32);
+      for ptr in ptrs {
+        data.push(encode_ptr(ptr, &book.names).map_err(|err| format!("@{}: {}", name, err))?);
+      }
+      jump.push(Jump { name, fid: *fid as Val, init });
+    }
+
+    return Ok(Book { book: data, jump });
+  }
+
+  // Finds the offset of a definition on the book data.
+  pub fn get(&self, fid: Val) -> Option<Val> {
+    self.jump.iter().find(|jump| jump.fid == fid).map(|jump| jump.init)
+  }
+
+  // Emits the tables as C source, to be included by 'runtime_standalone.cu'.
+  pub fn to_c(&self) -> String {
+    let mut code = String::new();
+
+    // Generate function ids, named like the ones 'jit.rs' and 'c.rs' emit
+    for jump in &self.jump {
+      code.push_str(&format!("const u32 F_{} = 0x{:x};\n", ast::val_to_ident(jump.fid as run::Val), jump.fid));
+    }
+    code.push_str("\n");
+
+    // Generate book data
+    code.push_str("u32 BOOK_DATA[] = {\n");
+    for jump in &self.jump {
+      let init = jump.init as usize;
+      let nlen = self.book[init + 0] as usize;
+      let rlen = self.book[init + 1] as usize;
+      code.push_str(&format!("  // @{}\n", jump.name));
+      code.push_str(&format!("  // .nlen\n"));
+      code.push_str(&format!("  0x{:08X},\n", nlen));
+      code.push_str(&format!("  // .rlen\n"));
+      code.push_str(&format!("  0x{:08X},\n", rlen));
+      code.push_str("  // .node\n");
+      code.push_str(&show_words(&self.book[init + 2 .. init + 2 + nlen * 2]));
+      code.push_str("  // .rdex\n");
+      code.push_str(&show_words(&self.book[init + 2 + nlen * 2 .. init + 2 + nlen * 2 + rlen * 2]));
+    }
+    code.push_str("};\n\n");
+
+    // Generate jump table
+    code.push_str("u32 JUMP_DATA[] = {\n");
+    for jump in &self.jump {
+      code.push_str(&format!("  0x{:08X}, 0x{:08X}, // @{}\n", jump.fid, jump.init, jump.name));
+    }
+    code.push_str("};");
+
+    return code;
+  }
+
+  // Emits the tables as JSON.
+  pub fn to_json(&self) -> String {
+    let jump = self.jump.iter().map(|jump| {
+      format!("    {{ \"name\": \"{}\", \"fid\": {}, \"init\": {} }}", jump.name, jump.fid, jump.init)
+    }).collect::<Vec<_>>();
+    let book = self.book.iter().map(|word| word.to_string()).collect::<Vec<_>>();
+    let mut json = String::new();
+    json.push_str("{\n");
+    json.push_str(&format!("  \"jump\": [\n{}\n  ],\n", jump.join(",\n")));
+    json.push_str(&format!("  \"book\": [{}]\n", book.join(", ")));
+    json.push_str("}");
+    return json;
+  }
+
+  // Emits the tables as a little-endian binary blob:
+  // MAGIC, jump length, book length, JUMP_DATA (fid, init pairs), BOOK_DATA.
+  pub fn to_bin(&self) -> Vec<u8> {
+    let mut words = vec![MAGIC, self.jump.len() as u32, self.book.len() as u32];
+    for jump in &self.jump {
+      words.push(jump.fid);
+      words.push(jump.init);
+    }
+    words.extend_from_slice(&self.book);
+    return words.iter().flat_map(|word| word.to_le_bytes()).collect();
+  }
+}
+
+// Shows a slice of pointers as pairs, four pairs per line.
+fn show_words(words: &[u32]) -> String {
+  let mut code = String::new();
+  for (i, pair) in words.chunks(2).enumerate() {
+    code.push_str(&format!("  0x{:08X}, 0x{:08X},", pair[0], pair[1]));
+    if (i + 1) % 4 == 0 || i + 1 == words.len() / 2 {
+      code.push_str("\n");
+    }
+  }
+  return code;
+}
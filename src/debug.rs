@@ -0,0 +1,233 @@
+// Step debugger
+// -------------
+// A small REPL-style debugger for eager runtime nets. It steps interactions one at a time through
+// 'NetFields::reduce', expanding the root heads when no redexes are left (as 'normal' does), and
+// can list pending redexes, print the current net, inspect a node by location, and stop before
+// the dereference of a named definition.
+
+use crate::ast;
+use crate::run::{self, Book, Loc, NetFields, Ptr, Val};
+use std::collections::BTreeSet;
+
+pub struct Debugger<'a> {
+  pub net  : NetFields<'a, false>, // the net being debugged
+  pub book : &'a Book, // definitions
+  pub brks : BTreeSet<Val>, // breakpoints (definition ids)
+  pub steps: usize, // steps taken so far
+  pub held : bool, // stopped at a breakpoint
+}
+
+// Why a run of steps stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Stop {
+  Limit, // took the requested steps
+  Break(Val), // next step dereferences a breakpoint
+  Normal, // net is in normal form
+}
+
+pub const HELP: &str = "\
+Commands:
+  s, step [N]      Step N interactions (default 1)
+  c, continue      Step until a breakpoint or normal form
+  r, redexes       List pending redexes, next first
+  n, net           Show the current net
+  i, inspect LOC   Show the node at the given (hex) location
+  b, break NAME    Break before dereferencing @NAME
+  d, delete NAME   Remove the breakpoint on @NAME
+  l, breaks        List breakpoints
+  t, stats         Show step and rewrite counts
+  k, check         Check the heap's integrity
+  h, help          Show this message
+  q, quit          Exit the debugger";
+
+// Allocates an eager heap for a debugger to run on.
+pub fn alloc_nodes(size: usize) -> Box<run::Nodes<false>> {
+  run::Heap::<false>::init(size)
+}
+
+impl<'a> Debugger<'a> {
+  // Creates a debugger for a net booted from the given definition.
+  pub fn new(nodes: &'a run::Nodes<false>, book: &'a Book, root: Val) -> Self {
+    let net = NetFields::new(nodes);
+    net.boot(root);
+    Debugger { net, book, brks: BTreeSet::new(), steps: 0, held: false }
+  }
+
+  // The REFs that the next step will dereference.
+  pub fn next_derefs(&self) -> Vec<Ptr> {
+    match self.net.rdex.last() {
+      Some(&(a, b)) => {
+        if a.is_ref() && b.is_nod() {
+          vec![a]
+        } else if a.is_nod() && b.is_ref() {
+          vec![b]
+        } else {
+          vec![]
+        }
+      }
+      None => self.heads(),
+    }
+  }
+
+  // The REF heads that 'expand' would dereference, on a single thread.
+  pub fn heads(&self) -> Vec<Ptr> {
+    let mut heads = vec![];
+    let mut visit = vec![run::ROOT];
+    while let Some(dir) = visit.pop() {
+      let ptr = self.net.get_target(dir);
+      if ptr.is_ctr() {
+        visit.push(Ptr::new(run::VR2, 0, ptr.loc()));
+        visit.push(Ptr::new(run::VR1, 0, ptr.loc()));
+      } else if ptr.is_ref() {
+        heads.push(ptr);
+      }
+    }
+    return heads;
+  }
+
+  // Checks if the net is in normal form.
+  pub fn is_normal(&self) -> bool {
+    self.net.rdex.is_empty() && self.heads().is_empty()
+  }
+
+  // Performs a single step: an interaction, or an expansion if there are no redexes.
+  pub fn step(&mut self) -> bool {
+    if self.net.rdex.is_empty() {
+      if self.heads().is_empty() {
+        return false;
+      }
+      self.net.expand(self.book);
+    } else {
+      self.net.reduce(self.book, 1);
+    }
+    self.steps += 1;
+    return true;
+  }
+
+  // Steps up to 'limit' times, stopping before breakpoints. When resuming from a breakpoint, the
+  // first step is taken without checking, so that we don't stop at the same place again.
+  pub fn run(&mut self, limit: usize) -> Stop {
+    for _ in 0 .. limit {
+      if !self.held {
+        if let Some(brk) = self.next_derefs().iter().find(|ptr| self.brks.contains(&ptr.val())) {
+          self.held = true;
+          return Stop::Break(brk.val());
+        }
+      }
+      self.held = false;
+      if !self.step() {
+        return Stop::Normal;
+      }
+    }
+    return if self.is_normal() { Stop::Normal } else { Stop::Limit };
+  }
+
+  // The current net, read back from the heap.
+  pub fn to_net(&self) -> ast::Net {
+    ast::net_from_runtime(&self.net)
+  }
+
+  pub fn show_stop(&self, stop: &Stop) -> String {
+    match stop {
+      Stop::Limit      => format!("Stopped at step {}.", self.steps),
+      Stop::Break(fid) => format!("Breakpoint @{} hit at step {}.", ast::show_name(*fid, &[&self.book.names]), self.steps),
+      Stop::Normal     => format!("Normal form reached at step {}.", self.steps),
+    }
+  }
+
+  pub fn show_redexes(&self) -> String {
+    if self.net.rdex.is_empty() {
+      let heads = self.heads().iter().map(|ptr| ptr.view()).collect::<Vec<_>>();
+      if heads.is_empty() {
+        return format!("No redexes.");
+      }
+      return format!("No redexes. Next step expands: {}", heads.join(" "));
+    }
+    let mut text = String::new();
+    for (i, (a, b)) in self.net.rdex.iter().rev().enumerate() {
+      text.push_str(&format!("{:>6} {} ~ {}\n", i, a.view(), b.view()));
+    }
+    text.pop();
+    return text;
+  }
+
+  pub fn show_node(&self, loc: Loc) -> Result<String, String> {
+    if loc as usize >= self.net.heap.nodes.len() {
+      return Err(format!("location {:x} is out of bounds", loc));
+    }
+    let p1 = self.net.heap.get(loc, run::P1);
+    let p2 = self.net.heap.get(loc, run::P2);
+    let mut text = format!("{:08x} | {} {}", loc, p1.view(), p2.view());
+    for (port, ptr) in [("P1", p1), ("P2", p2)] {
+      if ptr.is_pri() {
+        text.push_str(&format!("\n  {}: {}", port, ast::show_runtime_tree(&self.net, ptr, &self.book.names)));
+      }
+    }
+    return Ok(text);
+  }
+
+  pub fn show_stats(&self) -> String {
+    let rwts = self.net.rwts;
+    format!("STEP: {} | RWTS: {} (ANNI {}, COMM {}, ERAS {}, DREF {}, OPER {}) | RDEX: {}",
+      self.steps, rwts.total(), rwts.anni, rwts.comm, rwts.eras, rwts.dref, rwts.oper, self.net.rdex.len())
+  }
+
+  // Runs a debugger command, returning its output, or None to quit.
+  pub fn exec(&mut self, line: &str) -> Option<Result<String, String>> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let arg = words.get(1).copied();
+    let name = || arg.map(|name| name.trim_start_matches('@')).ok_or_else(|| format!("missing definition name"));
+    let out = match words.first().copied().unwrap_or("") {
+      "" => Ok(String::new()),
+      "s" | "step" => {
+        match arg.map(|n| n.parse::<usize>()).unwrap_or(Ok(1)) {
+          Ok(limit) => { let stop = self.run(limit); Ok(self.show_stop(&stop)) }
+          Err(_)    => Err(format!("invalid step count '{}'", arg.unwrap())),
+        }
+      }
+      "c" | "continue" => {
+        let stop = self.run(usize::MAX);
+        Ok(self.show_stop(&stop))
+      }
+      "r" | "redexes" => Ok(self.show_redexes()),
+      "n" | "net" => Ok(ast::show_runtime_net(&self.net, &self.book.names)),
+      "i" | "inspect" => {
+        match arg.map(|loc| Loc::from_str_radix(loc.trim_start_matches("0x"), 16)) {
+          Some(Ok(loc)) => self.show_node(loc),
+          Some(Err(_))  => Err(format!("invalid location '{}'", arg.unwrap())),
+          None          => Err(format!("missing location")),
+        }
+      }
+      "b" | "break" => name().and_then(|name| {
+        let fid = ast::name_to_val(name);
+        if !self.book.defs.contains_key(&fid) {
+          return Err(format!("unknown definition @{}", name));
+        }
+        self.brks.insert(fid);
+        Ok(format!("Breakpoint set on @{}.", name))
+      }),
+      "d" | "delete" => name().and_then(|name| {
+        if self.brks.remove(&ast::name_to_val(name)) {
+          Ok(format!("Breakpoint on @{} removed.", name))
+        } else {
+          Err(format!("no breakpoint on @{}", name))
+        }
+      }),
+      "l" | "breaks" => {
+        if self.brks.is_empty() {
+          Ok(format!("No breakpoints."))
+        } else {
+          Ok(self.brks.iter().map(|fid| format!("@{}", ast::show_name(*fid, &[&self.book.names]))).collect::<Vec<_>>().join("\n"))
+        }
+      }
+      "t" | "stats" => Ok(self.show_stats()),
+      "k" | "check" => {
+        self.net.check_integrity(self.is_normal()).map(|nodes| format!("Heap is consistent ({} reachable nodes).", nodes))
+      }
+      "h" | "help" => Ok(HELP.to_string()),
+      "q" | "quit" => return None,
+      cmd => Err(format!("unknown command '{}' (try 'help')", cmd)),
+    };
+    return Some(out);
+  }
+}
@@ -0,0 +1,146 @@
+// Formatter
+// ---------
+// Prints a .hvmc file canonically. Definitions and imports keep their order and variable names,
+// each redex goes on its own line, and trees that don't fit the width are broken across indented
+// lines. Comments are kept: one that ends the last line of a definition's root or redex stays at
+// the end of that line, and any other is moved before the root or redex it is in, or precedes.
+// Blank lines between top-level items are kept, collapsed to one.
+
+use crate::ast::{self, Item, Net, Tree};
+use crate::run;
+
+pub const WIDTH: usize = 100;
+
+// A comment, with the line it's on and whether code precedes it on that line.
+struct Comment {
+  line: usize,
+  text: String,
+  trailing: bool,
+}
+
+// A definition's root, a redex, or an import, with its first and last lines.
+struct Part {
+  item: usize, // index of its item
+  index: usize, // 0 for the root, i + 1 for the i-th redex
+  init: usize, // first line
+  done: usize, // last line
+}
+
+// Formats a file, with lines of at most 'width' characters where possible.
+pub fn format(code: &str, width: usize) -> Result<String, String> {
+  let (items, comments) = ast::try_parse_source(code)?;
+
+  // Finds the lines of each part, and of each comment, counting from 0.
+  let starts = std::iter::once(0).chain(code.match_indices('\n').map(|(i, _)| i + 1)).collect::<Vec<_>>();
+  let line_of = |pos: usize| starts.partition_point(|start| *start <= pos) - 1;
+  let mut parts = vec![];
+  for (item, got) in items.iter().enumerate() {
+    let spans = match got {
+      Item::Import(import) => vec![import.span],
+      Item::Def(_, net) => {
+        let meta = net.meta.as_ref().unwrap();
+        std::iter::once(meta.span.to(meta.root)).chain(meta.rdex.iter().copied()).collect()
+      }
+    };
+    for (index, span) in spans.into_iter().enumerate() {
+      parts.push(Part { item, index, init: line_of(span.init), done: line_of(span.done - 1) });
+    }
+  }
+  let comments = comments.into_iter().map(|comment| {
+    let line = line_of(comment.span.init);
+    let trailing = !code[starts[line] .. comment.span.init].trim().is_empty();
+    Comment { line, text: comment.text, trailing }
+  }).collect::<Vec<_>>();
+
+  // Assigns each comment to a part: before it, or at the end of its last line.
+  let mut leading = parts.iter().map(|_| vec![]).collect::<Vec<Vec<&Comment>>>();
+  let mut trailing = parts.iter().map(|_| None).collect::<Vec<Option<&Comment>>>();
+  let mut tail = vec![];
+  for comment in &comments {
+    let after = parts.iter().rposition(|part| part.init <= comment.line);
+    match after {
+      Some(k) if comment.trailing && parts[k].done == comment.line => trailing[k] = Some(comment),
+      _ => match parts.iter().position(|part| part.done >= comment.line) {
+        Some(k) => leading[k].push(comment),
+        None    => tail.push(comment),
+      },
+    }
+  }
+
+  // Renders the parts, keeping blank lines that precede top-level items.
+  let lines = code.lines().collect::<Vec<_>>();
+  let mut out: Vec<String> = vec![];
+  let gap = |out: &mut Vec<String>, line: usize| {
+    if line > 0 && lines.get(line - 1).map_or(false, |prev| prev.trim().is_empty()) && out.last().map_or(false, |last| !last.is_empty()) {
+      out.push(String::new());
+    }
+  };
+  for (k, part) in parts.iter().enumerate() {
+    let tab = if part.index == 0 { 0 } else { 2 };
+    for comment in &leading[k] {
+      if tab == 0 {
+        gap(&mut out, comment.line);
+      }
+      out.push(format!("{}{}", " ".repeat(tab), comment.text));
+    }
+    if tab == 0 {
+      gap(&mut out, part.init);
+    }
+    let none = run::Names::default();
+    let mut show = match &items[part.item] {
+      Item::Import(import) => match &import.name {
+        Some(name) => format!("import \"{}\" as {}", import.path, name),
+        None       => format!("import \"{}\"", import.path),
+      },
+      Item::Def(name, net) if part.index == 0 => {
+        let head = format!("@{} = ", name);
+        format!("{}{}", head, show_tree(&net.root, refs(net, &none), 0, head.chars().count(), width))
+      }
+      Item::Def(_, net) => {
+        let (a, b) = &net.rdex[part.index - 1];
+        let a = show_tree(a, refs(net, &none), 2, 4, width);
+        let col = a.rsplit('\n').next().unwrap().chars().count() + if a.contains('\n') { 3 } else { 7 };
+        format!("  & {} ~ {}", a, show_tree(b, refs(net, &none), 2, col, width))
+      }
+    };
+    if let Some(comment) = trailing[k] {
+      show.push(' ');
+      show.push_str(&comment.text);
+    }
+    out.extend(show.split('\n').map(String::from));
+  }
+  for comment in tail {
+    gap(&mut out, comment.line);
+    out.push(comment.text.clone());
+  }
+  return Ok(out.into_iter().map(|line| line + "\n").collect());
+}
+
+// The names of a parsed net's interned references.
+fn refs<'a>(net: &'a Net, none: &'a run::Names) -> &'a run::Names {
+  net.meta.as_ref().map_or(none, |meta| &meta.refs)
+}
+
+// Shows a tree, breaking it across lines indented from 'tab' if it doesn't fit from column 'col'.
+// Interned references are named from 'names'.
+pub fn show_tree(tree: &Tree, names: &run::Names, tab: usize, col: usize, width: usize) -> String {
+  let flat = ast::show_tree_in(tree, names);
+  if col + flat.chars().count() <= width {
+    return flat;
+  }
+  let (open, kids, close) = match tree {
+    Tree::Con { lft, rgt } => ("(".to_string(), vec![lft, rgt], ")"),
+    Tree::Tup { lft, rgt } => ("[".to_string(), vec![lft, rgt], "]"),
+    Tree::Dup { lab, lft, rgt } => (format!("{{{}", lab), vec![lft, rgt], "}"),
+    Tree::Op1 { opr, lft, rgt } => (format!("<{}{}", lft, ast::show_opr(*opr)), vec![rgt], ">"),
+    Tree::Op2 { opr, lft, rgt } => (format!("<{}", ast::show_opr(*opr)), vec![lft, rgt], ">"),
+    Tree::Mat { sel, ret } => ("?<".to_string(), vec![sel, ret], ">"),
+    Tree::Era | Tree::Var { .. } | Tree::Ref { .. } | Tree::Num { .. } => return flat,
+  };
+  let mut text = open;
+  for kid in kids {
+    text.push_str(&format!("\n{}{}", " ".repeat(tab + 2), show_tree(kid, names, tab + 2, tab + 2, width)));
+  }
+  text.push_str(&format!("\n{}{}", " ".repeat(tab), close));
+  return text;
+}
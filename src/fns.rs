@@ -1,5 +1,8 @@
 use crate::run::{*};
 
+pub const NAMES : &[(Val, &str)] = &[
+];
+
 impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
   pub fn call_native(&mut self, book: &Book, ptr: Ptr, x: Ptr) -> bool {
     match ptr.loc() {
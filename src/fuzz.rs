@@ -0,0 +1,396 @@
+// Fuzzing
+// -------
+// Generates random books that are well-formed and terminate, and checks properties of the runtime
+// on them: that parallel normalization agrees with the sequential one on any number of threads, and
+// that books are unchanged by a round-trip through the printer and the parser.
+//
+// A generated net has two parts that never meet. The numeric part is made of chains of OP2 and MAT
+// nodes, whose inputs are numbers or references to numeric definitions, and whose results flow into
+// the structural part, made of ERA, CON, DUP and NUM nodes. That rules out the pairs the runtime
+// can't reduce, like REF ~ REF or OP2 ~ MAT. A definition only refers to the ones generated before
+// it, which bounds how many times references expand. Structural redexes may still loop, so each
+// book is first normalized with a fuel bound, and skipped if it runs out.
+
+use crate::ast::{self, Book, Net, Tree};
+use crate::rng::Rng;
+use crate::run::{self, NetFields};
+use crate::verify::{self, Outcome, Strategy};
+use std::collections::HashMap;
+
+// Operators that can't fail on any operands.
+const OPRS: [run::Lab; 12] = [run::ADD, run::SUB, run::MUL, run::EQ, run::NE, run::LT, run::GT, run::LTE, run::GTE, run::AND, run::OR, run::XOR];
+
+pub struct Generator {
+  pub defs: usize, // definitions besides @main
+  pub nodes: usize, // nodes per definition, roughly
+  pub labs: run::Lab, // distinct DUP labels
+}
+
+impl Default for Generator {
+  fn default() -> Self {
+    Generator { defs: 4, nodes: 24, labs: 3 }
+  }
+}
+
+// The state of a definition being generated.
+struct Builder<'a> {
+  rng: &'a mut Rng,
+  labs: run::Lab,
+  nums: &'a [String], // numeric definitions it can refer to
+  strs: &'a [String], // structural definitions it can refer to
+  left: usize, // nodes left
+  vars: usize, // variables created
+  rdex: Vec<(Tree, Tree)>,
+  ends: Vec<(String, usize)>, // unpaired structural leaves, with the component they're in
+}
+
+impl Generator {
+  // Generates a book with @main and 'defs' other definitions, each either numeric (it normalizes
+  // to a number) or structural.
+  pub fn generate(&self, rng: &mut Rng) -> Book {
+    let mut nums = vec![];
+    let mut strs = vec![];
+    let mut book = Book::new();
+    for i in 0 ..= self.defs {
+      let name = if i == self.defs { "main".to_string() } else { format!("f{}", i) };
+      let numeric = i < self.defs && rng.chance(50);
+      let mut builder = Builder { rng, labs: self.labs, nums: &nums, strs: &strs, left: self.nodes, vars: 0, rdex: vec![], ends: vec![] };
+      let net = if numeric { builder.num_def() } else { builder.str_def() };
+      book.insert(name.clone(), net);
+      if numeric { nums.push(name) } else { strs.push(name) }
+    }
+    return book;
+  }
+}
+
+impl<'a> Builder<'a> {
+  fn fresh(&mut self) -> String {
+    self.vars += 1;
+    format!("x{}", self.vars - 1)
+  }
+
+  // Spends a node, if there are any left.
+  fn spend(&mut self) -> bool {
+    if self.left > 0 {
+      self.left -= 1;
+      return true;
+    }
+    return false;
+  }
+
+  fn small(&mut self) -> Tree {
+    let val = if self.rng.chance(50) { self.rng.below(3) } else { self.rng.below(1000) };
+    Tree::Num { val: val as run::Val }
+  }
+
+  fn var(nam: &str) -> Tree {
+    Tree::Var { nam: nam.to_string() }
+  }
+
+  // Numeric part
+  // ------------
+
+  // Something that evaluates to a number, for the principal port of an operator: a number, or a
+  // reference to a numeric definition.
+  fn num_head(&mut self) -> Tree {
+    if !self.nums.is_empty() && self.rng.chance(30) {
+      return Tree::Ref { nam: ast::name_to_val(&self.nums[self.rng.below(self.nums.len())]) };
+    }
+    return self.small();
+  }
+
+  // A number, or a variable that an operator chain outputs a number to. It's safe anywhere.
+  fn num_val(&mut self) -> Tree {
+    if self.left > 0 && self.rng.chance(30) {
+      let nam = self.fresh();
+      self.num_into(Self::var(&nam));
+      return Self::var(&nam);
+    }
+    return self.small();
+  }
+
+  // Something that evaluates to a number, for the second operand of an operator.
+  fn num_arg(&mut self) -> Tree {
+    if !self.nums.is_empty() && self.rng.chance(20) {
+      return self.num_head();
+    }
+    return self.num_val();
+  }
+
+  // Adds a redex with a chain of operators, which outputs a number to 'sink'.
+  fn num_into(&mut self, sink: Tree) {
+    let mut sink = sink;
+    loop {
+      self.spend();
+      sink = if self.rng.chance(75) {
+        let opr = OPRS[self.rng.below(OPRS.len())];
+        Tree::Op2 { opr, lft: Box::new(self.num_arg()), rgt: Box::new(sink) }
+      } else {
+        self.mat(sink)
+      };
+      if self.left == 0 || self.rng.chance(50) {
+        break;
+      }
+    }
+    let head = self.num_head();
+    self.rdex.push((head, sink));
+  }
+
+  // A MAT node that outputs a number to 'sink': one on zero, and another, that may use the
+  // predecessor, otherwise.
+  fn mat(&mut self, sink: Tree) -> Tree {
+    let zero = self.num_val();
+    let succ = match self.rng.below(3) {
+      0 => Tree::Con { lft: Box::new(Tree::Era), rgt: Box::new(self.num_val()) },
+      1 => {
+        let p = self.fresh();
+        Tree::Con { lft: Box::new(Self::var(&p)), rgt: Box::new(Self::var(&p)) }
+      }
+      _ => {
+        let p = self.fresh();
+        let r = self.fresh();
+        let opr = OPRS[self.rng.below(OPRS.len())];
+        let head = self.num_head();
+        self.rdex.push((head, Tree::Op2 { opr, lft: Box::new(Self::var(&p)), rgt: Box::new(Self::var(&r)) }));
+        Tree::Con { lft: Box::new(Self::var(&p)), rgt: Box::new(Self::var(&r)) }
+      }
+    };
+    Tree::Mat { sel: Box::new(Tree::Con { lft: Box::new(zero), rgt: Box::new(succ) }), ret: Box::new(sink) }
+  }
+
+  fn num_def(&mut self) -> Net {
+    let root = if self.rng.chance(20) {
+      self.small()
+    } else {
+      let nam = self.fresh();
+      self.num_into(Self::var(&nam));
+      Self::var(&nam)
+    };
+    Net::new(root, std::mem::take(&mut self.rdex))
+  }
+
+  // Structural part
+  // ---------------
+
+  // A structural tree, in the given component. Leaves that are variables are only named once the
+  // whole net is generated, by 'pair'.
+  fn str_tree(&mut self, comp: usize) -> Tree {
+    if self.rng.chance(60) && self.spend() {
+      return self.str_node(comp);
+    }
+    match self.rng.below(10) {
+      0 ..= 5 => {
+        let nam = format!("?{}", self.ends.len());
+        self.ends.push((nam.clone(), comp));
+        Self::var(&nam)
+      }
+      6 => Tree::Era,
+      7 | 8 => self.small(),
+      _ => self.num_val(),
+    }
+  }
+
+  // A CON or DUP node.
+  fn str_node(&mut self, comp: usize) -> Tree {
+    let lft = Box::new(self.str_tree(comp));
+    let rgt = Box::new(self.str_tree(comp));
+    if self.rng.chance(50) {
+      Tree::Con { lft, rgt }
+    } else {
+      Tree::Dup { lab: self.rng.below(self.labs as usize) as run::Lab, lft, rgt }
+    }
+  }
+
+  // A structural tree with a node on top, or an eraser, to be one side of a redex.
+  fn str_side(&mut self, comp: usize) -> Tree {
+    if self.rng.chance(15) {
+      return Tree::Era;
+    }
+    self.spend();
+    self.str_node(comp)
+  }
+
+  fn str_def(&mut self) -> Net {
+    let root = self.str_tree(0);
+    for comp in 1 ..= 1 + self.rng.below(3) {
+      if !self.strs.is_empty() && self.rng.chance(30) {
+        // A call, which only ever meets a CON node, as a DUP may copy it instead.
+        let nam = ast::name_to_val(&self.strs[self.rng.below(self.strs.len())]);
+        self.spend();
+        let arg = Tree::Con { lft: Box::new(self.str_tree(comp)), rgt: Box::new(self.str_tree(comp)) };
+        self.rdex.push((Tree::Ref { nam }, arg));
+      } else {
+        let a = self.str_side(comp);
+        let b = self.str_side(comp);
+        self.rdex.push((a, b));
+      }
+    }
+    let mut root = root;
+    let mut rdex = std::mem::take(&mut self.rdex);
+    let mut vars = self.pair();
+    fill(&mut root, &mut vars);
+    for (a, b) in &mut rdex {
+      fill(a, &mut vars);
+      fill(b, &mut vars);
+    }
+    Net::new(root, rdex)
+  }
+
+  // Names the structural leaves, wiring pairs of them across components, so that cycles are rare.
+  // Leaves that are left over are sometimes wired within their component, or become erasers.
+  fn pair(&mut self) -> HashMap<String, Tree> {
+    let mut ends = std::mem::take(&mut self.ends);
+    for i in (1 .. ends.len()).rev() {
+      ends.swap(i, self.rng.below(i + 1));
+    }
+    let mut comp = (0 .. ends.iter().map(|(_, comp)| comp + 1).max().unwrap_or(0)).collect::<Vec<_>>();
+    fn find(comp: &mut Vec<usize>, i: usize) -> usize {
+      if comp[i] != i {
+        comp[i] = find(comp, comp[i]);
+      }
+      comp[i]
+    }
+    let mut vars = HashMap::new();
+    let mut done = vec![false; ends.len()];
+    for i in 0 .. ends.len() {
+      if done[i] {
+        continue;
+      }
+      let a = find(&mut comp, ends[i].1);
+      let mut other = (i + 1 .. ends.len()).find(|j| !done[*j] && find(&mut comp, ends[*j].1) != a);
+      if other.is_none() && self.rng.chance(40) {
+        other = (i + 1 .. ends.len()).find(|j| !done[*j]);
+      }
+      match other {
+        Some(j) => {
+          let nam = self.fresh();
+          comp[a] = find(&mut comp, ends[j].1);
+          vars.insert(ends[i].0.clone(), Self::var(&nam));
+          vars.insert(ends[j].0.clone(), Self::var(&nam));
+          done[j] = true;
+        }
+        None => {
+          vars.insert(ends[i].0.clone(), Tree::Era);
+        }
+      }
+      done[i] = true;
+    }
+    return vars;
+  }
+}
+
+// Replaces the placeholder leaves of a tree.
+fn fill(tree: &mut Tree, vars: &mut HashMap<String, Tree>) {
+  match tree {
+    Tree::Con { lft, rgt } | Tree::Tup { lft, rgt } | Tree::Dup { lft, rgt, .. } | Tree::Op2 { lft, rgt, .. } => {
+      fill(lft, vars);
+      fill(rgt, vars);
+    }
+    Tree::Mat { sel, ret } => {
+      fill(sel, vars);
+      fill(ret, vars);
+    }
+    Tree::Op1 { rgt, .. } => fill(rgt, vars),
+    Tree::Var { nam } => {
+      if let Some(got) = vars.remove(nam) {
+        *tree = got;
+      }
+    }
+    Tree::Era | Tree::Ref { .. } | Tree::Num { .. } => {}
+  }
+}
+
+pub struct Fuzzer {
+  pub gen: Generator,
+  pub threads: Vec<usize>, // thread counts to compare the sequential result with
+  pub fuel: usize, // max interactions of a generated book
+  pub size: usize, // nodes of the nets it's normalized on
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FuzzStats {
+  pub books: usize, // books checked
+  pub skips: usize, // books skipped, as they ran out of fuel
+  pub rwts: usize, // interactions performed by the sequential runs
+}
+
+// A book that breaks a property, with the seed that generates it.
+pub struct Failure {
+  pub seed: u64,
+  pub book: Book,
+  pub error: String,
+}
+
+impl Default for Fuzzer {
+  fn default() -> Self {
+    Fuzzer { gen: Generator::default(), threads: vec![1, 2, 4, 8], fuel: 1 << 12, size: 1 << 22 }
+  }
+}
+
+impl Fuzzer {
+  // Checks the books generated from 'count' seeds starting at 'seed', stopping at the first failure.
+  pub fn fuzz(&self, seed: u64, count: usize) -> Result<FuzzStats, Failure> {
+    let nodes = run::Heap::<false>::init(self.size);
+    let mut pre = NetFields::new(&nodes);
+    let mut net = run::Net::new(self.size, false);
+    let mut stats = FuzzStats::default();
+    for seed in seed .. seed + count as u64 {
+      let book = self.gen.generate(&mut Rng::new(seed));
+      match self.check(&mut pre, &mut net, &book) {
+        Ok(Some(rwts)) => {
+          stats.books += 1;
+          stats.rwts += rwts;
+        }
+        Ok(None) => {
+          stats.skips += 1;
+        }
+        Err(error) => {
+          return Err(Failure { seed, book, error });
+        }
+      }
+    }
+    return Ok(stats);
+  }
+
+  // Checks a book, returning the interactions of its sequential run, or None if it ran out of fuel.
+  pub fn check(&self, pre: &mut NetFields<false>, net: &mut run::Net, book: &Book) -> Result<Option<usize>, String> {
+    let code = ast::show_book(book);
+    match ast::try_parse_book(&code) {
+      Ok(back) if back == *book => {}
+      Ok(back) => return Err(format!("printing and parsing changed the book into:\n{}", ast::show_book(&back))),
+      Err(err) => return Err(format!("the printed book doesn't parse: {}", err)),
+    }
+    let book = ast::book_to_runtime(book);
+    if !self.terminates(pre, &book)? {
+      return Ok(None);
+    }
+    net.boot_with(&book, "main", &[])?;
+    net.normal(&book);
+    let seq = Outcome { strategy: Strategy::Sequential, norm: net.to_net(), rwts: Some(net.get_rewrites()) };
+    for &threads in &self.threads {
+      net.set_threads(threads);
+      net.boot_with(&book, "main", &[])?;
+      net.parallel_normal(&book);
+      let par = Outcome { strategy: Strategy::Parallel, norm: net.to_net(), rwts: Some(net.get_rewrites()) };
+      if let Some(diff) = verify::compare(&book, &[seq.clone(), par]) {
+        return Err(format!("with {} threads, {}", threads, diff));
+      }
+    }
+    return Ok(Some(seq.rwts.unwrap().total()));
+  }
+
+  // Normalizes a book sequentially, checking that it takes at most 'fuel' interactions.
+  fn terminates(&self, net: &mut NetFields<false>, book: &run::Book) -> Result<bool, String> {
+    net.boot_with(book, "main", &[])?;
+    let mut fuel = self.fuel;
+    net.expand(book);
+    while net.rdex.len() > 0 {
+      if fuel == 0 {
+        return Ok(false);
+      }
+      fuel -= net.reduce(book, fuel);
+      net.expand(book);
+    }
+    return Ok(true);
+  }
+}
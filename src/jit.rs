@@ -13,13 +13,23 @@ pub fn compile_book(book: &run::Book) -> String {
 
   for (fid, def) in book.defs.iter() {
     if def.node.len() > 0 {
-      let name = &ast::val_to_name(*fid as run::Val);
+      let name = &ast::val_to_ident(*fid as run::Val);
       code.push_str(&format!("pub const F_{:4} : Val = 0x{:06x};\n", name, fid));
     }
   }
 
   code.push_str(&format!("\n"));
 
+  // Interned names, added to the book on startup, so that readback shows them
+  let mut names = book.names.iter().filter(|(fid, _)| ast::is_interned(**fid)).collect::<Vec<_>>();
+  names.sort();
+  code.push_str(&format!("pub const NAMES : &[(Val, &str)] = &[\n"));
+  for (fid, name) in names {
+    code.push_str(&format!("{}(0x{:x}, {:?}),\n", ident(1), fid, name));
+  }
+  code.push_str(&format!("];\n"));
+  code.push_str(&format!("\n"));
+
   code.push_str(&format!("impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {{\n"));
   code.push_str(&format!("\n"));
 
@@ -27,7 +37,7 @@ pub fn compile_book(book: &run::Book) -> String {
   code.push_str(&format!("{}match ptr.val() {{\n", ident(2)));
   for (fid, def) in book.defs.iter() {
     if def.node.len() > 0 {
-      let fun = ast::val_to_name(*fid);
+      let fun = ast::val_to_ident(*fid);
       code.push_str(&format!("{}F_{} => {{ return self.F_{}(ptr, Trg::Ptr(x)); }}\n", ident(3), fun, fun));
     }
   }
@@ -74,7 +84,7 @@ pub fn tag(tag: run::Tag) -> &'static str {
 
 pub fn atom(ptr: run::Ptr) -> String {
   if ptr.is_ref() {
-    return format!("Ptr::big(REF, F_{})", ast::val_to_name(ptr.val()));
+    return format!("Ptr::big(REF, F_{})", ast::val_to_ident(ptr.val()));
   } else {
     return format!("Ptr::new({}, 0x{:x}, 0x{:x})", tag(ptr.tag()), ptr.lab(), ptr.loc());
   }
@@ -118,14 +128,43 @@ pub fn compile_term(book: &run::Book, tab: usize, fid: run::Val) -> String {
     def  : &run::Def,
     rdex : (run::Ptr, run::Ptr),
   ) -> String {
-    let (rf, rx) = adjust_redex(rdex.0, rdex.1);
-    let rf_name  = format!("_{}", fresh(newx));
     let mut code = String::new();
-    code.push_str(&format!("{}let {} : Trg = Trg::Ptr({});\n", ident(tab), rf_name, &atom(rf)));
-    code.push_str(&burn(book, tab, None, newx, vars, def, rx, &Target { nam: rf_name }));
+    // ATOM ~ TERM: burns the term against the atom, applying fast rewrites
+    if let Some((rf, rx)) = adjust_redex(rdex.0, rdex.1) {
+      let rf_name = format!("_{}", fresh(newx));
+      code.push_str(&format!("{}let {} : Trg = Trg::Ptr({});\n", ident(tab), rf_name, &atom(rf)));
+      code.push_str(&burn(book, tab, None, newx, vars, def, rx, &Target { nam: rf_name }));
+    // ATOM ~ ATOM or TERM ~ TERM: allocates both sides and pushes a generic redex
+    } else {
+      let a = alloc(tab, newx, vars, def, rdex.0, &mut code);
+      let b = alloc(tab, newx, vars, def, rdex.1, &mut code);
+      code.push_str(&format!("{}self.safe_link(Trg::Ptr({}), Trg::Ptr({}));\n", ident(tab), a, b));
+    }
     return code;
   }
 
+  // Builds a principal port on the heap, returning an expression that evaluates to it
+  fn alloc(
+    tab  : usize,
+    newx : &mut usize,
+    vars : &mut HashMap<run::Ptr, String>,
+    def  : &run::Def,
+    ptr  : run::Ptr,
+    code : &mut String,
+  ) -> String {
+    if ptr.is_nod() {
+      let lc = fresh(newx);
+      let p1 = def.node[ptr.loc() as usize].1;
+      let p2 = def.node[ptr.loc() as usize].2;
+      code.push_str(&format!("{}let {} = self.alloc();\n", ident(tab), lc));
+      code.push_str(&make(tab, newx, vars, def, p2, &format!("Trg::Ptr(Ptr::new(VR2, 0, {}))", lc)));
+      code.push_str(&make(tab, newx, vars, def, p1, &format!("Trg::Ptr(Ptr::new(VR1, 0, {}))", lc)));
+      return format!("Ptr::new({}, {}, {})", tag(ptr.tag()), ptr.lab(), lc);
+    } else {
+      return atom(ptr);
+    }
+  }
+
   fn call(
     book : &run::Book,
     tab  : usize,
@@ -212,6 +251,7 @@ pub fn compile_term(book: &run::Book, tab: usize, fid: run::Val) -> String {
           code.push_str(&format!("{}self.rwts.anni += 2;\n", ident(tab+1)));
           code.push_str(&format!("{}self.rwts.oper += 1;\n", ident(tab+1)));
           code.push_str(&format!("{}let got = {};\n", ident(tab+1), trg.take()));
+          code.push_str(&format!("{}self.free(got.loc());\n", ident(tab+1)));
           code.push_str(&format!("{}let {} = Trg::Dir(Ptr::new(VR1, 0, got.loc()));\n", ident(tab+1), num.show()));
           code.push_str(&format!("{}let {} = Trg::Dir(Ptr::new(VR2, 0, got.loc()));\n", ident(tab+1), res.show()));
           code.push_str(&format!("{}if {}.val() == 0 {{\n", ident(tab+1), num.get()));
@@ -315,6 +355,7 @@ pub fn compile_term(book: &run::Book, tab: usize, fid: run::Val) -> String {
       code.push_str(&format!("{}if {}.tag() == {} {{\n", ident(tab), trg.get(), tag(ptr.tag())));
       code.push_str(&format!("{}self.rwts.anni += 1;\n", ident(tab+1)));
       code.push_str(&format!("{}let got = {};\n", ident(tab+1), trg.take()));
+      code.push_str(&format!("{}self.free(got.loc());\n", ident(tab+1)));
       code.push_str(&format!("{}{} = Trg::Dir(Ptr::new(VR1, 0, got.loc()));\n", ident(tab+1), &x1.show()));
       code.push_str(&format!("{}{} = Trg::Dir(Ptr::new(VR2, 0, got.loc()));\n", ident(tab+1), &x2.show()));
       code.push_str(&format!("{}}} else {{\n", ident(tab)));
@@ -408,7 +449,7 @@ pub fn compile_term(book: &run::Book, tab: usize, fid: run::Val) -> String {
     }
   }
 
-  let fun = ast::val_to_name(fid);
+  let fun = ast::val_to_ident(fid);
   let def = &book.get(fid).unwrap();
 
   let mut code = String::new();
@@ -432,21 +473,14 @@ pub fn compile_term(book: &run::Book, tab: usize, fid: run::Val) -> String {
   return code;
 }
 
-// TODO: HVM-Lang must always output in this form.
-fn adjust_redex(rf: run::Ptr, rx: run::Ptr) -> (run::Ptr, run::Ptr) {
+// Orients an `ATOM ~ TERM` redex so that the atom comes first. Returns None for redexes that
+// don't have this shape (`ATOM ~ ATOM` or `TERM ~ TERM`), which are compiled generically.
+fn adjust_redex(rf: run::Ptr, rx: run::Ptr) -> Option<(run::Ptr, run::Ptr)> {
   if rf.is_skp() && !rx.is_skp() {
-    return (rf, rx);
+    return Some((rf, rx));
   } else if !rf.is_skp() && rx.is_skp() {
-    return (rx, rf);
+    return Some((rx, rf));
   } else {
-    println!("Invalid redex. Compiled HVM requires that ALL defs are in the form:");
-    println!("@name = ROOT");
-    println!("  & ATOM ~ TERM");
-    println!("  & ATOM ~ TERM");
-    println!("  & ATOM ~ TERM");
-    println!("  ...");
-    println!("Where ATOM must be either a ref (`@foo`), a num (`#123`), or an era (`*`).");
-    println!("If you used HVM-Lang, please report on https://github.com/HigherOrderCO/hvm-lang.");
-    panic!("Invalid HVMC file.");
+    return None;
   }
 }
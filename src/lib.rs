@@ -8,7 +8,20 @@
 #![allow(non_upper_case_globals)]
 
 pub mod ast;
+pub mod c;
+pub mod cuda;
+pub mod debug;
+pub mod fmt;
 pub mod fns;
+pub mod fuzz;
 pub mod jit;
+pub mod module;
+pub mod opt;
+pub mod prof;
+pub mod repl;
+pub mod rng;
 pub mod run;
+pub mod sim;
+pub mod trace;
 pub mod u60;
+pub mod verify;
@@ -11,10 +11,22 @@ use std::env;
 use std::fs;
 
 use hvmc::ast;
+use hvmc::c;
+use hvmc::cuda;
+use hvmc::debug;
+use hvmc::fmt;
 use hvmc::fns;
+use hvmc::fuzz;
 use hvmc::jit;
+use hvmc::module;
+use hvmc::opt;
+use hvmc::prof;
+use hvmc::repl;
 use hvmc::run;
+use hvmc::sim;
+use hvmc::trace;
 use hvmc::u60;
+use hvmc::verify;
 
 use std::collections::HashSet;
 
@@ -28,16 +40,36 @@ fn get_args() -> Args {
   let args: Vec<String> = env::args().collect();
   let func = args.get(1).unwrap_or(&"help".to_string()).to_string();
   let argm = args.get(2).unwrap_or(&"".to_string()).to_string();
-  let opts = args.iter().skip(3).map(|s| s.to_string()).collect::<HashSet<_>>();
+  let opts = get_rest().into_iter().map(|(arg, _)| arg).collect::<HashSet<_>>();
   return Args { func, argm, opts };
 }
 
+// Options that take a value, as in '--opt value'
+const VALUE_OPTS: &[&str] = &[
+  "--after", "--budget", "--def", "--entry", "--fuel", "--inline", "--profile-json", "--seed",
+  "--simulate", "--stats-json", "--strategies", "--threads", "--width",
+];
+
+// The arguments after 'argm', each with its value if it is an option that takes one
+fn get_rest() -> Vec<(String, Option<String>)> {
+  let mut args = env::args().skip(3);
+  let mut rest = vec![];
+  while let Some(arg) = args.next() {
+    let value = if VALUE_OPTS.contains(&arg.as_str()) { args.next() } else { None };
+    rest.push((arg, value));
+  }
+  return rest;
+}
+
 // Runs 'main' without showing the CLI options
 fn run_without_cli(args: Args) {
   let lazy    = args.opts.contains("-L");
   let seq     = lazy || args.opts.contains("-1");
   let file    = args.argm;
-  let book    = run::Book::new();
+  let mut book = run::Book::new();
+  for (fid, name) in fns::NAMES {
+    book.names.insert(*fid, name.to_string());
+  }
   let mut net = run::Net::new(1 << 28, false);
   let begin   = std::time::Instant::now();
   if lazy { todo!() }
@@ -46,57 +78,499 @@ fn run_without_cli(args: Args) {
   } else {
     net.parallel_normal(&book);
   }
-  println!("{}", net.show());
+  println!("{}", net.show(&book));
   print_stats(&net, begin);
 }
 
 fn run_with_cli(args: Args) -> Result<(), Box<dyn std::error::Error>> {
-  let lazy = args.opts.contains("-L");
-  let seq  = lazy || args.opts.contains("-1");
+  let lazy  = args.opts.contains("-L");
+  let seq   = lazy || args.opts.contains("-1");
+  let prune = args.opts.contains("--prune");
+  let main  = [ast::name_to_val("main")];
   match args.func.as_str() {
     "run" => {
       if args.argm.len() > 0 {
         let file    = args.argm;
-        let book    = load_book(&file);
+        let entry   = get_entry();
+        let book    = load_book(&file, prune, &[ast::name_to_val(&entry)]);
         let mut net = run::Net::new(1 << 28, lazy);
+        if let Err(err) = net.boot_with(&book, &entry, &[]) {
+          eprintln!("Can't boot: {}", err);
+          std::process::exit(1);
+        }
+        let json    = get_option("--profile-json");
+        if args.opts.contains("--profile") || json.is_some() {
+          net.enable_profile(&book);
+        }
+        if args.opts.contains("--check") {
+          net.set_check(true);
+        }
+        match get_option("--threads").map(|num| num.parse::<usize>()) {
+          Some(Ok(num)) => net.set_threads(num),
+          Some(Err(_)) => {
+            eprintln!("Invalid number for --threads");
+            std::process::exit(1);
+          }
+          None => {}
+        }
+        let simulate = match get_option("--simulate").map(|num| num.parse::<u64>()) {
+          Some(Ok(seed)) => Some(seed),
+          Some(Err(_)) => {
+            eprintln!("Invalid number for --simulate");
+            std::process::exit(1);
+          }
+          None => None,
+        };
         let begin   = std::time::Instant::now();
+        let cpu     = cpu_time();
         if seq {
           net.normal(&book);
+        } else if let Some(seed) = simulate {
+          net.simulated_normal(&book, seed);
         } else {
           net.parallel_normal(&book);
         }
-        //println!("{}", net.show());
-        println!("{}", net.show());
+        //println!("{}", net.show(&book));
+        println!("{}", net.show(&book));
+        if args.opts.contains("--gc") {
+          println!("GC     : {} leaked nodes freed", net.collect_garbage(true));
+        }
         if args.opts.contains("-s") {
           print_stats(&net, begin);
         }
+        if let Some(json) = get_option("--stats-json") {
+          fs::write(json, stats_to_json(&net, begin, cpu))?;
+        }
+        if let Some(prof) = net.get_profile() {
+          match json {
+            Some(json) => fs::write(json, prof.to_json())?,
+            None       => print!("{}", prof.show_table()),
+          }
+        }
       } else {
-        println!("Usage: hvmc run <file.hvmc> [-s]");
+        println!("Usage: hvmc run <file.hvmc> [-s] [--entry <name>] [--prune] [--check] [--gc] [--threads <n>] [--simulate <seed>] [--stats-json <file.json>] [--profile | --profile-json <file.json>]");
+        std::process::exit(1);
+      }
+    }
+    "eval" => {
+      if let (true, Some(code)) = (args.argm.len() > 0, get_positional(&args)) {
+        let code    = match ast::try_parse_net(&code) {
+          Ok(code) => code,
+          Err(err) => {
+            eprintln!("Can't parse: {}", err);
+            std::process::exit(1);
+          }
+        };
+        let book    = load_book(&args.argm, prune, &ast::net_refs(&code));
+        let mut net = run::Net::new(1 << 28, lazy);
+        if let Err(err) = net.boot_net(&book, &code) {
+          eprintln!("Can't boot: {}", err);
+          std::process::exit(1);
+        }
+        let begin = std::time::Instant::now();
+        if seq {
+          net.normal(&book);
+        } else {
+          net.parallel_normal(&book);
+        }
+        println!("{}", net.show(&book));
+        if args.opts.contains("-s") {
+          print_stats(&net, begin);
+        }
+      } else {
+        println!("Usage: hvmc eval <file.hvmc> '<net>' [-s]");
         std::process::exit(1);
       }
     }
     "compile" => {
       if args.argm.len() > 0 {
         let file  = args.argm;
-        let book  = load_book(&file);
+        let book  = load_book(&file, prune, &main);
         let net   = run::Net::new(1 << 28, lazy);
         let begin = std::time::Instant::now();
         compile_book_to_rust_crate(&file, &book)?;
         compile_rust_crate_to_executable(&file)?;
       } else {
-        println!("Usage: hvmc compile <file.hvmc>");
+        println!("Usage: hvmc compile <file.hvmc> [--prune]");
         std::process::exit(1);
       }
     }
     "gen-cuda-book" => {
       if args.argm.len() > 0 {
         let file  = args.argm;
-        let book  = load_book(&file);
-        let net   = run::Net::new(1 << 28, lazy);
+        let book  = load_book(&file, prune, &main);
+        let cuda  = match cuda::Book::new(&book) {
+          Ok(cuda) => cuda,
+          Err(err) => {
+            eprintln!("Can't generate CUDA book: {}", err);
+            std::process::exit(1);
+          }
+        };
+        if args.opts.contains("--json") {
+          println!("{}", cuda.to_json());
+        } else if args.opts.contains("--bin") {
+          std::io::Write::write_all(&mut std::io::stdout(), &cuda.to_bin())?;
+        } else {
+          println!("{}", cuda.to_c());
+        }
+      } else {
+        println!("Usage: hvmc gen-cuda-book <file.hvmc> [--json|--bin] [--prune]");
+        std::process::exit(1);
+      }
+    }
+    "sim-cuda" => {
+      if args.argm.len() > 0 {
+        let file = args.argm;
+        let book = load_book(&file, prune, &main);
+        let main = ast::name_to_val("main") as cuda::Val;
+        let net  = match cuda::Book::new(&book).and_then(|cuda| sim::Net::new(&cuda, main, cuda::HEAP_SIZE_L2)) {
+          Ok(net) => net,
+          Err(err) => {
+            eprintln!("Can't simulate CUDA runtime: {}", err);
+            std::process::exit(1);
+          }
+        };
         let begin = std::time::Instant::now();
-        println!("{}", gen_cuda_book(&book));
+        net.normal();
+        if let Err(err) = net.check() {
+          eprintln!("Can't simulate CUDA runtime: {}", err);
+          std::process::exit(1);
+        }
+        match net.to_net() {
+          Ok(norm) => println!("{}", ast::show_net_in(&norm, &book.names)),
+          Err(err) => println!("Can't read back net: {}", err),
+        }
+        let abrt = net.abrt.load(std::sync::atomic::Ordering::Relaxed);
+        if abrt > 0 {
+          eprintln!("WARNING: {} interactions failed to allocate and were pushed back", abrt);
+        }
+        if args.opts.contains("-s") {
+          print_sim_stats(&net, begin);
+        }
+      } else {
+        println!("Usage: hvmc sim-cuda <file.hvmc> [-s] [--prune]");
+        std::process::exit(1);
+      }
+    }
+    "trace" => {
+      if args.argm.len() > 0 {
+        let file    = args.argm;
+        let book    = load_book(&file, prune, &main);
+        let mut net = run::Net::new(1 << 28, lazy);
+        if !trace::ENABLED {
+          eprintln!("Tracing is disabled. Build with '--features trace'.");
+          std::process::exit(1);
+        }
+        if seq {
+          net.normal(&book);
+        } else {
+          net.parallel_normal(&book);
+        }
+        print!("{}", trace::show_log(net.get_trace(), &book.names));
+      } else {
+        println!("Usage: hvmc trace <file.hvmc> [-1] > <file.log>");
+        std::process::exit(1);
+      }
+    }
+    "trace-view" => {
+      if args.argm.len() > 0 {
+        let log = load_log(&args.argm);
+        match get_positional(&args) {
+          Some(other) => print!("{}", trace::show_diff(&log, &load_log(&other))),
+          None        => print!("{}", trace::show_events(&log)),
+        }
+      } else {
+        println!("Usage: hvmc trace-view <file.log> [<other.log>]");
+        std::process::exit(1);
+      }
+    }
+    "trace-replay" => {
+      if let (true, Some(log)) = (args.argm.len() > 0, get_positional(&args)) {
+        let book    = load_book(&args.argm, prune, &main);
+        let log     = load_log(&log);
+        let mut net = run::Net::new(1 << 28, lazy);
+        if !trace::ENABLED {
+          eprintln!("Tracing is disabled. Build with '--features trace'.");
+          std::process::exit(1);
+        }
+        net.normal(&book);
+        print!("{}", trace::show_diff(&log, net.get_trace()));
+      } else {
+        println!("Usage: hvmc trace-replay <file.hvmc> <file.log>");
+        std::process::exit(1);
+      }
+    }
+    "debug" => {
+      if args.argm.len() > 0 {
+        let file  = args.argm;
+        let book  = load_book(&file, prune, &main);
+        let nodes = debug::alloc_nodes(1 << 28);
+        let mut dbg = debug::Debugger::new(&nodes, &book, ast::name_to_val("main"));
+        println!("Debugging '{}'. Type 'help' for commands.", file);
+        let mut line = String::new();
+        loop {
+          print!("(hvmc) ");
+          std::io::Write::flush(&mut std::io::stdout())?;
+          line.clear();
+          if std::io::stdin().read_line(&mut line)? == 0 {
+            break;
+          }
+          match dbg.exec(&line) {
+            Some(Ok(out)) => if out.len() > 0 { println!("{}", out) },
+            Some(Err(err)) => println!("Error: {}", err),
+            None => break,
+          }
+        }
+      } else {
+        println!("Usage: hvmc debug <file.hvmc>");
+        std::process::exit(1);
+      }
+    }
+    "repl" => {
+      let mut repl = repl::Repl::new(1 << 28);
+      repl.auto = args.opts.contains("-s");
+      if args.argm.len() > 0 && !args.argm.starts_with('-') {
+        match repl.load(&args.argm) {
+          Ok(out)  => println!("{}", out),
+          Err(err) => println!("Error: {}", err),
+        }
+      }
+      println!("Type ':help' for commands.");
+      let mut line = String::new();
+      loop {
+        print!("> ");
+        std::io::Write::flush(&mut std::io::stdout())?;
+        line.clear();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+          break;
+        }
+        match repl.exec(&line) {
+          Some(Ok(out)) => if out.len() > 0 { println!("{}", out) },
+          Some(Err(err)) => println!("Error: {}", err),
+          None => break,
+        }
+      }
+    }
+    "prune" => {
+      if args.argm.len() > 0 {
+        let mut book = load_ast_book(&args.argm);
+        let names = get_entries();
+        if let Some(name) = names.iter().find(|name| !book.contains_key(*name)) {
+          eprintln!("Unknown definition @{}", name);
+          std::process::exit(1);
+        }
+        let seen = ast::book_to_runtime(&book).reachable(&names.iter().map(|name| ast::name_to_val(name)).collect::<Vec<_>>());
+        let size = book.len();
+        book.retain(|name, _| seen.contains(&ast::name_to_val(name)));
+        eprintln!("Pruned {} of {} definitions.", size - book.len(), size);
+        print!("{}", ast::show_book(&book));
+      } else {
+        println!("Usage: hvmc prune <file.hvmc> [--entry <name,...>]");
+        std::process::exit(1);
+      }
+    }
+    "fmt" => {
+      if args.argm.len() > 0 {
+        let file = args.argm.clone();
+        let code = fs::read_to_string(&file)?;
+        let width = match get_option("--width").map(|num| num.parse::<usize>()) {
+          Some(Ok(num)) => num,
+          Some(Err(_)) => {
+            eprintln!("Invalid number for --width");
+            std::process::exit(1);
+          }
+          None => fmt::WIDTH,
+        };
+        match fmt::format(&code, width) {
+          Ok(text) if text == code => {}
+          Ok(_) if args.opts.contains("--check") => {
+            eprintln!("'{}' is not formatted", file);
+            std::process::exit(1);
+          }
+          Ok(text) => fs::write(&file, text)?,
+          Err(err) => {
+            eprintln!("On '{}': {}", file, err);
+            std::process::exit(1);
+          }
+        }
+      } else {
+        println!("Usage: hvmc fmt <file.hvmc> [--check] [--width <n>]");
+        std::process::exit(1);
+      }
+    }
+    "verify" => {
+      if args.argm.len() > 0 {
+        let file  = args.argm.clone();
+        let names = get_entries();
+        let book  = load_book(&file, prune, &names.iter().map(|name| ast::name_to_val(name)).collect::<Vec<_>>());
+        let mut strategies = vec![];
+        for name in get_option("--strategies").unwrap_or("sequential,parallel,lazy".to_string()).split(',') {
+          match verify::Strategy::from_name(name) {
+            Some(strategy) => strategies.push(strategy),
+            None => {
+              eprintln!("Unknown strategy '{}'", name);
+              std::process::exit(1);
+            }
+          }
+        }
+        let mut outs = match verify::collect(&book, &names, &strategies, 1 << 28) {
+          Ok(outs) => outs,
+          Err(err) => {
+            eprintln!("Can't verify: {}", err);
+            std::process::exit(1);
+          }
+        };
+        if strategies.contains(&verify::Strategy::Compiled) {
+          match names.iter().position(|name| name == "main") {
+            Some(i) => outs[i].push(run_compiled(&file, &book)?),
+            None    => eprintln!("Compiled code only runs @main, so it isn't verified."),
+          }
+        }
+        match verify::first_mismatch(&book, &names, &outs) {
+          Some((name, diff)) => {
+            println!("Mismatch on @{}: {}", name, diff);
+            std::process::exit(1);
+          }
+          None => println!("Strategies {} agree on {}.", strategies.iter().map(|strategy| strategy.name()).collect::<Vec<_>>().join(", "), names.iter().map(|name| format!("@{}", name)).collect::<Vec<_>>().join(", ")),
+        }
+      } else {
+        println!("Usage: hvmc verify <file.hvmc> [--entry <name,...>] [--strategies <sequential,parallel,lazy,compiled>]");
+        std::process::exit(1);
+      }
+    }
+    "diff" => {
+      if let (true, Some(other)) = (args.argm.len() > 0, get_positional(&args)) {
+        let a = load_ast_book(&args.argm);
+        let b = load_ast_book(&other);
+        let mut same = true;
+        for name in a.keys().chain(b.keys().filter(|name| !a.contains_key(*name))) {
+          match (a.get(name), b.get(name)) {
+            (Some(_), None) => println!("Only in '{}': @{}", args.argm, name),
+            (None, Some(_)) => println!("Only in '{}': @{}", other, name),
+            (Some(x), Some(y)) if !ast::net_equiv(x, y) => {
+              println!("Differs: @{}", name);
+              println!("  {}", ast::show_net(x).replace('\n', "\n  "));
+              println!("  vs");
+              println!("  {}", ast::show_net(y).replace('\n', "\n  "));
+            }
+            _ => continue,
+          }
+          same = false;
+        }
+        if !same {
+          std::process::exit(1);
+        }
+      } else {
+        println!("Usage: hvmc diff <a.hvmc> <b.hvmc>");
+        std::process::exit(1);
+      }
+    }
+    "fuzz" => {
+      let count = if args.argm.is_empty() { Ok(100) } else { args.argm.parse::<usize>() };
+      let Ok(count) = count else {
+        println!("Usage: hvmc fuzz [<count>] [--seed <n>] [--threads <n,...>] [--fuel <interactions>]");
+        std::process::exit(1);
+      };
+      let mut fuzzer = fuzz::Fuzzer::default();
+      let mut seed = 0;
+      for (option, value) in [("--seed", &mut seed), ("--fuel", &mut fuzzer.fuel)] {
+        match get_option(option).map(|num| num.parse::<usize>()) {
+          Some(Ok(num)) => *value = num,
+          Some(Err(_)) => {
+            eprintln!("Invalid number for {}", option);
+            std::process::exit(1);
+          }
+          None => {}
+        }
+      }
+      if let Some(threads) = get_option("--threads") {
+        match threads.split(',').map(|num| num.parse::<usize>()).collect::<Result<Vec<_>, _>>() {
+          Ok(threads) => fuzzer.threads = threads,
+          Err(_) => {
+            eprintln!("Invalid number for --threads");
+            std::process::exit(1);
+          }
+        }
+      }
+      match fuzzer.fuzz(seed as u64, count) {
+        Ok(stats) => {
+          println!("Checked {} books, {} interactions ({} skipped, out of fuel).", stats.books, stats.rwts, stats.skips);
+        }
+        Err(fail) => {
+          println!("Seed {} fails: {}", fail.seed, fail.error);
+          print!("{}", ast::show_book(&fail.book));
+          std::process::exit(1);
+        }
+      }
+    }
+    "optimize" => {
+      if args.argm.len() > 0 {
+        let book = load_ast_book(&args.argm);
+        let mut optimizer = opt::Optimizer::default();
+        for (option, value) in [("--budget", &mut optimizer.budget), ("--inline", &mut optimizer.inline)] {
+          match get_option(option).map(|num| num.parse::<usize>()) {
+            Some(Ok(num)) => *value = num,
+            Some(Err(_)) => {
+              eprintln!("Invalid number for {}", option);
+              std::process::exit(1);
+            }
+            None => {}
+          }
+        }
+        let (book, stats) = optimizer.optimize(&book);
+        eprintln!("Optimized {} of {} definitions: {} interactions, {} calls inlined, {} -> {} nodes.", stats.defs, book.len(), stats.rwts, stats.inln, stats.init, stats.done);
+        print!("{}", ast::show_book(&book));
       } else {
-        println!("Usage: hvmc gen-cuda-book <file.hvmc>");
+        println!("Usage: hvmc optimize <file.hvmc> [--budget <interactions>] [--inline <nodes>]");
+        std::process::exit(1);
+      }
+    }
+    "dot" => {
+      if args.argm.len() > 0 {
+        let file = args.argm.clone();
+        let book = load_ast_book(&file);
+        let def  = get_option("--def");
+        if let Some(name) = def.as_ref().filter(|name| !book.contains_key(*name)) {
+          eprintln!("Unknown definition @{}", name);
+          std::process::exit(1);
+        }
+        match get_option("--after").map(|steps| steps.parse::<usize>()) {
+          // Renders a runtime net, after the given number of steps
+          Some(Ok(steps)) => {
+            let rt_book = ast::book_to_runtime(&book);
+            let nodes   = debug::alloc_nodes(1 << 28);
+            let mut dbg = debug::Debugger::new(&nodes, &rt_book, ast::name_to_val(def.as_deref().unwrap_or("main")));
+            dbg.run(steps);
+            print!("{}", ast::net_to_dot_in(&dbg.to_net(), &rt_book.names));
+          }
+          Some(Err(_)) => {
+            eprintln!("Invalid step count for --after");
+            std::process::exit(1);
+          }
+          // Renders a static definition, or the whole book
+          None => match def {
+            Some(name) => print!("{}", ast::net_to_dot(&book[&name])),
+            None       => print!("{}", ast::book_to_dot(&book)),
+          }
+        }
+      } else {
+        println!("Usage: hvmc dot <file.hvmc> [--def <name>] [--after <steps>]");
+        std::process::exit(1);
+      }
+    }
+    "gen-c" => {
+      if args.argm.len() > 0 {
+        let file = args.argm;
+        let book = load_book(&file, prune, &main);
+        match c::compile_book(&book) {
+          Ok(code) => println!("{}", code),
+          Err(err) => {
+            eprintln!("Can't generate C: {}", err);
+            std::process::exit(1);
+          }
+        }
+      } else {
+        println!("Usage: hvmc gen-c <file.hvmc> [--prune]");
         std::process::exit(1);
       }
     }
@@ -104,11 +578,49 @@ fn run_with_cli(args: Args) -> Result<(), Box<dyn std::error::Error>> {
       println!("Usage: hvmc <cmd> <file.hvmc> [-s]");
       println!("Commands:");
       println!("  run           - Run the given file");
+      println!("  eval          - Normalize a net using the given file's definitions");
       println!("  compile       - Compile the given file to an executable");
       println!("  gen-cuda-book - Generate a CUDA book from the given file");
+      println!("  sim-cuda      - Run the given file on a CPU simulation of the CUDA runtime");
+      println!("  gen-c         - Generate a standalone C program from the given file");
+      println!("  debug         - Step through the given file interactively");
+      println!("  repl          - Evaluate nets interactively, optionally loading the given file");
+      println!("  dot           - Render the given file's nets as a Graphviz graph");
+      println!("  prune         - Print the given file without definitions unreachable from the entry points");
+      println!("  fmt           - Format the given file in place");
+      println!("  verify        - Check that the evaluation strategies agree on the given file");
+      println!("  diff          - Compare the definitions of two files, up to variable names and redex order");
+      println!("  fuzz          - Check the runtime on random books: parallel runs against sequential ones, and parsing");
+      println!("  optimize      - Print the given file with its definitions pre-reduced and small ones inlined");
+      println!("  trace         - Run the given file, printing its interaction log");
+      println!("  trace-view    - Show an interaction log, or diff it against another");
+      println!("  trace-replay  - Run the given file sequentially, diffing it against a log");
       println!("Options:");
       println!("  [-s] Show stats, including rewrite count");
       println!("  [-1] Single-core mode (no parallelism)");
+      println!("  [--entry <name>] Definition to run (default: main); prune and verify take several, separated by commas");
+      println!("  [--prune] Remove definitions unreachable from @main, run's --entry or eval's net, before running or compiling");
+      println!("  [--stats-json <file>] Write run stats as JSON");
+      println!("  [--check] Check the heap's integrity after each epoch (slow)");
+      println!("  [--gc] Free nodes left unreachable after normalization, reporting how many leaked");
+      println!("  [--json|--bin] Output format of gen-cuda-book");
+      println!("  [--profile] Show dereferences, allocated nodes and rewrites of each definition");
+      println!("  [--profile-json <file>] Write the per-definition profile as JSON");
+      println!("  [--def <name>] Definition to render on dot (default: all, or main with --after)");
+      println!("  [--after <steps>] Render the runtime net after the given steps on dot");
+      println!("  [--strategies <list>] Strategies compared by verify (default: sequential,parallel,lazy; also: compiled)");
+      println!("  [--threads <n>] Threads of a parallel run (default: one per core, or 4 with --simulate)");
+      println!("  [--simulate <seed>] Run on a single thread, emulating a parallel run's threads in an order drawn from the seed; its stats are reproducible, unlike a real parallel run's");
+      println!("Options of fuzz:");
+      println!("  [--seed <n>] First seed of the books generated (default: 0)");
+      println!("  [--threads <list>] Thread counts books are run with (default: 1,2,4,8)");
+      println!("  [--fuel <n>] Max interactions of a generated book, or it's skipped (default: 4096)");
+      println!("Options of fmt:");
+      println!("  [--check] Fail if the file isn't formatted, without changing it");
+      println!("  [--width <n>] Line width trees are broken at (default: 100)");
+      println!("Options of optimize:");
+      println!("  [--budget <n>] Max interactions performed on each definition (default: 1024)");
+      println!("  [--inline <n>] Max nodes of a definition inlined (default: 16)");
     }
   }
   Ok(())
@@ -124,6 +636,30 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
   run_with_cli(get_args())
 }
 
+// The first argument after 'argm' that is neither an option nor an option's value
+fn get_positional(args: &Args) -> Option<String> {
+  get_rest().into_iter().map(|(arg, _)| arg).find(|arg| !arg.starts_with('-'))
+}
+
+// The value given to an option, as in '--opt value'
+fn get_option(opt: &str) -> Option<String> {
+  get_rest().into_iter().find(|(arg, _)| arg == opt).and_then(|(_, value)| value)
+}
+
+fn load_log(file: &str) -> Vec<trace::Event> {
+  let Ok(log) = fs::read_to_string(file) else {
+    eprintln!("Log file not found");
+    std::process::exit(1);
+  };
+  match trace::parse_log(&log) {
+    Ok(events) => events,
+    Err(err) => {
+      eprintln!("Can't read log '{}': {}", file, err);
+      std::process::exit(1);
+    }
+  }
+}
+
 fn print_stats(net: &run::Net, begin: std::time::Instant) {
   let rewrites = net.get_rewrites();
   println!("RWTS   : {}", rewrites.total());
@@ -132,17 +668,109 @@ fn print_stats(net: &run::Net, begin: std::time::Instant) {
   println!("- ERAS : {}", rewrites.eras);
   println!("- DREF : {}", rewrites.dref);
   println!("- OPER : {}", rewrites.oper);
+  let usage = net.get_usage();
+  println!("LIVE   : {} nodes", usage.live());
+  if usage.bound {
+    println!("PEAK   : at most {} nodes (summed over threads)", usage.peak());
+  } else {
+    println!("PEAK   : {} nodes", usage.peak());
+  }
+  println!("TIME   : {:.3} s", begin.elapsed().as_secs_f64());
+  println!("RPS    : {:.3} m", get_mrps(rewrites.total(), begin.elapsed().as_secs_f64()));
+}
+
+// Millions of rewrites per second
+fn get_mrps(rewrites: usize, secs: f64) -> f64 {
+  if secs > 0.0 { rewrites as f64 / secs / 1_000_000.0 } else { 0.0 }
+}
+
+// Process CPU time (user + system) in seconds, if available
+#[cfg(unix)]
+fn cpu_time() -> Option<f64> {
+  let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+  if unsafe { libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) } != 0 {
+    return None;
+  }
+  let usage = unsafe { usage.assume_init() };
+  let secs  = |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1_000_000.0;
+  Some(secs(usage.ru_utime) + secs(usage.ru_stime))
+}
+
+#[cfg(not(unix))]
+fn cpu_time() -> Option<f64> {
+  None
+}
+
+fn stats_to_json(net: &run::Net, begin: std::time::Instant, cpu: Option<f64>) -> String {
+  let rewrites = net.get_rewrites();
+  let sched    = net.get_sched();
+  let wall     = begin.elapsed().as_secs_f64();
+  let cpu      = cpu.zip(cpu_time()).map_or("null".to_string(), |(init, done)| format!("{:.3}", done - init));
+  let trwts    = sched.trwts.iter().map(|rwts| rwts.to_string()).collect::<Vec<_>>();
+  let mut json = String::new();
+  json.push_str("{\n");
+  json.push_str(&format!("  \"rwts\": {{ \"total\": {}, \"anni\": {}, \"comm\": {}, \"eras\": {}, \"dref\": {}, \"oper\": {} }},\n",
+    rewrites.total(), rewrites.anni, rewrites.comm, rewrites.eras, rewrites.dref, rewrites.oper));
+  json.push_str(&format!("  \"wall_time\": {:.6},\n", wall));
+  json.push_str(&format!("  \"cpu_time\": {},\n", cpu));
+  json.push_str(&format!("  \"mrps\": {:.3},\n", get_mrps(rewrites.total(), wall)));
+  json.push_str(&format!("  \"heap_size\": {},\n", net.get_size()));
+  json.push_str(&format!("  \"live_nodes\": {},\n", net.get_usage().live()));
+  json.push_str(&format!("  \"peak_nodes\": {},\n", net.get_usage().peak()));
+  json.push_str(&format!("  \"peak_nodes_exact\": {},\n", !net.get_usage().bound));
+  json.push_str(&format!("  \"threads\": {},\n", sched.tids));
+  json.push_str(&format!("  \"epochs\": {},\n", sched.epochs));
+  json.push_str(&format!("  \"splits\": {},\n", sched.splits));
+  json.push_str(&format!("  \"thread_rwts\": [{}]\n", trwts.join(", ")));
+  json.push_str("}");
+  return json;
+}
+
+fn print_sim_stats(net: &sim::Net, begin: std::time::Instant) {
+  let rewrites = net.get_rewrites();
+  println!("RWTS   : {}", net.get_total());
+  println!("- ANNI : {}", rewrites.anni);
+  println!("- COMM : {}", rewrites.comm);
+  println!("- ERAS : {}", rewrites.eras);
+  println!("- DREF : {}", rewrites.dref);
+  println!("- LINK : {}", net.link.load(std::sync::atomic::Ordering::Relaxed));
+  println!("- VOID : {}", net.void.load(std::sync::atomic::Ordering::Relaxed));
   println!("TIME   : {:.3} s", (begin.elapsed().as_millis() as f64) / 1000.0);
-  println!("RPS    : {:.3} m", (rewrites.total() as f64) / (begin.elapsed().as_millis() as f64) / 1000.0);
 }
 
-// Load file
-fn load_book(file: &str) -> run::Book {
-  let Ok(file) = fs::read_to_string(file) else {
-    eprintln!("Input file not found");
+// Load file, dropping the definitions unreachable from the entry points if 'prune' is set
+fn load_book(file: &str, prune: bool, entries: &[run::Val]) -> run::Book {
+  let mut book = ast::book_to_runtime(&load_ast_book(file));
+  if prune {
+    book.prune(entries);
+  }
+  return book;
+}
+
+// The definition to run, as in '--entry name' (default: main)
+fn get_entry() -> String {
+  let entry = get_option("--entry").unwrap_or("main".to_string());
+  if entry.contains(',') {
+    eprintln!("Only one definition can be run, but --entry got '{}'", entry);
     std::process::exit(1);
-  };
-  return ast::book_to_runtime(&ast::do_parse_book(&file));
+  }
+  return entry;
+}
+
+// The entry points, as in '--entry a,b' (default: main)
+fn get_entries() -> Vec<String> {
+  get_option("--entry").unwrap_or("main".to_string()).split(',').map(String::from).collect()
+}
+
+// Loads a book, along with its imports
+fn load_ast_book(file: &str) -> ast::Book {
+  match module::load_book(file) {
+    Ok(book) => book,
+    Err(err) => {
+      eprintln!("{}", err);
+      std::process::exit(1);
+    }
+  }
 }
 
 pub fn compile_book_to_rust_crate(f_name: &str, book: &run::Book) -> Result<(), std::io::Error> {
@@ -157,17 +785,44 @@ pub fn compile_book_to_rust_crate(f_name: &str, book: &run::Book) -> Result<(),
   fs::create_dir_all(&format!("{}/src", outdir))?;
   fs::write(".hvm/Cargo.toml", cargo_toml)?;
   fs::write(".hvm/src/ast.rs", include_str!("../src/ast.rs"))?;
+  fs::write(".hvm/src/c.rs", include_str!("../src/c.rs"))?;
+  fs::write(".hvm/src/cuda.rs", include_str!("../src/cuda.rs"))?;
+  fs::write(".hvm/src/debug.rs", include_str!("../src/debug.rs"))?;
+  fs::write(".hvm/src/fmt.rs", include_str!("../src/fmt.rs"))?;
+  fs::write(".hvm/src/fuzz.rs", include_str!("../src/fuzz.rs"))?;
   fs::write(".hvm/src/jit.rs", include_str!("../src/jit.rs"))?;
+  fs::write(".hvm/src/module.rs", include_str!("../src/module.rs"))?;
+  fs::write(".hvm/src/opt.rs", include_str!("../src/opt.rs"))?;
+  fs::write(".hvm/src/prof.rs", include_str!("../src/prof.rs"))?;
+  fs::write(".hvm/src/repl.rs", include_str!("../src/repl.rs"))?;
+  fs::write(".hvm/src/rng.rs", include_str!("../src/rng.rs"))?;
   fs::write(".hvm/src/lib.rs", include_str!("../src/lib.rs"))?;
   fs::write(".hvm/src/main.rs", include_str!("../src/main.rs"))?;
   fs::write(".hvm/src/run.rs", include_str!("../src/run.rs"))?;
+  fs::write(".hvm/src/sim.rs", include_str!("../src/sim.rs"))?;
+  fs::write(".hvm/src/trace.rs", include_str!("../src/trace.rs"))?;
   fs::write(".hvm/src/u60.rs", include_str!("../src/u60.rs"))?;
+  fs::write(".hvm/src/verify.rs", include_str!("../src/verify.rs"))?;
   fs::write(".hvm/src/fns.rs", fns_rs)?;
   return Ok(());
 }
 
+// Compiles a book to an executable and runs it, reading back the normal form it prints.
+fn run_compiled(file: &str, book: &run::Book) -> Result<verify::Outcome, Box<dyn std::error::Error>> {
+  compile_book_to_rust_crate(file, book)?;
+  compile_rust_crate_to_executable(file)?;
+  let output = std::process::Command::new(format!("./{}", file.replace(".hvmc", ""))).output()?;
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let norm   = ast::try_parse_net(&stdout.lines().take_while(|line| !line.starts_with("RWTS")).collect::<Vec<_>>().join("\n"))?;
+  Ok(verify::Outcome { strategy: verify::Strategy::Compiled, norm, rwts: None })
+}
+
 pub fn compile_rust_crate_to_executable(f_name: &str) -> Result<(), std::io::Error> {
   let output = std::process::Command::new("cargo").current_dir("./.hvm").arg("build").arg("--release").output()?;
+  if !output.status.success() {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("cargo build failed:\n{}", stderr)));
+  }
   let target = format!("./{}", f_name.replace(".hvmc", ""));
   if std::path::Path::new(&target).exists() {
     fs::remove_file(&target)?;
@@ -175,82 +830,3 @@ pub fn compile_rust_crate_to_executable(f_name: &str) -> Result<(), std::io::Err
   fs::copy("./.hvm/target/release/hvmc", target)?;
   return Ok(());
 }
-
-// TODO: move to hvm-cuda repo
-pub fn gen_cuda_book(book: &run::Book) -> String {
-  use std::collections::BTreeMap;
-
-  // Sort the book.defs by key
-  let mut defs = BTreeMap::new();
-  for (fid, def) in book.defs.iter() {
-    if def.node.len() > 0 {
-      defs.insert(fid, def.clone());
-    }
-  }
-
-  // Initializes code
-  let mut code = String::new();
-
-  // Generate function ids
-  for (i, id) in defs.keys().enumerate() {
-    code.push_str(&format!("const u32 F_{} = 0x{:x};\n", crate::ast::val_to_name(**id), id));
-  }
-  code.push_str("\n");
-
-  // Create book
-  code.push_str("u32 BOOK_DATA[] = {\n");
-
-  // Generate book data
-  for (i, (id, net)) in defs.iter().enumerate() {
-    let node_len = net.node.len();
-    let rdex_len = net.rdex.len();
-
-    code.push_str(&format!("  // @{}\n", crate::ast::val_to_name(**id)));
-
-    // Collect all pointers from root, nodes and rdex into a single buffer
-    code.push_str(&format!("  // .nlen\n"));
-    code.push_str(&format!("  0x{:08X},\n", node_len));
-    code.push_str(&format!("  // .rlen\n"));
-    code.push_str(&format!("  0x{:08X},\n", rdex_len));
-
-    // .node
-    code.push_str("  // .node\n");
-    for (i, node) in net.node.iter().enumerate() {
-      code.push_str(&format!("  0x{:08X},", node.1.0));
-      code.push_str(&format!(" 0x{:08X},", node.2.0));
-      if (i + 1) % 4 == 0 {
-        code.push_str("\n");
-      }
-    }
-    if node_len % 4 != 0 {
-      code.push_str("\n");
-    }
-
-    // .rdex
-    code.push_str("  // .rdex\n");
-    for (i, (a, b)) in net.rdex.iter().enumerate() {
-      code.push_str(&format!("  0x{:08X},", a.0));
-      code.push_str(&format!(" 0x{:08X},", b.0));
-      if (i + 1) % 4 == 0 {
-        code.push_str("\n");
-      }
-    }
-    if rdex_len % 4 != 0 {
-      code.push_str("\n");
-    }
-  }
-
-  code.push_str("};\n\n");
-
-  code.push_str("u32 JUMP_DATA[] = {\n");
-
-  let mut index = 0;
-  for (i, fid) in defs.keys().enumerate() {
-    code.push_str(&format!("  0x{:08X}, 0x{:08X}, // @{}\n", fid, index, crate::ast::val_to_name(**fid)));
-    index += 2 + 2 * defs[fid].node.len() as u32 + 2 * defs[fid].rdex.len() as u32;
-  }
-
-  code.push_str("};");
-
-  return code;
-}
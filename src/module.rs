@@ -0,0 +1,103 @@
+// Modules
+// -------
+// Loads a book from a file, along with the files it imports. Imported definitions are merged into
+// the importing book, and 'import "lib.hvmc" as Lib' prefixes them, and the references to them,
+// with 'Lib.'. Paths are relative to the importing file. Importing the same definition twice, as
+// in diamond imports, is fine, as is importing one that only differs in its variable names, but
+// importing a name that is already defined with a different net is an error, as are two names with
+// the same interned id. Within a file, as before, a later definition replaces an earlier one.
+
+use crate::ast::{self, Book, Net, Tree};
+use crate::run::{Names, Val};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Loads a file, resolving its imports.
+pub fn load_book(path: &str) -> Result<Book, String> {
+  let book = load_book_go(Path::new(path), &mut vec![])?;
+  ast::book_names(&book).map_err(|err| format!("On '{}': {}", path, err))?;
+  return Ok(book);
+}
+
+fn load_book_go(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Book, String> {
+  let canon = path.canonicalize().map_err(|err| format!("Can't read '{}': {}", path.display(), err))?;
+  if stack.contains(&canon) {
+    return Err(format!("Import cycle through '{}'", path.display()));
+  }
+  let code = fs::read_to_string(&canon).map_err(|err| format!("Can't read '{}': {}", path.display(), err))?;
+  let (imports, mut book) = ast::try_parse_module(&code).map_err(|err| format!("On '{}': {}", path.display(), err))?;
+  stack.push(canon);
+  let dir = path.parent().unwrap_or(Path::new(""));
+  for import in imports {
+    let mut lib = load_book_go(&dir.join(&import.path), stack)?;
+    if let Some(name) = &import.name {
+      lib = namespace(lib, name);
+    }
+    merge(&mut book, lib).map_err(|err| format!("On '{}': {}", path.display(), err))?;
+  }
+  stack.pop();
+  return Ok(book);
+}
+
+// Merges definitions into a book.
+pub fn merge(book: &mut Book, defs: Book) -> Result<(), String> {
+  for (name, net) in defs {
+    match book.get(&name) {
+      Some(prev) if ast::net_equiv(prev, &net) => {}
+      Some(_) => return Err(format!("Duplicate definition @{}", name)),
+      None => {
+        book.insert(name, net);
+      }
+    }
+  }
+  return Ok(());
+}
+
+// Prefixes every definition of a book, and the references to them, with 'name.'.
+pub fn namespace(book: Book, name: &str) -> Book {
+  let ids = book.keys().map(|def| (ast::name_to_val(def), ast::name_to_val(&format!("{}.{}", name, def)))).collect();
+  let names = book.keys().map(|def| format!("{}.{}", name, def)).map(|def| (ast::name_to_val(&def), def)).collect::<Names>();
+  book.into_iter().map(|(def, mut net)| {
+    rename_net(&mut net, &ids);
+    // The new names are longer, so they may be interned, and must be kept for printing.
+    let refs = ast::net_refs(&net);
+    if let Some(meta) = &mut net.meta {
+      for nam in refs.into_iter().filter(|nam| ast::is_interned(*nam)) {
+        if let Some(name) = names.get(&nam) {
+          meta.refs.insert(nam, name.clone());
+        }
+      }
+    }
+    (format!("{}.{}", name, def), net)
+  }).collect()
+}
+
+fn rename_tree(tree: &mut Tree, ids: &HashMap<Val, Val>) {
+  match tree {
+    Tree::Ref { nam } => {
+      if let Some(id) = ids.get(nam) {
+        *nam = *id;
+      }
+    }
+    Tree::Con { lft, rgt } | Tree::Tup { lft, rgt } | Tree::Dup { lft, rgt, .. } | Tree::Op2 { lft, rgt, .. } => {
+      rename_tree(lft, ids);
+      rename_tree(rgt, ids);
+    }
+    Tree::Mat { sel, ret } => {
+      rename_tree(sel, ids);
+      rename_tree(ret, ids);
+    }
+    Tree::Op1 { rgt, .. } => rename_tree(rgt, ids),
+    Tree::Era | Tree::Var { .. } | Tree::Num { .. } => {}
+  }
+}
+
+// Renames the references of a net.
+pub fn rename_net(net: &mut Net, ids: &HashMap<Val, Val>) {
+  rename_tree(&mut net.root, ids);
+  for (a, b) in &mut net.rdex {
+    rename_tree(a, ids);
+    rename_tree(b, ids);
+  }
+}
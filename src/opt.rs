@@ -0,0 +1,128 @@
+// Book optimizer
+// --------------
+// Statically reduces the redexes inside each definition, up to a budget of interactions, and
+// inlines small, non-recursive definitions that are called from an active pair. Other references
+// are left alone, except that a REF ~ DUP pair is reduced when the definition doesn't use the DUP's
+// label, as the runtime then copies the REF instead of expanding it. Reductions are performed by
+// the runtime itself, on a scratch net, so the result is an equivalent book. A definition is only
+// replaced when its optimized net isn't larger than the original.
+
+use crate::ast::{self, Book, Net, Tree};
+use crate::run::{self, NetFields, Ptr, Val};
+use std::collections::HashSet;
+
+pub struct Optimizer {
+  pub budget: usize, // max interactions per definition
+  pub inline: usize, // max nodes of an inlined definition
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OptStats {
+  pub defs: usize, // definitions replaced
+  pub rwts: usize, // interactions performed statically
+  pub inln: usize, // calls inlined
+  pub init: usize, // nodes before
+  pub done: usize, // nodes after
+}
+
+impl Default for Optimizer {
+  fn default() -> Self {
+    Optimizer { budget: 1 << 10, inline: 16 }
+  }
+}
+
+fn tree_size(tree: &Tree) -> usize {
+  match tree {
+    Tree::Con { lft, rgt } | Tree::Tup { lft, rgt } | Tree::Dup { lft, rgt, .. } | Tree::Op2 { lft, rgt, .. } => 1 + tree_size(lft) + tree_size(rgt),
+    Tree::Mat { sel, ret } => 1 + tree_size(sel) + tree_size(ret),
+    Tree::Op1 { rgt, .. } => 1 + tree_size(rgt),
+    Tree::Era | Tree::Var { .. } | Tree::Ref { .. } | Tree::Num { .. } => 0,
+  }
+}
+
+// Nodes of a net, as allocated by the runtime.
+pub fn net_size(net: &Net) -> usize {
+  tree_size(&net.root) + net.rdex.iter().map(|(a, b)| tree_size(a) + tree_size(b)).sum::<usize>()
+}
+
+impl Optimizer {
+  // The definitions that can be inlined: small ones that don't reach themselves.
+  pub fn inlinable(&self, book: &run::Book) -> HashSet<Val> {
+    book.defs.iter().filter(|(fid, def)| {
+      let refs = ast::runtime_def_get_inside(def).refs.into_iter().collect::<Vec<_>>();
+      def.node.len() <= self.inline + 1 && !book.reachable(&refs).contains(fid)
+    }).map(|(fid, _)| *fid).collect()
+  }
+
+  // Optimizes a book, returning the new book and what was done.
+  pub fn optimize(&self, book: &Book) -> (Book, OptStats) {
+    let rt_book = ast::book_to_runtime(book);
+    let inline  = self.inlinable(&rt_book);
+    let largest = rt_book.defs.values().map(|def| def.node.len()).max().unwrap_or(0);
+    let nodes   = run::Heap::<false>::init(largest + self.budget * self.inline.max(4) + 1);
+    let mut net = NetFields::new(&nodes);
+    let mut stats = OptStats::default();
+    let mut done = Book::new();
+    for (name, orig) in book {
+      let init = net_size(orig);
+      stats.init += init;
+      match self.optimize_def(&mut net, &rt_book, &inline, ast::name_to_val(name)) {
+        Some((new, rwts, inln)) if rwts > 0 && net_size(&new) <= init => {
+          stats.defs += 1;
+          stats.rwts += rwts;
+          stats.inln += inln;
+          stats.done += net_size(&new);
+          done.insert(name.clone(), new);
+        }
+        _ => {
+          stats.done += init;
+          done.insert(name.clone(), orig.clone());
+        }
+      }
+    }
+    return (done, stats);
+  }
+
+  // Loads a definition on a scratch net and reduces its safe redexes, returning the resulting
+  // net, with the interactions performed and calls inlined.
+  pub fn optimize_def(&self, net: &mut NetFields<false>, book: &run::Book, inline: &HashSet<Val>, fid: Val) -> Option<(Net, usize, usize)> {
+    let def = book.get(fid)?;
+    if def.node.is_empty() {
+      return None;
+    }
+    net.reset();
+    let root = net.load(def);
+    net.link(root, run::ROOT);
+    let mut kept = vec![];
+    let mut rwts = 0;
+    let mut inln = 0;
+    while let Some((a, b)) = net.rdex.pop() {
+      let (r, x) = if a.is_ref() { (a, b) } else { (b, a) };
+      let call = r.is_ref() && x.is_nod();
+      let copy = call && x.is_dup() && book.get(r.val()).map_or(false, |def| !def.labs.contains(&x.lab()));
+      let safe = if call {
+        copy || inline.contains(&r.val())
+      } else {
+        is_valid(a, b)
+      };
+      if safe && rwts < self.budget {
+        if call && !copy {
+          inln += 1;
+        }
+        net.interact(book, a, b);
+        rwts += 1;
+      } else {
+        kept.push((a, b));
+      }
+    }
+    net.rdex = kept;
+    return Some((ast::net_from_runtime(net), rwts, inln));
+  }
+}
+
+// Checks if the runtime can reduce a pair that isn't a call. Invalid pairs are left for the runtime
+// to report, should they be reached at all.
+fn is_valid(a: Ptr, b: Ptr) -> bool {
+  let ops = |p: Ptr| p.is_op1() || p.is_op2() || p.is_mat();
+  !(a.is_ref() && b.is_ref()) && !(ops(a) && ops(b))
+}
@@ -0,0 +1,178 @@
+// Per-definition profiler
+// -----------------------
+// An opt-in profiler that attributes work to definitions. Every allocated node is tagged with the
+// definition it came from: nodes allocated by a dereference belong to the called definition, and
+// nodes allocated by an interaction inherit the origin of the active pair. Each definition then
+// counts its dereferences, its allocated and freed nodes, and the rewrites performed on its nodes.
+// Origins are kept on a table shared by all threads, and counters are merged at the end of a
+// parallel run.
+
+use crate::ast;
+use crate::run::{Book, Loc, Ptr, Val};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+// Counters of a definition.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DefStats {
+  pub dref: usize, // times dereferenced
+  pub node: usize, // nodes allocated
+  pub free: usize, // nodes freed
+  pub rwts: usize, // rewrites on its nodes
+}
+
+// Counters of a definition, atomic.
+#[derive(Default)]
+pub struct AtomicDefStats {
+  pub dref: AtomicUsize, // times dereferenced
+  pub node: AtomicUsize, // nodes allocated
+  pub free: AtomicUsize, // nodes freed
+  pub rwts: AtomicUsize, // rewrites on its nodes
+}
+
+pub struct Profile {
+  pub fids: Arc<Vec<Val>>, // definition id of each index (index 0 is unattributed)
+  pub names: Arc<Vec<String>>, // definition name of each index
+  pub idxs: Arc<HashMap<Val, u32, nohash_hasher::BuildNoHashHasher<Val>>>, // index of each definition id
+  pub orig: Arc<Box<[AtomicU32]>>, // origin index of each node
+  pub stat: Vec<DefStats>, // counters of each index
+  pub curr: u32, // origin of the nodes being allocated
+}
+
+impl DefStats {
+  pub fn add_to(&self, target: &AtomicDefStats) {
+    target.dref.fetch_add(self.dref, Ordering::Relaxed);
+    target.node.fetch_add(self.node, Ordering::Relaxed);
+    target.free.fetch_add(self.free, Ordering::Relaxed);
+    target.rwts.fetch_add(self.rwts, Ordering::Relaxed);
+  }
+
+  // Nodes still alive.
+  pub fn live(&self) -> usize {
+    self.node.saturating_sub(self.free)
+  }
+}
+
+impl AtomicDefStats {
+  pub fn add_to(&self, target: &mut DefStats) {
+    target.dref += self.dref.load(Ordering::Relaxed);
+    target.node += self.node.load(Ordering::Relaxed);
+    target.free += self.free.load(Ordering::Relaxed);
+    target.rwts += self.rwts.load(Ordering::Relaxed);
+  }
+}
+
+impl Profile {
+  // Creates a profiler for a book, on a heap with the given size.
+  pub fn new(book: &Book, size: usize) -> Self {
+    let mut fids = book.defs.keys().copied().collect::<Vec<_>>();
+    fids.sort();
+    fids.insert(0, 0);
+    let idxs = fids.iter().enumerate().skip(1).map(|(i, fid)| (*fid, i as u32)).collect();
+    let names = fids.iter().map(|fid| ast::show_name(*fid, &[&book.names])).collect();
+    // A zeroed buffer is allocated lazily, so that untouched nodes cost no memory.
+    let orig = vec![0u32; size].into_boxed_slice();
+    let orig = unsafe { Box::from_raw(Box::into_raw(orig) as *mut [AtomicU32]) };
+    let stat = vec![DefStats::default(); fids.len()];
+    Profile { fids: Arc::new(fids), names: Arc::new(names), idxs: Arc::new(idxs), orig: Arc::new(orig), stat, curr: 0 }
+  }
+
+  // Creates a profiler for a child thread, sharing the origin table, with zeroed counters.
+  pub fn fork(&self) -> Self {
+    Profile {
+      fids: Arc::clone(&self.fids),
+      names: Arc::clone(&self.names),
+      idxs: Arc::clone(&self.idxs),
+      orig: Arc::clone(&self.orig),
+      stat: vec![DefStats::default(); self.fids.len()],
+      curr: 0,
+    }
+  }
+
+  // Attributes an interaction to the origin of its nodes.
+  #[inline(always)]
+  pub fn interact(&mut self, a: Ptr, b: Ptr) {
+    self.curr = if a.has_loc() {
+      self.orig[a.loc() as usize].load(Ordering::Relaxed)
+    } else if b.has_loc() {
+      self.orig[b.loc() as usize].load(Ordering::Relaxed)
+    } else {
+      0
+    };
+    self.stat[self.curr as usize].rwts += 1;
+  }
+
+  // Attributes a rewrite that isn't an interaction of its own (a skipped pair, a root expansion or
+  // the copy after a dereference) to the current origin, so that the totals add up to RWTS.
+  #[inline(always)]
+  pub fn rewrite(&mut self) {
+    self.stat[self.curr as usize].rwts += 1;
+  }
+
+  // Attributes a dereference, and the nodes it allocates, to the called definition.
+  #[inline(always)]
+  pub fn call(&mut self, fid: Val) {
+    self.curr = self.idxs.get(&fid).copied().unwrap_or(0);
+    self.stat[self.curr as usize].dref += 1;
+  }
+
+  #[inline(always)]
+  pub fn alloc(&mut self, loc: Loc) {
+    self.orig[loc as usize].store(self.curr, Ordering::Relaxed);
+    self.stat[self.curr as usize].node += 1;
+  }
+
+  #[inline(always)]
+  pub fn free(&mut self, loc: Loc) {
+    let orig = self.orig[loc as usize].load(Ordering::Relaxed);
+    self.stat[orig as usize].free += 1;
+  }
+
+  pub fn new_delta(&self) -> Vec<AtomicDefStats> {
+    (0 .. self.fids.len()).map(|_| AtomicDefStats::default()).collect()
+  }
+
+  pub fn add_to(&self, target: &[AtomicDefStats]) {
+    for (stat, target) in self.stat.iter().zip(target) {
+      stat.add_to(target);
+    }
+  }
+
+  pub fn add_from(&mut self, delta: &[AtomicDefStats]) {
+    for (stat, delta) in self.stat.iter_mut().zip(delta) {
+      delta.add_to(stat);
+    }
+  }
+
+  // Counters of each definition that did some work, sorted by rewrites, then dereferences.
+  pub fn entries(&self) -> Vec<(String, DefStats)> {
+    let mut entries = self.names.iter().zip(&self.stat)
+      .enumerate()
+      .filter(|(_, (_, stat))| **stat != DefStats::default())
+      .map(|(i, (name, stat))| (if i == 0 { "(none)".to_string() } else { name.clone() }, *stat))
+      .collect::<Vec<_>>();
+    entries.sort_by(|a, b| b.1.rwts.cmp(&a.1.rwts).then(b.1.dref.cmp(&a.1.dref)).then(a.0.cmp(&b.0)));
+    return entries;
+  }
+
+  pub fn show_table(&self) -> String {
+    let entries = self.entries();
+    let total = entries.iter().map(|(_, stat)| stat.rwts).sum::<usize>().max(1);
+    let width = entries.iter().map(|(name, _)| name.len() + 1).max().unwrap_or(0).max(4);
+    let mut text = format!("{:<width$} {:>12} {:>12} {:>12} {:>12} {:>7}\n", "NAME", "DREF", "NODE", "LIVE", "RWTS", "%RWTS", width = width);
+    for (name, stat) in &entries {
+      let name = if name.starts_with('(') { name.clone() } else { format!("@{}", name) };
+      let perc = stat.rwts as f64 * 100.0 / total as f64;
+      text.push_str(&format!("{:<width$} {:>12} {:>12} {:>12} {:>12} {:>6.2}%\n", name, stat.dref, stat.node, stat.live(), stat.rwts, perc, width = width));
+    }
+    return text;
+  }
+
+  pub fn to_json(&self) -> String {
+    let defs = self.entries().iter().map(|(name, stat)| {
+      format!("    {{ \"name\": \"{}\", \"dref\": {}, \"node\": {}, \"live\": {}, \"rwts\": {} }}", name, stat.dref, stat.node, stat.live(), stat.rwts)
+    }).collect::<Vec<_>>();
+    return format!("{{\n  \"defs\": [\n{}\n  ]\n}}", defs.join(",\n"));
+  }
+}
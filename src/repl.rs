@@ -0,0 +1,167 @@
+// Interactive REPL
+// ----------------
+// Keeps a book in memory, to which definitions can be added or replaced by typing '@name = net',
+// and normalizes any other net typed, reporting its normal form and, optionally, its stats. A
+// single runtime net is allocated and reset between evaluations, so evaluating is cheap.
+
+use crate::ast;
+use crate::run::{self, Book, Rewrites};
+use std::time::Duration;
+
+// How expressions are evaluated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+  Lazy,
+  Eager,
+  Parallel,
+}
+
+pub struct Repl {
+  pub book: ast::Book, // definitions, as written
+  pub defs: Book, // definitions, on the runtime
+  pub net : Option<run::Net>, // runtime net, allocated on first use
+  pub size: usize, // runtime net size, in nodes
+  pub mode: Mode, // evaluation mode
+  pub auto: bool, // show stats after each evaluation
+  pub last: Option<(Rewrites, Duration)>, // stats of the last evaluation
+}
+
+pub const HELP: &str = "\
+Enter '@name = <net>' to add or replace a definition, or a net to normalize it.
+Commands:
+  :load FILE         Load the definitions of a file
+  :show [@NAME]      Show a definition, or the whole book
+  :stats [on|off]    Show the last evaluation's stats, or toggle showing them after each one
+  :mode [MODE]       Show or set the evaluation mode: lazy, eager or parallel
+  :help              Show this message
+  :quit              Exit the REPL";
+
+impl Mode {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Mode::Lazy     => "lazy",
+      Mode::Eager    => "eager",
+      Mode::Parallel => "parallel",
+    }
+  }
+}
+
+impl Repl {
+  // Creates a REPL with an empty book, evaluating on a net with the given size.
+  pub fn new(size: usize) -> Self {
+    Repl { book: ast::Book::new(), defs: Book::new(), net: None, size, mode: Mode::Eager, auto: false, last: None }
+  }
+
+  // Adds or replaces definitions, returning their names.
+  pub fn define(&mut self, book: ast::Book) -> Result<Vec<String>, String> {
+    let mut names = self.defs.names.clone();
+    for (name, net) in &book {
+      ast::check_net(net).map_err(|err| format!("on @{}: {}", name, err))?;
+      ast::add_def_names(&mut names, name, net)?;
+    }
+    for (name, net) in &book {
+      self.defs.def(ast::name_to_val(name), ast::net_to_runtime_def(net));
+    }
+    self.defs.names = names;
+    ast::runtime_book_set_labs(&mut self.defs);
+    let names = book.keys().cloned().collect();
+    self.book.extend(book);
+    return Ok(names);
+  }
+
+  pub fn load(&mut self, file: &str) -> Result<String, String> {
+    let defs = self.define(crate::module::load_book(file)?)?;
+    Ok(format!("Loaded {} definitions from '{}'.", defs.len(), file))
+  }
+
+  // Normalizes a net, returning its normal form.
+  pub fn eval(&mut self, net: &ast::Net) -> Result<ast::Net, String> {
+    let lazy = self.mode == Mode::Lazy;
+    // The net's heap is either lazy or eager, so switching between these allocates a new one.
+    if self.net.as_ref().map_or(true, |net| matches!(net, run::Net::Lazy(_)) != lazy) {
+      self.net = None;
+      self.net = Some(run::Net::new(self.size, lazy));
+    }
+    let rt_net = self.net.as_mut().unwrap();
+    rt_net.boot_net(&self.defs, net)?;
+    let begin = std::time::Instant::now();
+    match self.mode {
+      Mode::Parallel => rt_net.parallel_normal(&self.defs),
+      _              => rt_net.normal(&self.defs),
+    }
+    self.last = Some((rt_net.get_rewrites(), begin.elapsed()));
+    Ok(rt_net.to_net())
+  }
+
+  pub fn show_stats(&self) -> String {
+    match self.last {
+      Some((rwts, time)) => format!("RWTS: {} (ANNI {}, COMM {}, ERAS {}, DREF {}, OPER {}) | TIME: {:.3} s",
+        rwts.total(), rwts.anni, rwts.comm, rwts.eras, rwts.dref, rwts.oper, time.as_secs_f64()),
+      None => format!("Nothing evaluated yet."),
+    }
+  }
+
+  // Runs a line of input, returning its output, or None to quit.
+  pub fn exec(&mut self, line: &str) -> Option<Result<String, String>> {
+    let line = line.trim();
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let arg = words.get(1).copied();
+    let out = match words.first().copied().unwrap_or("") {
+      "" => Ok(String::new()),
+      ":l" | ":load" => match arg {
+        Some(file) => self.load(file),
+        None       => Err(format!("missing file name")),
+      },
+      ":s" | ":show" => match arg.map(|name| name.trim_start_matches('@')) {
+        Some(name) => match self.book.get(name) {
+          Some(net) => Ok(format!("@{} = {}", name, ast::show_net(net))),
+          None      => Err(format!("unknown definition @{}", name)),
+        },
+        None => Ok(ast::show_book(&self.book).trim_end().to_string()),
+      },
+      ":stats" => match arg {
+        Some("on")  => { self.auto = true; Ok(format!("Showing stats after each evaluation.")) }
+        Some("off") => { self.auto = false; Ok(format!("Not showing stats after each evaluation.")) }
+        Some(arg)   => Err(format!("expected 'on' or 'off', found '{}'", arg)),
+        None        => Ok(self.show_stats()),
+      },
+      ":m" | ":mode" => match arg {
+        Some("lazy")     => { self.mode = Mode::Lazy; Ok(format!("Mode set to lazy.")) }
+        Some("eager")    => { self.mode = Mode::Eager; Ok(format!("Mode set to eager.")) }
+        Some("parallel") => { self.mode = Mode::Parallel; Ok(format!("Mode set to parallel.")) }
+        Some(arg)        => Err(format!("unknown mode '{}'", arg)),
+        None             => Ok(format!("Mode is {}.", self.mode.name())),
+      },
+      ":h" | ":help" => Ok(HELP.to_string()),
+      ":q" | ":quit" => return None,
+      cmd if cmd.starts_with(':') => Err(format!("unknown command '{}' (try ':help')", cmd)),
+      _ if is_definition(line) => ast::try_parse_book(line).and_then(|book| self.define(book)).map(|names| {
+        names.iter().map(|name| {
+          let undefined = ast::net_refs(&self.book[name]).into_iter().filter(|fid| self.defs.get(*fid).is_none()).collect::<Vec<_>>();
+          if undefined.is_empty() {
+            format!("Defined @{}.", name)
+          } else {
+            format!("Defined @{} (refers to undefined {}).", name, undefined.iter().map(|fid| format!("@{}", ast::show_name(*fid, &[&self.defs.names]))).collect::<Vec<_>>().join(", "))
+          }
+        }).collect::<Vec<_>>().join("\n")
+      }),
+      _ => ast::try_parse_net(line).and_then(|net| self.eval(&net)).map(|norm| {
+        let mut text = ast::show_net_in(&norm, &self.defs.names);
+        if self.auto {
+          text.push('\n');
+          text.push_str(&self.show_stats());
+        }
+        text
+      }),
+    };
+    return Some(out);
+  }
+}
+
+// Checks if a line defines something ('@name = net'), rather than being a net ('@name').
+fn is_definition(line: &str) -> bool {
+  match line.strip_prefix('@') {
+    Some(rest) => rest.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_' || c == '.').trim_start().starts_with('='),
+    None       => false,
+  }
+}
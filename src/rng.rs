@@ -0,0 +1,34 @@
+// Random numbers
+// --------------
+// A seeded generator, for the books 'fuzz' generates and the thread orders 'simulated_normal' draws,
+// so that both can be replayed from their seed.
+
+// A pseudo-random generator (xorshift64*).
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    // Scrambles the seed (splitmix64), so that nearby seeds give unrelated streams, and none is 0.
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    Rng((z ^ (z >> 31)) | 1)
+  }
+
+  pub fn next(&mut self) -> u64 {
+    self.0 ^= self.0 >> 12;
+    self.0 ^= self.0 << 25;
+    self.0 ^= self.0 >> 27;
+    self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+  }
+
+  // A number in 0 .. n.
+  pub fn below(&mut self, n: usize) -> usize {
+    (self.next() % n as u64) as usize
+  }
+
+  pub fn chance(&mut self, percent: usize) -> bool {
+    self.below(100) < percent
+  }
+}
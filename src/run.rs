@@ -8,9 +8,12 @@
 // space evaluation of recursive functions on Scott encoded datatypes.
 
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::sync::{Arc, Barrier};
+use std::sync::{Arc, Barrier, Mutex};
 use std::collections::HashMap;
 use std::collections::HashSet;
+use crate::prof;
+use crate::rng::Rng;
+use crate::trace;
 use crate::u60;
 
 pub type Tag  = u8;
@@ -54,6 +57,10 @@ pub const LSH: Lab = 0x0E; // left-shift
 pub const RSH: Lab = 0x0F; // right-shift
 pub const NOT: Lab = 0x10; // logical-not
 
+const SHARE_LIMIT : usize = 1 << 12; // max share redexes per split
+const LOCAL_LIMIT : usize = 1 << 18; // max local rewrites per epoch
+const SIMULATED_TIDS : usize = 4; // threads simulated by 'simulated_normal', if not set
+
 pub const ERAS: Ptr = Ptr::new(ERA, 0, 0);
 pub const ROOT: Ptr = Ptr::new(VR2, 0, 0);
 pub const NULL: Ptr = Ptr(0x0000_0000_0000_0000);
@@ -127,7 +134,36 @@ pub struct AtomicRewrites {
   pub oper: AtomicUsize, // oper rewrites
 }
 
+// Live node counter. On child threads, nodes may be freed by a thread other than the one that
+// allocated them, so 'peak' is the peak of allocated minus freed nodes since the fork. Since live
+// nodes only grow between frees, the peak is updated on 'free', and completed by 'peak()'.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Usage {
+  pub alloc: usize, // nodes allocated
+  pub freed: usize, // nodes freed
+  pub peak : usize, // peak live nodes, as of the last free
+  pub bound: bool, // whether the peak is an upper bound, summed over parallel threads
+}
+
+// Live node counter, atomic.
+#[derive(Default)]
+pub struct AtomicUsage {
+  pub alloc: AtomicUsize, // nodes allocated
+  pub freed: AtomicUsize, // nodes freed
+  pub peak : AtomicUsize, // sum of peak live nodes
+}
+
+// Scheduler statistics.
+#[derive(Clone, Debug, Default)]
+pub struct Sched {
+  pub tids  : usize, // thread count of the last run
+  pub epochs: usize, // reduce/expand rounds
+  pub splits: usize, // redex sharing rounds
+  pub trwts : Vec<usize>, // rewrites per thread
+}
+
 // An allocation area delimiter
+#[derive(Copy, Clone, Debug)]
 pub struct Area {
   pub init: usize, // first allocation index
   pub size: usize, // total nodes in area
@@ -145,6 +181,14 @@ where [(); LAZY as usize]: {
   pub area: Area, // allocation area
   pub next: usize, // next allocation index within area
   pub rwts: Rewrites, // rewrite count
+  pub tlog: Vec<trace::Event>, // interaction log ('trace' feature)
+  pub tcur: Option<usize>, // index of the logged interaction being performed ('trace' feature)
+  pub prof: Option<prof::Profile>, // per-definition profiler
+  pub schd: Sched, // scheduler stats
+  pub used: Usage, // live node counter
+  pub chek: bool, // check integrity after each epoch
+  pub thrd: usize, // threads used by 'parallel_normal', or 0 for one per core
+  pub dirt: Vec<Area>, // areas used by child threads
 }
 
 // A compact closed net, used for dereferences.
@@ -153,11 +197,16 @@ pub struct Def {
   pub labs: HashSet<Lab, nohash_hasher::BuildNoHashHasher<Lab>>,
   pub rdex: Vec<(Ptr, Ptr)>,
   pub node: Vec<((), Ptr, Ptr)>,
+  pub vars: HashMap<(Loc, Port), String>, // variable names, by the ports their ends are at
 }
 
+// Names of definitions, by id. Interned names can only be recovered from ids through one.
+pub type Names = HashMap<Val, String, nohash_hasher::BuildNoHashHasher<Val>>;
+
 // A map of id to definitions (closed nets).
 pub struct Book {
   pub defs: HashMap<Val, Def, nohash_hasher::BuildNoHashHasher<Val>>,
+  pub names: Names, // name of each definition, and of each reference it has
 }
 
 impl Ptr {
@@ -327,6 +376,7 @@ impl Book {
   pub fn new() -> Self {
     Book {
       defs: HashMap::with_hasher(std::hash::BuildHasherDefault::default()),
+      names: HashMap::with_hasher(std::hash::BuildHasherDefault::default()),
     }
   }
 
@@ -339,6 +389,29 @@ impl Book {
   pub fn get(&self, name: Val) -> Option<&Def> {
     self.defs.get(&name)
   }
+
+  // The definitions reachable from the given ones, through their references.
+  pub fn reachable(&self, entries: &[Val]) -> HashSet<Val> {
+    let mut seen = HashSet::new();
+    let mut visit = entries.to_vec();
+    while let Some(fid) = visit.pop() {
+      if seen.insert(fid) {
+        if let Some(def) = self.defs.get(&fid) {
+          visit.extend(crate::ast::runtime_def_get_inside(def).refs);
+        }
+      }
+    }
+    return seen;
+  }
+
+  // Removes the definitions that aren't reachable from the given ones, returning how many.
+  pub fn prune(&mut self, entries: &[Val]) -> usize {
+    let seen = self.reachable(entries);
+    let size = self.defs.len();
+    self.defs.retain(|fid, _| seen.contains(fid));
+    self.names.retain(|fid, _| seen.contains(fid));
+    return size - self.defs.len();
+  }
 }
 
 impl Def {
@@ -347,6 +420,7 @@ impl Def {
       labs: HashSet::with_hasher(std::hash::BuildHasherDefault::default()),
       rdex: vec![],
       node: vec![],
+      vars: HashMap::new(),
     }
   }
 }
@@ -470,6 +544,36 @@ impl Rewrites {
 
 }
 
+impl Usage {
+  // Current live nodes.
+  pub fn live(&self) -> usize {
+    self.alloc.saturating_sub(self.freed)
+  }
+
+  // Peak live nodes.
+  pub fn peak(&self) -> usize {
+    self.peak.max(self.live())
+  }
+
+  pub fn add_to(&self, target: &AtomicUsage) {
+    target.alloc.fetch_add(self.alloc, Ordering::Relaxed);
+    target.freed.fetch_add(self.freed, Ordering::Relaxed);
+    target.peak.fetch_add(self.peak(), Ordering::Relaxed);
+  }
+}
+
+impl AtomicUsage {
+  // Merges the counters of 'tids' child threads. Since threads don't peak at the same time, the
+  // summed peaks of several threads only give an upper bound of the peak live nodes.
+  pub fn add_to(&self, target: &mut Usage, tids: usize) {
+    let peak = target.live() + self.peak.load(Ordering::Relaxed);
+    target.peak   = target.peak().max(peak);
+    target.alloc += self.alloc.load(Ordering::Relaxed);
+    target.freed += self.freed.load(Ordering::Relaxed);
+    target.bound |= tids > 1;
+  }
+}
+
 impl AtomicRewrites {
   pub fn new() -> Self {
     AtomicRewrites {
@@ -503,6 +607,14 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
       area: Area { init: 0, size: nodes.len() },
       next: 0,
       rwts: Rewrites::new(),
+      tlog: vec![],
+      tcur: None,
+      prof: None,
+      schd: Sched::default(),
+      used: Usage::default(),
+      chek: false,
+      thrd: 0,
+      dirt: vec![],
     }
   }
 
@@ -511,6 +623,72 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
     self.heap.set_root(Ptr::big(REF, root_id));
   }
 
+  // Resets the net and boots from a definition, applied to the given arguments, as in:
+  // @name ~ (arg0 (arg1 ... root))
+  pub fn boot_with(&mut self, book: &Book, name: &str, args: &[crate::ast::Tree]) -> Result<(), String> {
+    use crate::ast::{Net, Tree};
+    let fid = crate::ast::name_to_val(name);
+    if !book.defs.contains_key(&fid) {
+      return Err(format!("unknown definition @{}", name));
+    }
+    if args.is_empty() {
+      self.reset();
+      self.boot(fid);
+      return Ok(());
+    }
+    let root = Tree::Var { nam: "%root".to_string() }; // not a valid user name, so it can't clash
+    let mut app = root.clone();
+    for arg in args.iter().rev() {
+      app = Tree::Con { lft: Box::new(arg.clone()), rgt: Box::new(app) };
+    }
+    return self.boot_net(book, &Net::new(root, vec![(Tree::Ref { nam: fid }, app)]));
+  }
+
+  // Resets the net and boots from an arbitrary net, which may refer to the book's definitions.
+  pub fn boot_net(&mut self, book: &Book, net: &crate::ast::Net) -> Result<(), String> {
+    crate::ast::check_net(net)?;
+    if let Some(fid) = crate::ast::net_refs(net).into_iter().find(|fid| !book.defs.contains_key(fid)) {
+      let name = match &net.meta {
+        Some(meta) => crate::ast::show_name(fid, &[&meta.refs]),
+        None       => crate::ast::val_to_name(fid),
+      };
+      return Err(format!("unknown definition @{}", name));
+    }
+    // Loads it as a closed net, like a dereference does, so that lazy nets link their parents.
+    let def = crate::ast::net_to_runtime_def(net);
+    self.reset();
+    let root = self.load(&def);
+    self.link(root, ROOT);
+    return Ok(());
+  }
+
+  // Clears the heap areas used so far, the redexes and all counters, so that the net can be booted
+  // again. Since only used areas are cleared, resetting after a small run is cheap.
+  pub fn reset(&mut self) {
+    let mut dirt = std::mem::take(&mut self.dirt);
+    dirt.push(Area { init: self.area.init, size: (self.next + 1).min(self.area.size) });
+    for area in dirt {
+      for loc in area.init .. area.init + area.size {
+        self.heap.set(loc as Loc, P1, NULL);
+        self.heap.set(loc as Loc, P2, NULL);
+        if LAZY {
+          self.heap.set_pri(loc as Loc, NULL, NULL);
+        }
+      }
+    }
+    self.heap.set_root(NULL);
+    self.rdex.clear();
+    self.next = 0;
+    self.rwts = Rewrites::new();
+    self.tlog.clear();
+    self.tcur = None;
+    self.schd = Sched::default();
+    self.used = Usage::default();
+    if let Some(prof) = &mut self.prof {
+      *prof = prof.fork();
+    }
+  }
+
   // Total rewrite count.
   pub fn rewrites(&self) -> usize {
     return self.rwts.anni + self.rwts.comm + self.rwts.eras + self.rwts.dref + self.rwts.oper;
@@ -535,10 +713,27 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
     };
     self.heap.set(index, P1, LOCK);
     self.heap.set(index, P2, LOCK);
-    //println!("ALLOC {}", index);
+    #[cfg(feature = "trace")]
+    if let Some(event) = self.tcur.and_then(|idx| self.tlog.get_mut(idx)) {
+      event.locs.push(index);
+    }
+    if let Some(prof) = &mut self.prof {
+      prof.alloc(index);
+    }
+    self.used.alloc += 1;
     index
   }
 
+  // Records that a node died. Its slots are cleared by the links that consume it.
+  #[inline(always)]
+  pub fn free(&mut self, loc: Loc) {
+    if let Some(prof) = &mut self.prof {
+      prof.free(loc);
+    }
+    self.used.peak = self.used.peak.max(self.used.live());
+    self.used.freed += 1;
+  }
+
   // Gets a pointer's target.
   #[inline(always)]
   pub fn get_target(&self, ptr: Ptr) -> Ptr {
@@ -590,6 +785,11 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
   pub fn redux(&mut self, a: Ptr, b: Ptr) {
     if Ptr::can_skip(a, b) {
       self.rwts.eras += 1;
+      if let Some(prof) = &mut self.prof {
+        prof.rewrite();
+      }
+      #[cfg(feature = "trace")]
+      self.tlog.push(trace::Event::new(self.tid, trace::Rule::Void, a, b));
     } else if !LAZY {
       self.rdex.push((a, b));
     } else {
@@ -792,45 +992,58 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
     }
   }
   
+  // Logs the interaction being performed, if built with the 'trace' feature.
+  #[inline(always)]
+  #[allow(unused_variables)]
+  fn record(&mut self, rule: trace::Rule, a: Ptr, b: Ptr) {
+    #[cfg(feature = "trace")]
+    {
+      self.tcur = Some(self.tlog.len());
+      self.tlog.push(trace::Event::new(self.tid, rule, a, b));
+    }
+  }
+
   // Performs an interaction over a redex.
   #[inline(always)]
   pub fn interact(&mut self, book: &Book, a: Ptr, b: Ptr) {
-    //println!("inter {} ~ {}", a.view(), b.view());
+    if let Some(prof) = &mut self.prof {
+      prof.interact(a, b);
+    }
     match (a.tag(), b.tag()) {
       (REF   , OP2..) => self.call(book, a, b),
       (OP2.. , REF  ) => self.call(book, b, a),
-      (LAM.. , LAM..) if a.lab() == b.lab() => self.anni(a, b),
-      (LAM.. , LAM..) => self.comm(a, b),
-      (LAM.. , ERA  ) => self.era2(a),
-      (ERA   , LAM..) => self.era2(b),
-      (REF   , ERA  ) => self.rwts.eras += 1,
-      (ERA   , REF  ) => self.rwts.eras += 1,
-      (REF   , NUM  ) => self.rwts.eras += 1,
-      (NUM   , REF  ) => self.rwts.eras += 1,
-      (ERA   , ERA  ) => self.rwts.eras += 1,
-      (LAM.. , NUM  ) => self.copy(a, b),
-      (NUM   , LAM..) => self.copy(b, a),
-      (NUM   , ERA  ) => self.rwts.eras += 1,
-      (ERA   , NUM  ) => self.rwts.eras += 1,
-      (NUM   , NUM  ) => self.rwts.eras += 1,
-      (OP2   , NUM  ) => self.op2n(a, b),
-      (NUM   , OP2  ) => self.op2n(b, a),
-      (OP1   , NUM  ) => self.op1n(a, b),
-      (NUM   , OP1  ) => self.op1n(b, a),
-      (OP2   , LAM..) => self.comm(a, b),
-      (LAM.. , OP2  ) => self.comm(b, a),
-      (OP1   , LAM..) => self.pass(a, b),
-      (LAM.. , OP1  ) => self.pass(b, a),
-      (OP2   , ERA  ) => self.era2(a),
-      (ERA   , OP2  ) => self.era2(b),
-      (OP1   , ERA  ) => self.era1(a),
-      (ERA   , OP1  ) => self.era1(b),
-      (MAT   , NUM  ) => self.mtch(a, b),
-      (NUM   , MAT  ) => self.mtch(b, a),
-      (MAT   , LAM..) => self.comm(a, b),
-      (LAM.. , MAT  ) => self.comm(b, a),
-      (MAT   , ERA  ) => self.era2(a),
-      (ERA   , MAT  ) => self.era2(b),
+      (LAM.. , LAM..) if a.lab() == b.lab() => { self.record(trace::Rule::Anni, a, b); self.anni(a, b) }
+      (LAM.. , LAM..) => { self.record(trace::Rule::Comm, a, b); self.comm(a, b) }
+      (LAM.. , ERA  ) => { self.record(trace::Rule::Era2, a, b); self.era2(a) }
+      (ERA   , LAM..) => { self.record(trace::Rule::Era2, a, b); self.era2(b) }
+      (REF   , ERA  ) => { self.record(trace::Rule::Void, a, b); self.rwts.eras += 1 }
+      (ERA   , REF  ) => { self.record(trace::Rule::Void, a, b); self.rwts.eras += 1 }
+      (REF   , NUM  ) => { self.record(trace::Rule::Void, a, b); self.rwts.eras += 1 }
+      (NUM   , REF  ) => { self.record(trace::Rule::Void, a, b); self.rwts.eras += 1 }
+      (ERA   , ERA  ) => { self.record(trace::Rule::Void, a, b); self.rwts.eras += 1 }
+      (LAM.. , NUM  ) => { self.record(trace::Rule::Copy, a, b); self.copy(a, b) }
+      (NUM   , LAM..) => { self.record(trace::Rule::Copy, a, b); self.copy(b, a) }
+      (NUM   , ERA  ) => { self.record(trace::Rule::Void, a, b); self.rwts.eras += 1 }
+      (ERA   , NUM  ) => { self.record(trace::Rule::Void, a, b); self.rwts.eras += 1 }
+      (NUM   , NUM  ) => { self.record(trace::Rule::Void, a, b); self.rwts.eras += 1 }
+      (OP2   , NUM  ) => { self.record(trace::Rule::Op2n, a, b); self.op2n(a, b) }
+      (NUM   , OP2  ) => { self.record(trace::Rule::Op2n, a, b); self.op2n(b, a) }
+      (OP1   , NUM  ) => { self.record(trace::Rule::Op1n, a, b); self.op1n(a, b) }
+      (NUM   , OP1  ) => { self.record(trace::Rule::Op1n, a, b); self.op1n(b, a) }
+      (OP2   , LAM..) => { self.record(trace::Rule::Comm, a, b); self.comm(a, b) }
+      (LAM.. , OP2  ) => { self.record(trace::Rule::Comm, a, b); self.comm(b, a) }
+      (OP1   , LAM..) => { self.record(trace::Rule::Pass, a, b); self.pass(a, b) }
+      (LAM.. , OP1  ) => { self.record(trace::Rule::Pass, a, b); self.pass(b, a) }
+      (OP2   , ERA  ) => { self.record(trace::Rule::Era2, a, b); self.era2(a) }
+      (ERA   , OP2  ) => { self.record(trace::Rule::Era2, a, b); self.era2(b) }
+      (OP1   , ERA  ) => { self.record(trace::Rule::Era1, a, b); self.era1(a) }
+      (ERA   , OP1  ) => { self.record(trace::Rule::Era1, a, b); self.era1(b) }
+      (MAT   , NUM  ) => { self.record(trace::Rule::Mtch, a, b); self.mtch(a, b) }
+      (NUM   , MAT  ) => { self.record(trace::Rule::Mtch, a, b); self.mtch(b, a) }
+      (MAT   , LAM..) => { self.record(trace::Rule::Comm, a, b); self.comm(a, b) }
+      (LAM.. , MAT  ) => { self.record(trace::Rule::Comm, a, b); self.comm(b, a) }
+      (MAT   , ERA  ) => { self.record(trace::Rule::Era2, a, b); self.era2(a) }
+      (ERA   , MAT  ) => { self.record(trace::Rule::Era2, a, b); self.era2(b) }
       _ => {
         println!("Invalid interaction: {} ~ {}", a.view(), b.view());
         unreachable!();
@@ -840,6 +1053,8 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
 
   pub fn anni(&mut self, a: Ptr, b: Ptr) {
     self.rwts.anni += 1;
+    self.free(a.loc());
+    self.free(b.loc());
     let a1 = Ptr::new(VR1, 0, a.loc());
     let b1 = Ptr::new(VR1, 0, b.loc());
     self.atomic_link(a1, b1);
@@ -850,6 +1065,8 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
 
   pub fn comm(&mut self, a: Ptr, b: Ptr) {
     self.rwts.comm += 1;
+    self.free(a.loc());
+    self.free(b.loc());
     let loc0 = self.alloc();
     let loc1 = self.alloc();
     let loc2 = self.alloc();
@@ -874,6 +1091,7 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
 
   pub fn era2(&mut self, a: Ptr) {
     self.rwts.eras += 1;
+    self.free(a.loc());
     let a1 = Ptr::new(VR1, 0, a.loc());
     self.half_atomic_link(a1, ERAS);
     let a2 = Ptr::new(VR2, 0, a.loc());
@@ -882,12 +1100,16 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
 
   pub fn era1(&mut self, a: Ptr) {
     self.rwts.eras += 1;
+    self.free(a.loc());
+    self.heap.set(a.loc(), P1, NULL);
     let a2 = Ptr::new(VR2, 0, a.loc());
     self.half_atomic_link(a2, ERAS);
   }
 
   pub fn pass(&mut self, a: Ptr, b: Ptr) {
     self.rwts.comm += 1;
+    self.free(a.loc());
+    self.free(b.loc());
     let loc0 = self.alloc();
     let loc1 = self.alloc();
     let loc2 = self.alloc();
@@ -897,6 +1119,7 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
     self.heap.set(loc1, P2, Ptr::new(VR1, 0, loc0));
     self.heap.set(loc2, P1, self.heap.get(a.loc(), P1));
     self.heap.set(loc2, P2, Ptr::new(VR2, 0, loc0));
+    self.heap.set(a.loc(), P1, NULL);
     let a2 = Ptr::new(VR2, 0, a.loc());
     self.half_atomic_link(a2, Ptr::new(b.tag(), b.lab(), loc0));
     let b1 = Ptr::new(VR1, 0, b.loc());
@@ -907,6 +1130,7 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
 
   pub fn copy(&mut self, a: Ptr, b: Ptr) {
     self.rwts.comm += 1;
+    self.free(a.loc());
     let a1 = Ptr::new(VR1, 0, a.loc());
     self.half_atomic_link(a1, b);
     let a2 = Ptr::new(VR2, 0, a.loc());
@@ -915,6 +1139,7 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
 
   pub fn mtch(&mut self, a: Ptr, b: Ptr) {
     self.rwts.oper += 1;
+    self.free(a.loc());
     let a1 = Ptr::new(VR1, 0, a.loc()); // branch
     let a2 = Ptr::new(VR2, 0, a.loc()); // return
     if b.val() == 0 {
@@ -939,6 +1164,7 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
 
   pub fn op2n(&mut self, a: Ptr, b: Ptr) {
     self.rwts.oper += 1;
+    self.free(a.loc());
     let loc0 = self.alloc();
     let a1 = Ptr::new(VR1, 0, a.loc());
     let a2 = Ptr::new(VR2, 0, a.loc());
@@ -951,6 +1177,8 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
     self.rwts.oper += 1;
     let op = a.lab();
     let v0 = self.heap.get(a.loc(), P1).val();
+    self.free(a.loc());
+    self.heap.set(a.loc(), P1, NULL);
     let v1 = b.val();
     let v2 = self.op(op, v0, v1);
     let a2 = Ptr::new(VR2, 0, a.loc());
@@ -984,8 +1212,11 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
   // Expands a closed net.
   #[inline(always)]
   pub fn call(&mut self, book: &Book, ptr: Ptr, trg: Ptr) {
-    //println!("call {} {}", ptr.view(), trg.view());
     self.rwts.dref += 1;
+    self.record(trace::Rule::Dref, ptr, trg);
+    if let Some(prof) = &mut self.prof {
+      prof.call(ptr.val());
+    }
     let mut ptr = ptr;
     // FIXME: change "while" to "if" once lang prevents refs from returning refs
     if ptr.is_ref() {
@@ -997,36 +1228,45 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
       let fid = ptr.val();
       let got = book.get(fid).unwrap();
       if !LAZY && trg.is_dup() && !got.labs.contains(&trg.lab()) {
+        if let Some(prof) = &mut self.prof {
+          prof.rewrite();
+        }
         return self.copy(trg, ptr);
       } else if got.node.len() > 0 {
-        let len = got.node.len() - 1;
-        // Allocate space.
-        for i in 0 .. len {
-          *unsafe { self.locs.get_unchecked_mut(1 + i) } = self.alloc();
-        }
-        // Load nodes, adjusted.
-        for i in 0 .. len {
-          let p1 = self.adjust(unsafe { got.node.get_unchecked(1 + i) }.1);
-          let p2 = self.adjust(unsafe { got.node.get_unchecked(1 + i) }.2);
-          let lc = *unsafe { self.locs.get_unchecked(1 + i) };
-          //println!(":: link loc={} [{} {}]", lc, p1.view(), p2.view());
-          if p1 != ROOT { self.link(Ptr::new(VR1, 0, lc), p1); }
-          if p2 != ROOT { self.link(Ptr::new(VR2, 0, lc), p2); }
-        }
-        // Load redexes, adjusted.
-        for r in &got.rdex {
-          let p1 = self.adjust(r.0);
-          let p2 = self.adjust(r.1);
-          self.redux(p1, p2);
-          //self.rdex.push((p1, p2));
-        }
-        // Load root, adjusted.
-        ptr = self.adjust(got.node[0].2);
+        ptr = self.load(got);
       }
     }
     self.link(ptr, trg);
   }
 
+  // Allocates a closed net's nodes and redexes, returning its root.
+  #[inline(always)]
+  pub fn load(&mut self, got: &Def) -> Ptr {
+    let len = got.node.len() - 1;
+    // Allocate space.
+    for i in 0 .. len {
+      *unsafe { self.locs.get_unchecked_mut(1 + i) } = self.alloc();
+    }
+    // Load nodes, adjusted.
+    for i in 0 .. len {
+      let p1 = self.adjust(unsafe { got.node.get_unchecked(1 + i) }.1);
+      let p2 = self.adjust(unsafe { got.node.get_unchecked(1 + i) }.2);
+      let lc = *unsafe { self.locs.get_unchecked(1 + i) };
+      //println!(":: link loc={} [{} {}]", lc, p1.view(), p2.view());
+      if p1 != ROOT { self.link(Ptr::new(VR1, 0, lc), p1); }
+      if p2 != ROOT { self.link(Ptr::new(VR2, 0, lc), p2); }
+    }
+    // Load redexes, adjusted.
+    for r in &got.rdex {
+      let p1 = self.adjust(r.0);
+      let p2 = self.adjust(r.1);
+      self.redux(p1, p2);
+      //self.rdex.push((p1, p2));
+    }
+    // Load root, adjusted.
+    return self.adjust(got.node[0].2);
+  }
+
   // Adjusts dereferenced pointer locations.
   #[inline(always)]
   fn adjust(&mut self, ptr: Ptr) -> Ptr {
@@ -1060,6 +1300,131 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
     return txt;
   }
 
+  // Checks the heap's consistency, walking from the root and the redexes. Every variable must point
+  // to a variable pointing back to it, every node must be reachable once, and no reachable cell may
+  // be null or locked. Redirections may exist mid-run, but are reported on normal nets, in which
+  // the whole heap is also scanned for leftover locked cells. Returns the reachable node count.
+  pub fn check_integrity(&self, normal: bool) -> Result<usize, String> {
+    const MAX_ERRORS: usize = 16;
+    let mut errs = vec![];
+    let mut seen = HashSet::<Loc, nohash_hasher::BuildNoHashHasher<Loc>>::default();
+    let mut visit = vec![(self.heap.get_root(), ROOT)];
+    for (a, b) in &self.rdex {
+      for ptr in [*a, *b] {
+        if ptr.is_pri() {
+          visit.push((ptr, NULL));
+        } else {
+          errs.push(format!("redex {} ~ {} holds a non-principal port", a.view(), b.view()));
+        }
+      }
+    }
+    while let Some((ptr, dir)) = visit.pop() {
+      if errs.len() >= MAX_ERRORS {
+        errs.push(format!("..."));
+        break;
+      }
+      let at = if dir == NULL { format!("redex") } else { format!("{}", dir.view()) };
+      if ptr == LOCK || ptr == GONE {
+        errs.push(format!("locked cell at {}", at));
+      } else if ptr == NULL {
+        errs.push(format!("null cell at {}", at));
+      } else if ptr.is_var() {
+        let back = self.get_target(ptr);
+        if back.is_red() {
+          if normal {
+            errs.push(format!("dangling redirection at {}", ptr.view()));
+          }
+        } else if back != dir {
+          errs.push(format!("{} points to {}, which points to {}", at, ptr.view(), back.view()));
+        }
+      } else if ptr.is_red() {
+        if normal {
+          errs.push(format!("dangling redirection at {}", at));
+        }
+      } else if ptr.is_nod() {
+        if !seen.insert(ptr.loc()) {
+          errs.push(format!("node {:08x} is reachable twice", ptr.loc()));
+        } else {
+          visit.push((self.heap.get(ptr.loc(), P2), Ptr::new(VR2, 0, ptr.loc())));
+          visit.push((self.heap.get(ptr.loc(), P1), Ptr::new(VR1, 0, ptr.loc())));
+        }
+      }
+    }
+    if normal {
+      for loc in 0 .. self.heap.nodes.len() as Loc {
+        if errs.len() >= MAX_ERRORS {
+          break;
+        }
+        for port in [P1, P2] {
+          let got = self.heap.get(loc, port);
+          if got == LOCK || got == GONE {
+            errs.push(format!("locked cell at {}", Ptr::new(VR1 + port as Tag, 0, loc).view()));
+          }
+        }
+      }
+    }
+    if errs.is_empty() {
+      return Ok(seen.len());
+    } else {
+      return Err(errs.join("\n"));
+    }
+  }
+
+  // Marks the nodes connected to the root and the redexes, on a bitset indexed by location. Both
+  // principal ports and variables are followed, since a node may only be connected by an aux port.
+  pub fn reachable(&self) -> Vec<u64> {
+    let mut seen = vec![0u64; (self.heap.nodes.len() + 63) / 64];
+    let mut visit = vec![self.heap.get_root()];
+    for (a, b) in &self.rdex {
+      visit.push(*a);
+      visit.push(*b);
+    }
+    while let Some(ptr) = visit.pop() {
+      if ptr != LOCK && ptr != GONE && (ptr.is_nod() || ptr.is_var() || ptr.is_red()) && ptr.loc() != 0 {
+        let loc = ptr.loc() as usize;
+        if seen[loc / 64] >> (loc % 64) & 1 == 0 {
+          seen[loc / 64] |= 1 << (loc % 64);
+          visit.push(self.heap.get(ptr.loc(), P1));
+          visit.push(self.heap.get(ptr.loc(), P2));
+        }
+      }
+    }
+    return seen;
+  }
+
+  // Finds live nodes that are unreachable from the root and the redexes, such as disconnected
+  // cycles left by erasures, returning how many leaked. If 'clear' is set, they're freed, so that
+  // their slots can be reused. Scans the whole heap, so it is meant to run between normalizations.
+  pub fn collect_garbage(&mut self, clear: bool) -> usize {
+    let seen = self.reachable();
+    let mut leak = 0;
+    for loc in 1 .. self.heap.nodes.len() as Loc {
+      let p1 = self.heap.get(loc, P1);
+      let p2 = self.heap.get(loc, P2);
+      if (p1 != NULL || p2 != NULL) && seen[loc as usize / 64] >> (loc % 64) & 1 == 0 {
+        leak += 1;
+        if clear {
+          self.heap.set(loc, P1, NULL);
+          self.heap.set(loc, P2, NULL);
+          if LAZY {
+            self.heap.set_pri(loc, NULL, NULL);
+          }
+          self.free(loc);
+        }
+      }
+    }
+    return leak;
+  }
+
+  // Runs the integrity check, in debug mode, panicking on failure.
+  pub fn check_epoch(&self, normal: bool) {
+    if self.chek {
+      if let Err(err) = self.check_integrity(normal) {
+        panic!("integrity check failed after epoch {}:\n{}", self.schd.epochs, err);
+      }
+    }
+  }
+
   // Reduces all redexes.
   #[inline(always)]
   pub fn reduce(&mut self, book: &Book, limit: usize) -> usize {
@@ -1094,6 +1459,9 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
         if got != LOCK {
           //println!("[{:08x}] expand {:08x}", net.tid, dir.0);
           net.call(book, ptr, dir);
+          if let Some(prof) = &mut net.prof {
+            prof.rewrite();
+          }
         }
       }
     }
@@ -1117,21 +1485,22 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
     if tid == 0 {
       net.next = self.next;
     }
+    net.prof = self.prof.as_ref().map(|prof| prof.fork());
+    net.chek = self.chek;
     return net;
   }
 
-  // Evaluates a term to normal form in parallel
+  // Evaluates a term to normal form in parallel. The normal form is always the same, but the
+  // heap layout and stats depend on how the threads race, so they vary between runs.
   pub fn parallel_normal(&mut self, book: &Book) {
 
-    const SHARE_LIMIT : usize = 1 << 12; // max share redexes per split 
-    const LOCAL_LIMIT : usize = 1 << 18; // max local rewrites per epoch
-
     // Local thread context
     struct ThreadContext<'a, const LAZY: bool> where [(); LAZY as usize]: {
       tid: usize, // thread id
       tids: usize, // thread count
       tlog2: usize, // log2 of thread count
-      tick: usize, // current tick
+      tick: usize, // current tick (number of splits)
+      epoch: usize, // current epoch
       net: NetFields<'a, LAZY>, // thread's own net object
       book: &'a Book, // definition book
       delta: &'a AtomicRewrites, // global delta rewrites
@@ -1139,10 +1508,18 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
       rlens: &'a Vec<AtomicUsize>, // global redex lengths
       total: &'a AtomicUsize, // total redex length
       barry: Arc<Barrier>, // synchronization barrier
+      tlogs: &'a Mutex<Vec<trace::Event>>, // global interaction log
+      pdelta: &'a [prof::AtomicDefStats], // global delta profile
+      trwts: &'a Vec<AtomicUsize>, // global rewrites per thread
+      usage: &'a AtomicUsage, // global delta usage
+      rdexs: &'a Mutex<Vec<(Ptr, Ptr)>>, // global redexes, for integrity checks
+      fault: &'a Mutex<Option<String>>, // failed integrity check report
+      areas: &'a Mutex<Vec<Area>>, // global used areas
+      sched: &'a Mutex<Sched>, // global scheduler stats
     }
 
     // Initialize global objects
-    let cores = std::thread::available_parallelism().unwrap().get() as usize;
+    let cores = if self.thrd > 0 { self.thrd } else { std::thread::available_parallelism().unwrap().get() as usize };
     let tlog2 = cores.ilog2() as usize;
     let tids  = 1 << tlog2;
     let delta = AtomicRewrites::new(); // delta rewrite counter
@@ -1150,6 +1527,14 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
     let share = (0..SHARE_LIMIT*tids).map(|_| (APtr(AtomicU64::new(0)), APtr(AtomicU64::new(0)))).collect::<Vec<_>>();
     let total = AtomicUsize::new(0); // sum of redex bag length
     let barry = Arc::new(Barrier::new(tids)); // global barrier
+    let tlogs = Mutex::new(vec![]); // global interaction log
+    let pdelta = self.prof.as_ref().map_or(vec![], |prof| prof.new_delta()); // delta profile
+    let trwts = (0..tids).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+    let usage = AtomicUsage::default(); // delta usage
+    let rdexs = Mutex::new(vec![]); // redexes of all threads, in debug mode
+    let fault = Mutex::new(None); // report of a failed integrity check, in debug mode
+    let areas = Mutex::new(vec![]); // areas used by each thread
+    let sched = Mutex::new(Sched::default()); // scheduler stats, set by thread 0
 
     // Perform parallel reductions
     std::thread::scope(|s| {
@@ -1158,6 +1543,7 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
           tid: tid,
           tids: tids,
           tick: 0,
+          epoch: 0,
           net: self.fork(tid, tids),
           book: &book,
           tlog2: tlog2,
@@ -1166,6 +1552,14 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
           rlens: &rlens,
           total: &total,
           barry: Arc::clone(&barry),
+          tlogs: &tlogs,
+          pdelta: &pdelta,
+          trwts: &trwts,
+          usage: &usage,
+          rdexs: &rdexs,
+          fault: &fault,
+          areas: &areas,
+          sched: &sched,
         };
         s.spawn(move || {
           main(&mut ctx)
@@ -1173,9 +1567,30 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
       }
     });
 
+    // Threads stop on a failed check, so that it can be reported from here
+    if let Some(err) = fault.into_inner().unwrap() {
+      panic!("{}", err);
+    }
+
     // Clear redexes and sum stats
     self.rdex.clear();
     delta.add_to(&mut self.rwts);
+    usage.add_to(&mut self.used, tids);
+    if let Some(prof) = &mut self.prof {
+      prof.add_from(&pdelta);
+    }
+    let sched = sched.into_inner().unwrap();
+    self.schd.tids    = tids;
+    self.schd.epochs += sched.epochs;
+    self.schd.splits += sched.splits;
+    self.schd.trwts   = trwts.iter().map(|rwts| rwts.load(Ordering::Relaxed)).collect();
+    self.dirt.append(&mut areas.into_inner().unwrap());
+    self.check_epoch(true);
+
+    // Merge interaction logs, grouped by thread
+    let mut tlogs = tlogs.into_inner().unwrap();
+    tlogs.sort_by_key(|event| event.tid);
+    self.tlog.append(&mut tlogs);
 
     // Main reduction loop
     #[inline(always)]
@@ -1183,9 +1598,24 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
       loop {
         reduce(ctx);
         expand(ctx);
+        ctx.epoch += 1;
+        if ctx.net.chek && !check(ctx) { break; }
         if count(ctx) == 0 { break; }
       }
       ctx.net.rwts.add_to(ctx.delta);
+      ctx.net.used.add_to(ctx.usage);
+      ctx.trwts[ctx.tid].store(ctx.net.rwts.total(), Ordering::Relaxed);
+      if ctx.tid == 0 {
+        let mut sched = ctx.sched.lock().unwrap();
+        sched.epochs = ctx.epoch;
+        sched.splits = ctx.tick;
+      }
+      ctx.tlogs.lock().unwrap().append(&mut ctx.net.tlog);
+      ctx.net.tcur = None;
+      ctx.areas.lock().unwrap().push(Area { init: ctx.net.area.init, size: (ctx.net.next + 1).min(ctx.net.area.size) });
+      if let Some(prof) = &ctx.net.prof {
+        prof.add_to(ctx.pdelta);
+      }
     }
 
     // Reduce redexes locally, then share with target
@@ -1196,9 +1626,12 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
         if count(ctx) == 0 {
           break;
         }
+        // With a single thread, there is no one to share with
         let tlog2 = ctx.tlog2;
-        split(ctx, tlog2);
-        ctx.tick += 1;
+        if tlog2 > 0 {
+          split(ctx, tlog2);
+          ctx.tick += 1;
+        }
       }
     }
 
@@ -1208,6 +1641,22 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
       ctx.net.expand(ctx.book);
     }
 
+    // Checks the heap's integrity with the redexes of all threads (debug mode). Returns false on
+    // failure, on every thread, after thread 0 stores the report.
+    fn check<const LAZY: bool>(ctx: &mut ThreadContext<LAZY>) -> bool where [(); LAZY as usize]: {
+      ctx.rdexs.lock().unwrap().extend_from_slice(&ctx.net.rdex);
+      ctx.barry.wait();
+      if ctx.tid == 0 {
+        let mut net = NetFields::new(ctx.net.heap.nodes);
+        net.rdex = std::mem::take(&mut *ctx.rdexs.lock().unwrap());
+        if let Err(err) = net.check_integrity(false) {
+          *ctx.fault.lock().unwrap() = Some(format!("integrity check failed after epoch {}:\n{}", ctx.epoch, err));
+        }
+      }
+      ctx.barry.wait();
+      return ctx.fault.lock().unwrap().is_none();
+    }
+
     // Count total redexes (and populate 'rlens')
     #[inline(always)]
     fn count<const LAZY: bool>(ctx: &mut ThreadContext<LAZY>) -> usize where [(); LAZY as usize]: {
@@ -1254,6 +1703,111 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
     }
   }
 
+  // Evaluates a term to normal form on this thread alone, emulating the threads of a parallel run:
+  // they follow its schedule, but between synchronization points each runs in turn, in an order
+  // drawn from the seed. Runs with the same seed and thread count give the same heap and stats.
+  // This is a separate, deterministic evaluator: it doesn't make 'parallel_normal' reproducible.
+  pub fn simulated_normal(&mut self, book: &Book, seed: u64) {
+    let cores = if self.thrd > 0 { self.thrd } else { SIMULATED_TIDS };
+    let tlog2 = cores.ilog2() as usize;
+    let tids  = 1 << tlog2;
+    let mut rng  = Rng::new(seed);
+    let mut nets = (0 .. tids).map(|tid| self.fork(tid, tids)).collect::<Vec<_>>();
+    let mut tick  = 0;
+    let mut epoch = 0;
+    let total = |nets: &Vec<NetFields<LAZY>>| nets.iter().map(|net| net.rdex.len()).sum::<usize>();
+
+    // Perform reductions, a thread at a time
+    loop {
+      loop {
+        for tid in turns(&mut rng, tids) {
+          nets[tid].reduce(book, LOCAL_LIMIT);
+        }
+        if total(&nets) == 0 {
+          break;
+        }
+        if tlog2 > 0 {
+          share(&mut nets, tick, tlog2);
+          tick += 1;
+        }
+      }
+      for tid in turns(&mut rng, tids) {
+        nets[tid].expand(book);
+      }
+      epoch += 1;
+      if self.chek {
+        let mut net = NetFields::new(self.heap.nodes);
+        net.rdex = nets.iter().flat_map(|net| net.rdex.iter().copied()).collect();
+        if let Err(err) = net.check_integrity(false) {
+          panic!("integrity check failed after epoch {}:\n{}", epoch, err);
+        }
+      }
+      if total(&nets) == 0 {
+        break;
+      }
+    }
+
+    // Clear redexes and sum stats
+    self.rdex.clear();
+    let delta  = AtomicRewrites::new();
+    let usage  = AtomicUsage::default();
+    let pdelta = self.prof.as_ref().map_or(vec![], |prof| prof.new_delta());
+    for net in &mut nets {
+      net.rwts.add_to(&delta);
+      net.used.add_to(&usage);
+      if let Some(prof) = &net.prof {
+        prof.add_to(&pdelta);
+      }
+      self.tlog.append(&mut net.tlog);
+      net.tcur = None;
+      self.dirt.push(Area { init: net.area.init, size: (net.next + 1).min(net.area.size) });
+    }
+    delta.add_to(&mut self.rwts);
+    usage.add_to(&mut self.used, tids);
+    if let Some(prof) = &mut self.prof {
+      prof.add_from(&pdelta);
+    }
+    self.schd.tids    = tids;
+    self.schd.epochs += epoch;
+    self.schd.splits += tick;
+    self.schd.trwts   = nets.iter().map(|net| net.rwts.total()).collect();
+    self.check_epoch(true);
+
+    // The order the threads take turns in
+    fn turns(rng: &mut Rng, tids: usize) -> Vec<usize> {
+      let mut order = (0 .. tids).collect::<Vec<_>>();
+      for i in (1 .. tids).rev() {
+        order.swap(i, rng.below(i + 1));
+      }
+      return order;
+    }
+
+    // Shares redexes between pairs of threads, as each does on 'parallel_normal'
+    fn share<const LAZY: bool>(nets: &mut [NetFields<LAZY>], tick: usize, plog2: usize) where [(); LAZY as usize]: {
+      let lens = nets.iter().map(|net| net.rdex.len()).collect::<Vec<_>>();
+      let mut sent = nets.iter().map(|_| vec![]).collect::<Vec<Vec<(Ptr, Ptr)>>>();
+      for a_tid in 0 .. nets.len() {
+        let side  = (a_tid >> (plog2 - 1 - (tick % plog2))) & 1;
+        let shift = (1 << (plog2 - 1)) >> (tick % plog2);
+        let b_tid = if side == 1 { a_tid - shift } else { a_tid + shift };
+        let a_len = lens[a_tid];
+        let b_len = lens[b_tid];
+        let send  = if a_len > b_len { (a_len - b_len) / 2 } else { 0 };
+        let send  = std::cmp::min(send, SHARE_LIMIT);
+        let init  = a_len - send * 2;
+        let rdex  = &mut nets[a_tid].rdex;
+        for i in 0 .. send {
+          sent[b_tid].push(rdex[init + i * 2 + 1]);
+          rdex[init + i] = rdex[init + i * 2 + 0];
+        }
+        rdex.truncate(a_len - send);
+      }
+      for (net, got) in nets.iter_mut().zip(sent) {
+        net.rdex.extend(got);
+      }
+    }
+  }
+
   // Lazy mode weak head normalizer
   #[inline(always)]
   pub fn weak_normal(&mut self, book: &Book, mut prev: Ptr) -> Ptr {
@@ -1313,8 +1867,13 @@ impl<'a, const LAZY: bool> NetFields<'a, LAZY> where [(); LAZY as usize]: {
       while self.rdex.len() > 0 {
         self.reduce(book, usize::MAX);
         self.expand(book);
+        self.schd.epochs += 1;
+        self.check_epoch(false);
       }
+      self.check_epoch(true);
     }
+    self.schd.tids  = 1;
+    self.schd.trwts = vec![self.rwts.total()];
   }
 
 }
@@ -1356,11 +1915,19 @@ impl Net {
     }
   }
 
-  // Pretty prints.
-  pub fn show(&self) -> String {
+  // Pretty prints, with the names of the book's references.
+  pub fn show(&self, book: &Book) -> String {
     match self {
-      Net::Lazy(this)  => crate::ast::show_runtime_net(&this.net),
-      Net::Eager(this) => crate::ast::show_runtime_net(&this.net),
+      Net::Lazy(this)  => crate::ast::show_runtime_net(&this.net, &book.names),
+      Net::Eager(this) => crate::ast::show_runtime_net(&this.net, &book.names),
+    }
+  }
+
+  // Reads back as a net.
+  pub fn to_net(&self) -> crate::ast::Net {
+    match self {
+      Net::Lazy(this)  => crate::ast::net_from_runtime(&this.net),
+      Net::Eager(this) => crate::ast::net_from_runtime(&this.net),
     }
   }
 
@@ -1372,7 +1939,7 @@ impl Net {
     }
   }
 
-  // Reduces to normal form in parallel.
+  // Reduces to normal form in parallel. Heaps and stats vary between runs.
   pub fn parallel_normal(&mut self, book: &Book) {
     match self {
       Net::Lazy(this)  => this.net.parallel_normal(book),
@@ -1380,10 +1947,134 @@ impl Net {
     }
   }
 
+  // Reduces to normal form on one thread, emulating a parallel run's threads deterministically.
+  pub fn simulated_normal(&mut self, book: &Book, seed: u64) {
+    match self {
+      Net::Lazy(this)  => this.net.simulated_normal(book, seed),
+      Net::Eager(this) => this.net.simulated_normal(book, seed),
+    }
+  }
+
   pub fn get_rewrites(&self) -> Rewrites {
     match self {
       Net::Lazy(this)  => this.net.rwts,
       Net::Eager(this) => this.net.rwts,
     }
   }
+
+  pub fn get_sched(&self) -> &Sched {
+    match self {
+      Net::Lazy(this)  => &this.net.schd,
+      Net::Eager(this) => &this.net.schd,
+    }
+  }
+
+  // Enables integrity checks after each epoch.
+  pub fn set_check(&mut self, chek: bool) {
+    match self {
+      Net::Lazy(this)  => this.net.chek = chek,
+      Net::Eager(this) => this.net.chek = chek,
+    }
+  }
+
+  // Sets the threads used by 'parallel_normal', rounded down to a power of two, or 0 for one per core.
+  pub fn set_threads(&mut self, thrd: usize) {
+    match self {
+      Net::Lazy(this)  => this.net.thrd = thrd,
+      Net::Eager(this) => this.net.thrd = thrd,
+    }
+  }
+
+  pub fn check_integrity(&self, normal: bool) -> Result<usize, String> {
+    match self {
+      Net::Lazy(this)  => this.net.check_integrity(normal),
+      Net::Eager(this) => this.net.check_integrity(normal),
+    }
+  }
+
+  // Reads a heap cell.
+  pub fn get_cell(&self, loc: Loc, port: Port) -> Ptr {
+    match self {
+      Net::Lazy(this)  => this.net.heap.get(loc, port),
+      Net::Eager(this) => this.net.heap.get(loc, port),
+    }
+  }
+
+  // Overwrites a heap cell, bypassing the runtime. Meant for corrupting a heap on purpose.
+  pub fn set_cell(&mut self, loc: Loc, port: Port, ptr: Ptr) {
+    match self {
+      Net::Lazy(this)  => this.net.heap.set(loc, port, ptr),
+      Net::Eager(this) => this.net.heap.set(loc, port, ptr),
+    }
+  }
+
+  // Finds (and optionally frees) unreachable live nodes, returning how many leaked.
+  pub fn collect_garbage(&mut self, clear: bool) -> usize {
+    match self {
+      Net::Lazy(this)  => this.net.collect_garbage(clear),
+      Net::Eager(this) => this.net.collect_garbage(clear),
+    }
+  }
+
+  // Clears the used heap and all counters, so that the net can be booted again.
+  pub fn reset(&mut self) {
+    match self {
+      Net::Lazy(this)  => this.net.reset(),
+      Net::Eager(this) => this.net.reset(),
+    }
+  }
+
+  // Resets, then boots from a definition applied to the given arguments.
+  pub fn boot_with(&mut self, book: &Book, name: &str, args: &[crate::ast::Tree]) -> Result<(), String> {
+    match self {
+      Net::Lazy(this)  => this.net.boot_with(book, name, args),
+      Net::Eager(this) => this.net.boot_with(book, name, args),
+    }
+  }
+
+  // Resets, then boots from an arbitrary net.
+  pub fn boot_net(&mut self, book: &Book, net: &crate::ast::Net) -> Result<(), String> {
+    match self {
+      Net::Lazy(this)  => this.net.boot_net(book, net),
+      Net::Eager(this) => this.net.boot_net(book, net),
+    }
+  }
+
+  pub fn get_usage(&self) -> Usage {
+    match self {
+      Net::Lazy(this)  => this.net.used,
+      Net::Eager(this) => this.net.used,
+    }
+  }
+
+  // Heap size, in nodes.
+  pub fn get_size(&self) -> usize {
+    match self {
+      Net::Lazy(this)  => this.net.heap.nodes.len(),
+      Net::Eager(this) => this.net.heap.nodes.len(),
+    }
+  }
+
+  // Enables the per-definition profiler.
+  pub fn enable_profile(&mut self, book: &Book) {
+    match self {
+      Net::Lazy(this)  => this.net.prof = Some(prof::Profile::new(book, this.net.heap.nodes.len())),
+      Net::Eager(this) => this.net.prof = Some(prof::Profile::new(book, this.net.heap.nodes.len())),
+    }
+  }
+
+  pub fn get_profile(&self) -> Option<&prof::Profile> {
+    match self {
+      Net::Lazy(this)  => this.net.prof.as_ref(),
+      Net::Eager(this) => this.net.prof.as_ref(),
+    }
+  }
+
+  // Interactions recorded so far, if built with the 'trace' feature.
+  pub fn get_trace(&self) -> &[trace::Event] {
+    match self {
+      Net::Lazy(this)  => &this.net.tlog,
+      Net::Eager(this) => &this.net.tlog,
+    }
+  }
 }
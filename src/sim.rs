@@ -0,0 +1,835 @@
+// CPU simulation of the CUDA runtime
+// ----------------------------------
+// A reference implementation of the algorithm on 'cuda/runtime.cu', used to test it without a GPU.
+// It keeps the runtime's 32-bit pointers, per-squad allocation areas, 128x128 redex bags and host
+// schedule (expand, 128 ticks of rewrites with neighbor splits, expand, final rewrites), and loads
+// definitions from the tables built by 'cuda.rs'. Blocks of 128 squads run on CPU threads. Inside a
+// block, squads take turns, and the 4 quarters of a squad (A1, A2, B1, B2) run phase by phase, as
+// if separated by '__syncwarp'. Results and rewrite counts can then be compared with 'run.rs'.
+
+use crate::ast;
+use crate::cuda::{self, Ptr, Tag, Val, mkptr, tag, val};
+use crate::run;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+// Bags dimensions (128x128 redex bags)
+pub const BAGS_WIDTH_L2 : u32 = 7;
+pub const BAGS_WIDTH    : u32 = 1 << BAGS_WIDTH_L2;
+pub const BAGS_HEIGHT_L2: u32 = 7;
+pub const BAGS_HEIGHT   : u32 = 1 << BAGS_HEIGHT_L2;
+pub const BAGS_TOTAL_L2 : u32 = BAGS_WIDTH_L2 + BAGS_HEIGHT_L2;
+pub const BAGS_TOTAL    : u32 = 1 << BAGS_TOTAL_L2;
+
+// Threads per squad (4), squads per block (128), total squads (1 per bag)
+pub const SQUAD_SIZE    : u32 = 4;
+pub const GROUP_SIZE    : u32 = BAGS_WIDTH;
+pub const SQUAD_TOTAL_L2: u32 = BAGS_TOTAL_L2;
+pub const SQUAD_TOTAL   : u32 = BAGS_TOTAL;
+
+// Redex bags and expansions
+pub const MAX_NEW_REDEX       : u32 = 16; // pops stop when a bag is this close to full
+pub const RBAG_SIZE           : u32 = 256; // bag length slot + redexes
+pub const EXPANSIONS_PER_SQUAD: usize = 16; // max derefs per squad on global expansion
+
+// Special values
+pub const ROOT: Ptr = mkptr(cuda::VR2, 0); // pointer to root port
+pub const NONE: Ptr = 0x00000000; // empty value, not allocated
+pub const GONE: Ptr = 0xFFFFFFFE; // node has been moved to redex bag by paired thread
+pub const LOCK: Ptr = 0xFFFFFFFF; // value taken by another thread, will be replaced soon
+pub const FAIL: u32 = 0xFFFFFFFF; // signals failure to allocate
+
+// Quarters
+const A1: u32 = 0; // focuses on the A node, P1 port
+const A2: u32 = 1; // focuses on the A node, P2 port
+const B1: u32 = 2; // focuses on the B node, P1 port
+const B2: u32 = 3; // focuses on the B node, P2 port
+
+// Ports
+const P1: u32 = 0;
+const P2: u32 = 1;
+
+// A pair of pointers stored on a redex bag.
+pub type Wire = u64;
+
+// The simulated GPU memory.
+pub struct Net {
+  pub bags: Vec<AtomicU64>, // redex bags, each with its length on slot 0
+  pub heap: Vec<AtomicU32>, // nodes, as pairs of ports
+  pub head: Vec<AtomicU64>, // head expansion buffer
+  pub jump: Vec<u32>, // book jump table
+  pub book: Vec<u32>, // book data
+  pub area: u32, // allocation nodes per squad
+  pub tids: usize, // CPU threads
+  pub rwts: run::AtomicRewrites, // rewrite count
+  pub link: AtomicUsize, // var ~ pri rewrites (deref roots)
+  pub void: AtomicUsize, // rewrites of redexes without a rule
+  pub full: AtomicUsize, // redexes lost by pushing to full bags
+  pub fail: AtomicUsize, // derefs lost by failed allocations
+  pub abrt: AtomicUsize, // interactions aborted by failed allocations
+}
+
+// The state shared by the 4 quarters of a squad.
+struct Squad {
+  uid : u32, // bag id
+  aloc: u32, // where to alloc next node
+  sm32: [Ptr; 4], // shared 32-bit buffer
+  rwts: run::Rewrites, // local rewrites performed
+  link: usize, // local var ~ pri rewrites
+  void: usize, // local rewrites without a rule
+}
+
+// A substitution waiting to be linked, kept aside when it meets a LOCK.
+#[derive(Clone, Copy)]
+struct Link {
+  a_ptr: Ptr, // current end of the path
+  a_ref: usize, // source slot
+  b_ptr: Ptr, // pointer being linked
+}
+
+fn div(a: u32, b: u32) -> u32 {
+  (a + b - 1) / b
+}
+
+fn is_var(ptr: Ptr) -> bool {
+  ptr != 0 && tag(ptr) <= cuda::VR2
+}
+
+fn is_red(ptr: Ptr) -> bool {
+  tag(ptr) >= cuda::RD1 && tag(ptr) <= cuda::RD2
+}
+
+fn is_ctr(ptr: Ptr) -> bool {
+  tag(ptr) >= cuda::CT0 && tag(ptr) < cuda::CT5
+}
+
+fn is_era(ptr: Ptr) -> bool {
+  tag(ptr) == cuda::ERA
+}
+
+fn is_ref(ptr: Ptr) -> bool {
+  tag(ptr) == cuda::REF
+}
+
+fn is_pri(ptr: Ptr) -> bool {
+  is_ctr(ptr) || is_era(ptr) || is_ref(ptr)
+}
+
+fn has_loc(ptr: Ptr) -> bool {
+  is_ctr(ptr) || is_var(ptr)
+}
+
+// Gets the heap slot targeted by a var or redirection pointer
+fn slot(ptr: Ptr) -> usize {
+  val(ptr) as usize * 2 + (ptr & 1) as usize
+}
+
+fn redir(ptr: Ptr) -> Ptr {
+  mkptr(tag(ptr) + if is_var(ptr) { 2 } else { 0 }, val(ptr))
+}
+
+fn undir(ptr: Ptr) -> Ptr {
+  mkptr(tag(ptr) - if is_red(ptr) { 2 } else { 0 }, val(ptr))
+}
+
+fn mkwire(p1: Ptr, p2: Ptr) -> Wire {
+  ((p1 as Wire) << 32) | (p2 as Wire)
+}
+
+fn wire_lft(wire: Wire) -> Ptr {
+  (wire >> 32) as Ptr
+}
+
+fn wire_rgt(wire: Wire) -> Ptr {
+  (wire & 0xFFFFFFFF) as Ptr
+}
+
+fn adjust(ptr: Ptr, delta: u32) -> Ptr {
+  mkptr(tag(ptr), if has_loc(ptr) { val(ptr) + delta - 1 } else { val(ptr) })
+}
+
+fn interleave(idx: u32, width: u32, height: u32) -> u32 {
+  let old_row = idx / width;
+  let old_col = idx % width;
+  let new_row = old_col % height;
+  let new_col = old_col / height + old_row * (width / height);
+  new_row * width + new_col
+}
+
+// Local squad id (sid) to global squad id (uid)
+fn sid_to_uid(sid: u32, flip: bool) -> u32 {
+  if flip { interleave(sid, BAGS_WIDTH, BAGS_HEIGHT) } else { sid }
+}
+
+impl Squad {
+  fn new(sid: u32, flip: bool) -> Self {
+    Squad { uid: sid_to_uid(sid, flip), aloc: 0, sm32: [0; 4], rwts: run::Rewrites::new(), link: 0, void: 0 }
+  }
+}
+
+impl Net {
+  // Creates a net with 2^heap_l2 nodes, whose root is a reference to the given definition.
+  pub fn new(book: &cuda::Book, root: Val, heap_l2: u32) -> Result<Self, String> {
+    if heap_l2 < SQUAD_TOTAL_L2 + 2 || heap_l2 > cuda::HEAP_SIZE_L2 {
+      return Err(format!("heap size must be between 2^{} and 2^{} nodes", SQUAD_TOTAL_L2 + 2, cuda::HEAP_SIZE_L2));
+    }
+    if book.get(root).is_none() {
+      return Err(format!("@{} not found", ast::val_to_name(root as run::Val)));
+    }
+    let mut jump = vec![0; cuda::JUMP_SIZE as usize];
+    for entry in &book.jump {
+      jump[entry.fid as usize] = entry.init;
+    }
+    let net = Net {
+      bags: (0 .. BAGS_TOTAL * RBAG_SIZE).map(|_| AtomicU64::new(0)).collect(),
+      heap: (0 .. 2 << heap_l2).map(|_| AtomicU32::new(0)).collect(),
+      head: (0 .. SQUAD_TOTAL).map(|_| AtomicU64::new(0)).collect(),
+      jump,
+      book: book.book.clone(),
+      area: (1 << heap_l2) / SQUAD_TOTAL,
+      tids: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+      rwts: run::AtomicRewrites::new(),
+      link: AtomicUsize::new(0),
+      void: AtomicUsize::new(0),
+      full: AtomicUsize::new(0),
+      fail: AtomicUsize::new(0),
+      abrt: AtomicUsize::new(0),
+    };
+    net.heap[slot(ROOT)].store(mkptr(cuda::REF, root), Ordering::Relaxed);
+    return Ok(net);
+  }
+
+  // Runs the host schedule of 'runtime_standalone.cu'.
+  pub fn normal(&self) {
+    self.global_expand();
+    for tick in 0 .. 128 {
+      self.global_rewrite(16, tick, (tick / BAGS_WIDTH_L2) % 2 == 1);
+    }
+    self.global_expand();
+    self.global_rewrite(200000, 0, false);
+  }
+
+  // Fails if work was lost: redexes pushed to full bags, or expansions that couldn't allocate.
+  // Aborted interactions are pushed back and retried, so they don't count.
+  pub fn check(&self) -> Result<(), String> {
+    let full = self.full.load(Ordering::Relaxed);
+    let fail = self.fail.load(Ordering::Relaxed);
+    if full > 0 {
+      return Err(format!("{} redexes were pushed to full bags and lost", full));
+    }
+    if fail > 0 {
+      return Err(format!("{} expansions failed to allocate", fail));
+    }
+    Ok(())
+  }
+
+  // Total rewrites, as counted by the GPU.
+  pub fn get_total(&self) -> usize {
+    self.get_rewrites().total() + self.link.load(Ordering::Relaxed) + self.void.load(Ordering::Relaxed)
+  }
+
+  // Rewrites per rule, as counted by 'run.rs'.
+  pub fn get_rewrites(&self) -> run::Rewrites {
+    let mut rwts = run::Rewrites::new();
+    self.rwts.add_to(&mut rwts);
+    rwts
+  }
+
+  // Heap
+  // ----
+
+  fn load(&self, idx: usize) -> Ptr {
+    self.heap[idx].load(Ordering::Relaxed)
+  }
+
+  fn store(&self, idx: usize, ptr: Ptr) {
+    self.heap[idx].store(ptr, Ordering::Relaxed)
+  }
+
+  fn swap(&self, idx: usize, ptr: Ptr) -> Ptr {
+    self.heap[idx].swap(ptr, Ordering::SeqCst)
+  }
+
+  fn cas(&self, idx: usize, exp: Ptr, neo: Ptr) -> Ptr {
+    match self.heap[idx].compare_exchange(exp, neo, Ordering::SeqCst, Ordering::SeqCst) {
+      Ok(got) => got,
+      Err(got) => got,
+    }
+  }
+
+  fn is_nil(&self, loc: u32) -> bool {
+    self.load(loc as usize * 2) == NONE && self.load(loc as usize * 2 + 1) == NONE
+  }
+
+  // Traverses to the other side of a wire
+  fn enter(&self, mut ptr: Ptr) -> Ptr {
+    let mut got = self.load(slot(ptr));
+    while is_red(got) {
+      ptr = got;
+      got = self.load(slot(ptr));
+    }
+    ptr
+  }
+
+  // Allocates nodes on the squad's area. Each quarter checks one of 4 consecutive nodes.
+  fn alloc(&self, squad: &mut Squad, size: u32) -> u32 {
+    let size4 = div(size, 4) * 4;
+    let begin = squad.uid * self.area;
+    let mut space = 0;
+    let mut index = squad.aloc - (squad.aloc % 4);
+    for _ in 0 .. 256 {
+      let succ = (0 .. SQUAD_SIZE).all(|qid| self.is_nil(begin + index + qid));
+      index = (index + 4) % self.area;
+      space = if succ && index > 0 { space + 4 } else { 0 };
+      if space == size4 {
+        squad.aloc = index;
+        return (begin + index - space) % (self.heap.len() / 2) as u32;
+      }
+    }
+    FAIL
+  }
+
+  // Gets the value of a slot; waits if taken.
+  fn take(&self, idx: usize) -> Ptr {
+    let mut got = self.swap(idx, LOCK);
+    while got == LOCK {
+      std::hint::spin_loop();
+      got = self.swap(idx, LOCK);
+    }
+    got
+  }
+
+  // Attempts to replace 'exp' by 'neo', until it succeeds
+  fn replace(&self, idx: usize, exp: Ptr, neo: Ptr) {
+    while self.cas(idx, exp, neo) != exp {
+      std::hint::spin_loop();
+    }
+  }
+
+  // Bags
+  // ----
+
+  fn rlen(&self, uid: u32) -> &AtomicU64 {
+    &self.bags[(uid * RBAG_SIZE) as usize]
+  }
+
+  fn rbag(&self, uid: u32, idx: u64) -> Option<&AtomicU64> {
+    self.bags.get((uid * RBAG_SIZE) as usize + 1 + idx as usize)
+  }
+
+  // Pops a redex. All quarters read the same length, so it is popped once.
+  fn pop_redex(&self, squad: &mut Squad) -> Wire {
+    let rlen = self.rlen(squad.uid).load(Ordering::Relaxed) as u32;
+    if rlen > 0 && rlen <= RBAG_SIZE - MAX_NEW_REDEX {
+      let redex = self.rbag(squad.uid, rlen as u64 - 1).unwrap().swap(0, Ordering::Relaxed);
+      self.rlen(squad.uid).store(rlen as u64 - 1, Ordering::Relaxed);
+      return redex;
+    }
+    0
+  }
+
+  // Puts a redex, skipping pairs that don't react.
+  fn put_redex(&self, squad: &mut Squad, a_ptr: Ptr, b_ptr: Ptr) {
+    if (is_era(a_ptr) || is_ref(a_ptr)) && (is_era(b_ptr) || is_ref(b_ptr)) {
+      squad.rwts.eras += 1;
+      return;
+    }
+    let index = self.rlen(squad.uid).fetch_add(1, Ordering::SeqCst);
+    if index < (RBAG_SIZE - 1) as u64 {
+      self.rbag(squad.uid, index).unwrap().store(mkwire(a_ptr, b_ptr), Ordering::Relaxed);
+    } else {
+      self.full.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  // Splits the redexes of two bags evenly, as 8 threads (both squads) would.
+  fn split(&self, a_uid: u32, b_uid: u32) {
+    let a_len = self.rlen(a_uid).load(Ordering::Relaxed);
+    let b_len = self.rlen(b_uid).load(Ordering::Relaxed);
+    let (a_uid, b_uid) = if a_len < b_len { (a_uid, b_uid) } else { (b_uid, a_uid) };
+    let min = a_len.min(b_len);
+    let max = a_len.max(b_len);
+    if min + 1 >= max {
+      return;
+    }
+    let tids = (SQUAD_SIZE * 2) as u64;
+    for t in 0 .. RBAG_SIZE as u64 / tids {
+      // Each thread takes an element, then each thread stores it ('__syncthreads' in between)
+      let mut vals = [0; 8];
+      for tid in 0 .. tids {
+        let i = min + t * tids + tid;
+        if i < max {
+          vals[tid as usize] = self.rbag(b_uid, i).map_or(0, |got| got.swap(0, Ordering::Relaxed));
+        }
+      }
+      for tid in 0 .. tids {
+        let i = min + t * tids + tid;
+        if i < max {
+          let uid = if (i - min) % 2 == 0 { a_uid } else { b_uid };
+          if let Some(got) = self.rbag(uid, min + (t * tids + tid) / 2) {
+            got.store(vals[tid as usize], Ordering::Relaxed);
+          }
+        }
+      }
+    }
+    self.rlen(a_uid).store((min + max) / 2 + (min + max) % 2, Ordering::Relaxed);
+    self.rlen(b_uid).store((min + max) / 2, Ordering::Relaxed);
+  }
+
+  // Expands a reference, with the 4 quarters loading every 4th node and redex. Returns the
+  // adjusted root, or None if the allocation failed.
+  fn deref(&self, squad: &mut Squad, fid: Val, up: Ptr) -> Option<Ptr> {
+    let jump = self.jump[(fid & 0xFFFFFF) as usize] as usize;
+    let nlen = self.book[jump + 0];
+    let rlen = self.book[jump + 1];
+    let node = jump + 2;
+    let acts = jump + 2 + nlen as usize * 2;
+    let loc  = self.alloc(squad, nlen - 1);
+    if loc == FAIL {
+      return None;
+    }
+    squad.rwts.dref += 1;
+    for idx in 0 .. (nlen - 1) as usize {
+      self.store((loc as usize + idx) * 2 + 0, adjust(self.book[node + 2 + idx * 2 + 0], loc));
+      self.store((loc as usize + idx) * 2 + 1, adjust(self.book[node + 2 + idx * 2 + 1], loc));
+    }
+    for idx in 0 .. rlen as usize {
+      let p1 = adjust(self.book[acts + idx * 2 + 0], loc);
+      let p2 = adjust(self.book[acts + idx * 2 + 1], loc);
+      self.put_redex(squad, p1, p2);
+    }
+    let root = adjust(self.book[node + 1], loc);
+    if is_var(root) {
+      self.store(slot(root), up);
+    }
+    Some(root)
+  }
+
+  // Rewrite
+  // -------
+
+  fn atomic_join(&self, b_ptr: Ptr) {
+    loop {
+      let ste_ref = slot(b_ptr);
+      let ste_ptr = self.load(ste_ref);
+      if is_var(ste_ptr) {
+        let trg_ref = slot(ste_ptr);
+        let trg_ptr = self.heap[trg_ref].load(Ordering::SeqCst);
+        if is_red(trg_ptr) && self.cas(ste_ref, ste_ptr, undir(trg_ptr)) == ste_ptr {
+          self.store(trg_ref, 0);
+          continue;
+        }
+      }
+      break;
+    }
+  }
+
+  // Links until done, or until it meets a LOCK, in which case it returns false, so that the
+  // quarter holding it can progress.
+  fn atomic_link(&self, squad: &mut Squad, link: &mut Link) -> bool {
+    loop {
+      let t_ref = slot(link.a_ptr);
+      let t_ptr = self.heap[t_ref].load(Ordering::SeqCst);
+      if is_red(t_ptr) {
+        self.store(t_ref, 0);
+        link.a_ptr = t_ptr;
+      } else if is_var(t_ptr) {
+        if self.cas(t_ref, t_ptr, link.b_ptr) == t_ptr {
+          self.store(link.a_ref, 0);
+          // Collect the orphaned backward path.
+          let mut t_ref = slot(t_ptr);
+          let mut t_ptr = self.load(t_ref);
+          while is_red(t_ptr) {
+            self.store(t_ref, 0);
+            t_ref = slot(t_ptr);
+            t_ptr = self.load(t_ref);
+          }
+          return true;
+        }
+      } else if is_pri(t_ptr) || t_ptr == GONE {
+        // Two quarters reach this branch. The first creates a redex, the second clears memory.
+        let x_ref = link.a_ref.min(t_ref);
+        let y_ref = link.a_ref.max(t_ref);
+        let x_ptr = self.swap(x_ref, GONE);
+        if x_ptr != GONE {
+          let y_ptr = self.swap(y_ref, GONE);
+          self.put_redex(squad, x_ptr, y_ptr);
+        } else {
+          self.store(x_ref, 0);
+          self.replace(y_ref, GONE, 0);
+        }
+        return true;
+      } else if t_ptr == LOCK {
+        return false;
+      } else {
+        return true;
+      }
+    }
+  }
+
+  fn atomic_subst(&self, squad: &mut Squad, a_ptr: Ptr, a_dir: Ptr, b_ptr: Ptr, put: bool) -> Option<Link> {
+    let a_ref = slot(a_dir);
+    if is_var(a_ptr) {
+      let got = self.cas(slot(a_ptr), a_dir, b_ptr);
+      if got == a_dir {
+        self.swap(a_ref, NONE);
+      } else if is_var(b_ptr) {
+        self.swap(a_ref, redir(b_ptr));
+        self.atomic_join(redir(b_ptr));
+      } else if is_pri(b_ptr) {
+        self.swap(a_ref, b_ptr);
+        return Some(Link { a_ptr, a_ref, b_ptr });
+      }
+    } else if is_pri(a_ptr) && is_pri(b_ptr) {
+      if a_ptr < b_ptr || put {
+        self.put_redex(squad, b_ptr, a_ptr);
+      }
+      self.swap(a_ref, NONE);
+    } else {
+      self.swap(a_ref, NONE);
+    }
+    None
+  }
+
+  // Performs an interaction with the 4 quarters of a squad. Returns whether a redex was popped.
+  fn interact(&self, squad: &mut Squad) -> bool {
+    let redex = self.pop_redex(squad);
+    if redex == 0 {
+      return false;
+    }
+    let mut lft = wire_lft(redex);
+    let mut rgt = wire_rgt(redex);
+    let mut abort = false;
+
+    // Dereferences. All quarters agree on the REF, so they expand it together.
+    if is_ref(lft) && is_ctr(rgt) {
+      match self.deref(squad, val(lft), NONE) { Some(root) => lft = root, None => abort = true }
+    } else if is_ref(rgt) && is_ctr(lft) {
+      match self.deref(squad, val(rgt), NONE) { Some(root) => rgt = root, None => abort = true }
+    }
+
+    // Each quarter sees the redex from the side of its own node
+    let a_ptr = [lft, lft, rgt, rgt];
+    let b_ptr = [rgt, rgt, lft, lft];
+    let port  = [P1, P2, P1, P2];
+
+    // Defines type of interaction
+    let mut rewrite = lft != 0 && rgt != 0;
+    let var_pri = |q: usize| is_var(a_ptr[q]) && is_pri(b_ptr[q]) && port[q] == P1;
+    let era_ctr = |q: usize| is_era(a_ptr[q]) && is_ctr(b_ptr[q]);
+    let ctr_era = |q: usize| is_ctr(a_ptr[q]) && is_era(b_ptr[q]);
+    let con_con = is_ctr(lft) && is_ctr(rgt) && tag(lft) == tag(rgt);
+    let con_dup = is_ctr(lft) && is_ctr(rgt) && tag(lft) != tag(rgt);
+
+    // If con_dup, alloc clones base index
+    let mut dp_loc = FAIL;
+    if rewrite && con_dup {
+      dp_loc = self.alloc(squad, 4);
+      abort = abort || dp_loc == FAIL;
+    }
+
+    // Reverts when abort=true (every quarter pushes the redex back)
+    if rewrite && abort {
+      self.abrt.fetch_add(1, Ordering::Relaxed);
+      rewrite = false;
+      for q in 0 .. 4 {
+        self.put_redex(squad, a_ptr[q], b_ptr[q]);
+      }
+    }
+    if !rewrite {
+      return true;
+    }
+
+    // Inc rewrite count
+    if con_con {
+      squad.rwts.anni += 1;
+    } else if con_dup {
+      squad.rwts.comm += 1;
+    } else if era_ctr(0) || ctr_era(0) {
+      squad.rwts.eras += 1;
+    } else if var_pri(0) || var_pri(2) {
+      squad.link += 1;
+    } else {
+      squad.void += 1;
+    }
+
+    let mut ak_dir = [0; 4]; // dir to our aux port
+    let mut ak_ptr = [0; 4]; // val of our aux port
+    let mut bk_ptr = [0; 4]; // val of other aux port
+    let mut mv_ptr = [0; 4]; // val of ptr to send to other side
+    for q in 0 .. 4 {
+      let qid = q as u32;
+
+      // Gets port here
+      if ctr_era(q) || con_con || con_dup {
+        ak_dir[q] = mkptr(cuda::VR1 + port[q] as Tag, val(a_ptr[q]));
+        ak_ptr[q] = self.take(slot(ak_dir[q]));
+      }
+
+      // If era_ctr, send an erasure
+      if era_ctr(q) {
+        mv_ptr[q] = mkptr(cuda::ERA, 0);
+      }
+
+      // If con_con, send a redirection
+      if con_con {
+        mv_ptr[q] = ak_ptr[q];
+      }
+
+      // If con_dup, create inner wires between clones
+      if con_dup {
+        let cx_loc = dp_loc + qid;
+        let c1_loc = dp_loc + if qid <= A2 { 2 } else { 0 };
+        let c2_loc = dp_loc + if qid <= A2 { 3 } else { 1 };
+        let c_tag  = if port[q] == P1 { cuda::VR1 } else { cuda::VR2 };
+        self.swap(slot(mkptr(cuda::VR1, cx_loc)), mkptr(c_tag, c1_loc));
+        self.swap(slot(mkptr(cuda::VR2, cx_loc)), mkptr(c_tag, c2_loc));
+        mv_ptr[q] = mkptr(tag(a_ptr[q]), cx_loc);
+      }
+    }
+
+    // Send ptr to other side
+    for q in 0 .. 4 {
+      if era_ctr(q) || con_con || con_dup {
+        squad.sm32[(q + 2) % 4] = mv_ptr[q];
+      }
+    }
+
+    // Receive ptr from other side
+    for q in 0 .. 4 {
+      if con_con || ctr_era(q) || con_dup {
+        bk_ptr[q] = squad.sm32[q];
+      }
+    }
+
+    // If var_pri, the var must be a deref root, so we just subst
+    for q in 0 .. 4 {
+      if var_pri(q) {
+        self.swap(slot(a_ptr[q]), b_ptr[q]);
+      }
+    }
+
+    // Substitutes. Quarters waiting on a LOCK yield to the others, which may be holding it.
+    let mut links = [None; 4];
+    for q in 0 .. 4 {
+      if con_con || ctr_era(q) || con_dup {
+        links[q] = self.atomic_subst(squad, ak_ptr[q], ak_dir[q], bk_ptr[q], ctr_era(q) || con_dup);
+      }
+    }
+    while links.iter().any(|link| link.is_some()) {
+      for q in 0 .. 4 {
+        if let Some(link) = &mut links[q] {
+          if self.atomic_link(squad, link) {
+            links[q] = None;
+          }
+        }
+      }
+      std::hint::spin_loop();
+    }
+
+    return true;
+  }
+
+  fn save(&self, squad: &Squad) {
+    squad.rwts.add_to(&self.rwts);
+    self.link.fetch_add(squad.link, Ordering::Relaxed);
+    self.void.fetch_add(squad.void, Ordering::Relaxed);
+  }
+
+  // Runs a kernel, with each CPU thread taking blocks until all of them are done.
+  fn launch<F: Fn(u32) + Sync>(&self, block: F) {
+    let next = AtomicU32::new(0);
+    std::thread::scope(|s| {
+      for _ in 0 .. self.tids {
+        s.spawn(|| {
+          loop {
+            let bid = next.fetch_add(1, Ordering::Relaxed);
+            if bid >= BAGS_HEIGHT {
+              break;
+            }
+            block(bid);
+          }
+        });
+      }
+    });
+  }
+
+  // Performs 'repeat' interactions on each squad, then shares redexes with paired neighbors.
+  pub fn global_rewrite(&self, repeat: u32, tick: u32, flip: bool) {
+    self.launch(|bid| {
+      let mut squads = (0 .. GROUP_SIZE).map(|i| Squad::new(bid * GROUP_SIZE + i, flip)).collect::<Vec<_>>();
+      for _ in 0 .. repeat {
+        let mut busy = false;
+        for squad in &mut squads {
+          busy |= self.interact(squad);
+        }
+        // Squads only push to their own bags, so, if no squad popped, the next turns do nothing.
+        if !busy {
+          break;
+        }
+      }
+      let shift = (1 << (BAGS_WIDTH_L2 - 1)) >> (tick % BAGS_WIDTH_L2);
+      for i in 0 .. GROUP_SIZE {
+        let side = (i >> (BAGS_WIDTH_L2 - 1 - (tick % BAGS_WIDTH_L2))) & 1;
+        if side == 0 {
+          let a_sid = bid * GROUP_SIZE + i;
+          self.split(sid_to_uid(a_sid, flip), sid_to_uid(a_sid + shift, flip));
+        }
+      }
+      for squad in &squads {
+        self.save(squad);
+      }
+    });
+  }
+
+  // Takes an initial head location for each squad, descending the tree by the bits of its id.
+  fn global_expand_prepare(&self) {
+    for uid in 0 .. SQUAD_TOTAL {
+      let mut key = uid;
+      let mut dir = ROOT;
+      let mut ptr = NONE; // uninitialized on the GPU
+      for _ in 0 .. BAGS_TOTAL_L2 {
+        dir = self.enter(dir);
+        if is_var(dir) {
+          ptr = self.load(slot(dir));
+          if is_ctr(ptr) {
+            dir = mkptr(if key & 1 == 1 { cuda::VR1 } else { cuda::VR2 }, val(ptr));
+            key = key >> 1;
+          }
+        }
+      }
+      dir = self.enter(dir);
+      if is_var(dir) {
+        ptr = self.swap(slot(dir), LOCK);
+      }
+      let head = if ptr != LOCK { mkwire(dir, ptr) } else { mkwire(NONE, NONE) };
+      self.head[uid as usize].store(head, Ordering::Relaxed);
+    }
+  }
+
+  // Collects up to EXPANSIONS_PER_SQUAD references below a location, left to right.
+  fn expand(&self, dir: Ptr, heads: &mut Vec<Ptr>) {
+    let mut stack = vec![dir];
+    while let Some(dir) = stack.pop() {
+      if heads.len() >= EXPANSIONS_PER_SQUAD {
+        break;
+      }
+      let ptr = self.load(slot(dir));
+      if is_ctr(ptr) {
+        stack.push(mkptr(cuda::VR2, val(ptr)));
+        stack.push(mkptr(cuda::VR1, val(ptr)));
+      } else if is_red(ptr) {
+        stack.push(ptr);
+      } else if is_ref(ptr) {
+        heads.push(dir);
+      }
+    }
+  }
+
+  // Performs a global head expansion (1 deref per bag).
+  pub fn global_expand(&self) {
+    self.global_expand_prepare();
+    self.launch(|bid| {
+      let mut squads = (0 .. GROUP_SIZE).map(|i| Squad::new(bid * GROUP_SIZE + i, false)).collect::<Vec<_>>();
+      let wires = squads.iter().map(|squad| self.head[squad.uid as usize].load(Ordering::Relaxed)).collect::<Vec<_>>();
+      // A1 gives the taken ptr back ('__syncthreads' after)
+      for &wire in &wires {
+        if wire_rgt(wire) != NONE {
+          self.store(slot(wire_lft(wire)), wire_rgt(wire));
+        }
+      }
+      // A1 collects the expansion heads ('__syncthreads' after)
+      let mut heads = vec![vec![]; GROUP_SIZE as usize];
+      for (i, &wire) in wires.iter().enumerate() {
+        if wire_rgt(wire) != NONE {
+          self.expand(wire_lft(wire), &mut heads[i]);
+        }
+      }
+      // All quarters expand them
+      for (squad, heads) in squads.iter_mut().zip(heads) {
+        for dir in heads {
+          let ptr = self.load(slot(dir));
+          if is_ref(ptr) {
+            match self.deref(squad, val(ptr), dir) {
+              Some(root) => self.store(slot(dir), root),
+              None => { self.fail.fetch_add(1, Ordering::Relaxed); }
+            }
+          }
+        }
+        self.save(squad);
+      }
+    });
+  }
+
+  // Readback
+  // --------
+
+  // Reads the root and the redexes left on the bags back as a net.
+  pub fn to_net(&self) -> Result<ast::Net, String> {
+    let mut vars = HashMap::new();
+    let mut fresh = 0;
+    let root = self.tree(self.load(slot(ROOT)), Some(slot(ROOT)), &mut vars, &mut fresh)?;
+    let mut rdex = vec![];
+    for uid in 0 .. BAGS_TOTAL {
+      for idx in 0 .. (RBAG_SIZE - 1) as u64 {
+        let wire = self.rbag(uid, idx).unwrap().load(Ordering::Relaxed);
+        if wire != 0 {
+          let a = self.tree(wire_lft(wire), None, &mut vars, &mut fresh)?;
+          let b = self.tree(wire_rgt(wire), None, &mut vars, &mut fresh)?;
+          rdex.push((a, b));
+        }
+      }
+    }
+    Ok(ast::Net::new(root, rdex))
+  }
+
+  // Reads a tree, naming each var by the slot of the other end of its wire.
+  fn tree(&self, ptr: Ptr, parent: Option<usize>, vars: &mut HashMap<usize, String>, fresh: &mut usize) -> Result<ast::Tree, String> {
+    if ptr == NONE || ptr == GONE || ptr == LOCK {
+      let place = parent.map_or("a redex".to_string(), |parent| format!("slot 0x{:x}", parent));
+      return Err(format!("found 0x{:08X} on {}", ptr, place));
+    }
+    match tag(ptr) {
+      cuda::VR1 | cuda::VR2 => {
+        if let Some(nam) = parent.and_then(|parent| vars.get(&parent)) {
+          Ok(ast::Tree::Var { nam: nam.clone() })
+        } else {
+          let nam = ast::num_to_str(*fresh);
+          *fresh += 1;
+          vars.insert(slot(self.enter(ptr)), nam.clone());
+          Ok(ast::Tree::Var { nam })
+        }
+      }
+      cuda::RD1 | cuda::RD2 => {
+        self.tree(self.load(slot(ptr)), Some(slot(ptr)), vars, fresh)
+      }
+      cuda::REF => {
+        Ok(ast::Tree::Ref { nam: val(ptr) as run::Val })
+      }
+      cuda::ERA => {
+        Ok(ast::Tree::Era)
+      }
+      cuda::NUM => {
+        Ok(ast::Tree::Num { val: val(ptr) as run::Val })
+      }
+      cuda::ITE => {
+        let sel = Box::new(self.tree(self.load(val(ptr) as usize * 2 + 0), Some(val(ptr) as usize * 2 + 0), vars, fresh)?);
+        let ret = Box::new(self.tree(self.load(val(ptr) as usize * 2 + 1), Some(val(ptr) as usize * 2 + 1), vars, fresh)?);
+        Ok(ast::Tree::Mat { sel, ret })
+      }
+      _ if is_ctr(ptr) => {
+        // Labels 0 and 1 are the ones 'ast.rs' gives to Con and Tup
+        let lft = Box::new(self.tree(self.load(val(ptr) as usize * 2 + 0), Some(val(ptr) as usize * 2 + 0), vars, fresh)?);
+        let rgt = Box::new(self.tree(self.load(val(ptr) as usize * 2 + 1), Some(val(ptr) as usize * 2 + 1), vars, fresh)?);
+        match tag(ptr) - cuda::CT0 {
+          0   => Ok(ast::Tree::Con { lft, rgt }),
+          1   => Ok(ast::Tree::Tup { lft, rgt }),
+          lab => Ok(ast::Tree::Dup { lab: lab as run::Lab, lft, rgt }),
+        }
+      }
+      _ => {
+        Err(format!("found invalid pointer 0x{:08X}", ptr))
+      }
+    }
+  }
+}
@@ -0,0 +1,163 @@
+// Interaction tracing
+// -------------------
+// When built with the 'trace' feature, each NetFields records the interactions it performs: the
+// rule, both active pointers (the REF and its target, on derefs), the allocated locations and the
+// thread id. Events are written to a compact log, one per line, which can be read back, shown, and
+// compared step by step with the log of another run. Without the feature, nothing is recorded.
+
+use crate::ast;
+use crate::run::{self, Loc, Ptr};
+
+pub const ENABLED: bool = cfg!(feature = "trace");
+
+// An interaction rule.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Rule {
+  Anni, // annihilation
+  Comm, // commutation
+  Era2, // erasure of a binary node
+  Era1, // erasure of an unary node
+  Pass, // OP1 commutation
+  Copy, // NUM or REF copy
+  Mtch, // numeric match
+  Op2n, // binary operation
+  Op1n, // unary operation
+  Dref, // REF expansion
+  Void, // pair without effect
+}
+
+// A recorded interaction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Event {
+  pub tid : usize, // thread id
+  pub rule: Rule, // applied rule
+  pub a   : Ptr, // first active pointer (the REF, on derefs)
+  pub b   : Ptr, // second active pointer
+  pub locs: Vec<Loc>, // allocated locations
+}
+
+impl Rule {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Rule::Anni => "ANNI",
+      Rule::Comm => "COMM",
+      Rule::Era2 => "ERA2",
+      Rule::Era1 => "ERA1",
+      Rule::Pass => "PASS",
+      Rule::Copy => "COPY",
+      Rule::Mtch => "MTCH",
+      Rule::Op2n => "OP2N",
+      Rule::Op1n => "OP1N",
+      Rule::Dref => "DREF",
+      Rule::Void => "VOID",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Rule> {
+    [Rule::Anni, Rule::Comm, Rule::Era2, Rule::Era1, Rule::Pass, Rule::Copy, Rule::Mtch, Rule::Op2n, Rule::Op1n, Rule::Dref, Rule::Void]
+      .into_iter().find(|rule| rule.name() == name)
+  }
+}
+
+impl Event {
+  pub fn new(tid: usize, rule: Rule, a: Ptr, b: Ptr) -> Self {
+    Event { tid, rule, a, b, locs: vec![] }
+  }
+
+  // Compares two events, ignoring the thread id.
+  pub fn same(&self, other: &Event) -> bool {
+    self.rule == other.rule && self.a == other.a && self.b == other.b && self.locs == other.locs
+  }
+}
+
+// Log
+// ---
+
+// Format: one event per line, as "tid RULE a b locs [@name]", with pointers and locations in hex,
+// locations separated by commas (or '-' if none) and the REF name on derefs, from the book's names.
+pub fn show_log(events: &[Event], names: &run::Names) -> String {
+  let mut log = String::new();
+  for event in events {
+    let locs = if event.locs.is_empty() {
+      "-".to_string()
+    } else {
+      event.locs.iter().map(|loc| format!("{:x}", loc)).collect::<Vec<_>>().join(",")
+    };
+    log.push_str(&format!("{} {} {:016x} {:016x} {}", event.tid, event.rule.name(), event.a.0, event.b.0, locs));
+    if event.rule == Rule::Dref {
+      log.push_str(&format!(" @{}", ast::show_name(event.a.val(), &[names])));
+    }
+    log.push_str("\n");
+  }
+  return log;
+}
+
+pub fn parse_log(log: &str) -> Result<Vec<Event>, String> {
+  let mut events = vec![];
+  for (i, line) in log.lines().enumerate() {
+    if line.trim().is_empty() {
+      continue;
+    }
+    let error = || format!("invalid event on line {}: '{}'", i + 1, line);
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    if words.len() < 5 {
+      return Err(error());
+    }
+    let tid  = words[0].parse::<usize>().map_err(|_| error())?;
+    let rule = Rule::from_name(words[1]).ok_or_else(error)?;
+    let a    = Ptr(run::Val::from_str_radix(words[2], 16).map_err(|_| error())?);
+    let b    = Ptr(run::Val::from_str_radix(words[3], 16).map_err(|_| error())?);
+    let mut event = Event::new(tid, rule, a, b);
+    if words[4] != "-" {
+      for loc in words[4].split(',') {
+        event.locs.push(Loc::from_str_radix(loc, 16).map_err(|_| error())?);
+      }
+    }
+    events.push(event);
+  }
+  return Ok(events);
+}
+
+// Viewer
+// ------
+
+pub fn show_event(event: &Event) -> String {
+  let mut text = format!("[{:02}] {} {} ~ {}", event.tid, event.rule.name(), event.a.view(), event.b.view());
+  if !event.locs.is_empty() {
+    text.push_str(&format!(" +{}", event.locs.iter().map(|loc| format!("{:08x}", loc)).collect::<Vec<_>>().join(" ")));
+  }
+  return text;
+}
+
+pub fn show_events(events: &[Event]) -> String {
+  let mut text = String::new();
+  for (step, event) in events.iter().enumerate() {
+    text.push_str(&format!("{:>8} {}\n", step, show_event(event)));
+  }
+  return text;
+}
+
+// Finds the first step where two logs differ, ignoring thread ids.
+pub fn find_diff(a: &[Event], b: &[Event]) -> Option<usize> {
+  let len = a.len().min(b.len());
+  (0 .. len).find(|&i| !a[i].same(&b[i])).or(if a.len() != b.len() { Some(len) } else { None })
+}
+
+// Shows the first difference between two logs, with a few steps of context.
+pub fn show_diff(a: &[Event], b: &[Event]) -> String {
+  const CONTEXT: usize = 3;
+  let Some(step) = find_diff(a, b) else {
+    return format!("Logs are equal ({} steps).\n", a.len());
+  };
+  let mut text = format!("Logs differ at step {} ({} vs {} steps):\n", step, a.len(), b.len());
+  for i in step.saturating_sub(CONTEXT) .. step {
+    text.push_str(&format!("  {:>8} {}\n", i, show_event(&a[i])));
+  }
+  for i in step .. (step + CONTEXT).min(a.len()) {
+    text.push_str(&format!("- {:>8} {}\n", i, show_event(&a[i])));
+  }
+  for i in step .. (step + CONTEXT).min(b.len()) {
+    text.push_str(&format!("+ {:>8} {}\n", i, show_event(&b[i])));
+  }
+  return text;
+}
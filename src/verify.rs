@@ -0,0 +1,120 @@
+// Differential testing
+// --------------------
+// Normalizes definitions under each evaluation strategy and checks that the strategies agree.
+// Normal forms are compared as graphs, with 'ast::net_equiv', including the redexes left in them,
+// except against the lazy strategy, which leaves the redexes the root doesn't need. Rewrite counts
+// are only compared between the sequential and parallel strategies, which perform the same
+// interactions in different orders: the lazy one skips what the root doesn't need, and compiled
+// code fuses some rules.
+
+use crate::ast;
+use crate::run::{self, Rewrites};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Strategy {
+  Sequential, // 'normal'
+  Parallel, // 'parallel_normal'
+  Lazy, // 'normal' on a lazy net
+  Compiled, // code from 'jit::compile_book', run by the caller
+}
+
+// The result of normalizing a definition with a strategy.
+#[derive(Clone)]
+pub struct Outcome {
+  pub strategy: Strategy,
+  pub norm: ast::Net, // normal form, with the redexes it was left with
+  pub rwts: Option<Rewrites>, // rewrite counts, if comparable
+}
+
+impl Strategy {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Strategy::Sequential => "sequential",
+      Strategy::Parallel   => "parallel",
+      Strategy::Lazy       => "lazy",
+      Strategy::Compiled   => "compiled",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "sequential" => Some(Strategy::Sequential),
+      "parallel"   => Some(Strategy::Parallel),
+      "lazy"       => Some(Strategy::Lazy),
+      "compiled"   => Some(Strategy::Compiled),
+      _            => None,
+    }
+  }
+
+  // If it performs exactly the interactions the sequential strategy does.
+  pub fn is_exact(&self) -> bool {
+    matches!(self, Strategy::Sequential | Strategy::Parallel)
+  }
+}
+
+// Normalizes each of the given definitions with a strategy, on a single net of the given size.
+pub fn normalize(book: &run::Book, names: &[String], strategy: Strategy, size: usize) -> Result<Vec<Outcome>, String> {
+  if strategy == Strategy::Compiled {
+    return Err(format!("compiled code must be run by the caller"));
+  }
+  let mut net = run::Net::new(size, strategy == Strategy::Lazy);
+  let mut outs = vec![];
+  for name in names {
+    net.boot_with(book, name, &[])?;
+    match strategy {
+      Strategy::Parallel => net.parallel_normal(book),
+      _                  => net.normal(book),
+    }
+    let norm = net.to_net();
+    let rwts = if strategy.is_exact() { Some(net.get_rewrites()) } else { None };
+    outs.push(Outcome { strategy, norm, rwts });
+  }
+  return Ok(outs);
+}
+
+// Compares the outcomes of a definition with the first one, describing the first disagreement.
+pub fn compare(book: &run::Book, outs: &[Outcome]) -> Option<String> {
+  let (base, rest) = outs.split_first()?;
+  for out in rest {
+    let lazy = base.strategy == Strategy::Lazy || out.strategy == Strategy::Lazy;
+    let (a, b) = if lazy {
+      (ast::Net::new(base.norm.root.clone(), vec![]), ast::Net::new(out.norm.root.clone(), vec![]))
+    } else {
+      (base.norm.clone(), out.norm.clone())
+    };
+    if !ast::net_equiv(&a, &b) {
+      return Some(format!("normal forms differ between {} and {}:\n  {}\n  {}",
+        base.strategy.name(), out.strategy.name(), ast::show_net_in(&a, &book.names), ast::show_net_in(&b, &book.names)));
+    }
+    if let (Some(a), Some(b)) = (base.rwts, out.rwts) {
+      let a = [a.anni, a.comm, a.eras, a.dref, a.oper];
+      let b = [b.anni, b.comm, b.eras, b.dref, b.oper];
+      if a != b {
+        return Some(format!("rewrite counts differ between {} and {} (ANNI, COMM, ERAS, DREF, OPER):\n  {:?}\n  {:?}",
+          base.strategy.name(), out.strategy.name(), a, b));
+      }
+    }
+  }
+  return None;
+}
+
+// Normalizes the given definitions with each in-process strategy, returning their outcomes.
+pub fn collect(book: &run::Book, names: &[String], strategies: &[Strategy], size: usize) -> Result<Vec<Vec<Outcome>>, String> {
+  let mut outs = names.iter().map(|_| vec![]).collect::<Vec<Vec<Outcome>>>();
+  for strategy in strategies.iter().filter(|strategy| **strategy != Strategy::Compiled) {
+    for (i, out) in normalize(book, names, *strategy, size)?.into_iter().enumerate() {
+      outs[i].push(out);
+    }
+  }
+  return Ok(outs);
+}
+
+// The first definition on which the outcomes disagree, and how.
+pub fn first_mismatch(book: &run::Book, names: &[String], outs: &[Vec<Outcome>]) -> Option<(String, String)> {
+  names.iter().zip(outs).find_map(|(name, outs)| Some((name.clone(), compare(book, outs)?)))
+}
+
+// Checks that the in-process strategies agree on the given definitions.
+pub fn verify(book: &run::Book, names: &[String], strategies: &[Strategy], size: usize) -> Result<Option<(String, String)>, String> {
+  Ok(first_mismatch(book, names, &collect(book, names, strategies, size)?))
+}
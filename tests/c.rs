@@ -0,0 +1,65 @@
+use hvmc::{ast::{self, Book}, c, run};
+use loaders::*;
+use std::{fs, process::Command};
+
+mod loaders;
+
+// Compiles a book to C and runs it with '-s', returning its output, or None if there's no 'cc'
+fn run_c(name: &str, book: &run::Book, size: usize) -> Option<String> {
+  if Command::new("cc").arg("--version").output().is_err() {
+    eprintln!("skipping {}: cc not found", name);
+    return None;
+  }
+  let dir = std::env::temp_dir().join(format!("hvmc-c-{}-{}", std::process::id(), name));
+  fs::create_dir_all(&dir).unwrap();
+  let src = dir.join("main.c");
+  let exe = dir.join("main");
+  fs::write(&src, c::compile_book(book).unwrap()).unwrap();
+  let cc = Command::new("cc").arg("-O2").arg(format!("-DHVMC_HEAP_SIZE={}", size)).arg("-o").arg(&exe).arg(&src).output().unwrap();
+  assert!(cc.status.success(), "cc failed on {}:\n{}", name, String::from_utf8_lossy(&cc.stderr));
+  let out = Command::new(&exe).arg("-s").output().unwrap();
+  assert!(out.status.success(), "{} failed:\n{}", name, String::from_utf8_lossy(&out.stderr));
+  fs::remove_dir_all(&dir).unwrap();
+  Some(String::from_utf8(out.stdout).unwrap())
+}
+
+// Checks that the C program reaches the same normal form as 'run.rs', with the same rewrites
+fn check_c(name: &str, book: Book, size: usize) {
+  let book = ast::book_to_runtime(&book);
+  let Some(out) = run_c(name, &book, size) else {
+    return;
+  };
+  let mut net = run::Net::new(size, false);
+  net.normal(&book);
+  let rwts = net.get_rewrites();
+
+  let norm = out.lines().take_while(|line| !line.starts_with("RWTS")).collect::<Vec<_>>().join("\n");
+  assert!(ast::net_equiv(&ast::do_parse_net(&norm), &net.to_net()), "{}: C gives {}, run.rs gives {}", name, norm, net.show(&book));
+  let want = [
+    format!("RWTS   : {}", rwts.total()),
+    format!("- ANNI : {}", rwts.anni),
+    format!("- COMM : {}", rwts.comm),
+    format!("- ERAS : {}", rwts.eras),
+    format!("- DREF : {}", rwts.dref),
+    format!("- OPER : {}", rwts.oper),
+  ];
+  let got = out.lines().skip_while(|line| !line.starts_with("RWTS")).take(want.len()).collect::<Vec<_>>();
+  assert_eq!(got, want, "{}: rewrite counts differ", name);
+}
+
+#[test]
+fn test_c_programs() {
+  for file in ["chained_ops.hvm", "church_mul.hvm", "queue.hvm", "tree_alloc.hvm"] {
+    let mut book = load_lang(file);
+    let book = hvml::compile_book(&mut book, hvml::OptimizationLevel::Heavy).unwrap().core_book;
+    check_c(file, book, 1 << 16);
+  }
+}
+
+#[test]
+fn test_c_examples() {
+  for file in ["church_encoding/church.hvmc", "machine_u32/num_match.hvmc", "stress_tests/fib_rec.hvmc"] {
+    let code = fs::read_to_string(format!("{}/examples/{}", env!("CARGO_MANIFEST_DIR"), file)).unwrap();
+    check_c(&file.replace('/', "-"), parse_core(&code), 1 << 20);
+  }
+}
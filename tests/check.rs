@@ -0,0 +1,105 @@
+use hvmc::{ast::*, run::{self, Ptr, P1, P2}};
+use insta::assert_snapshot;
+use std::fs;
+
+// Normalizes '@main = (a a)', returning the net and the location of its only node
+fn normal_id() -> (run::Net, run::Loc) {
+  let book = book_to_runtime(&do_parse_book("@main = (a a)"));
+  let mut net = run::Net::new(1 << 10, false);
+  net.normal(&book);
+  let root = net.get_cell(0, P2);
+  assert!(root.is_ctr());
+  (net, root.loc())
+}
+
+#[test]
+fn test_check_clean() {
+  let code = fs::read_to_string(format!("{}/examples/church_encoding/church.hvmc", env!("CARGO_MANIFEST_DIR"))).unwrap();
+  let book = book_to_runtime(&do_parse_book(&code));
+  let mut net = run::Net::new(1 << 16, false);
+  net.set_check(true);
+  net.normal(&book);
+  assert_eq!(net.check_integrity(true), Ok(18));
+  let (net, _) = normal_id();
+  assert_eq!(net.check_integrity(true), Ok(1));
+}
+
+#[test]
+fn test_check_back_pointer() {
+  // Both aux ports of the node point to its second port
+  let (mut net, loc) = normal_id();
+  net.set_cell(loc, P2, Ptr::new(run::VR2, 0, loc));
+  assert_snapshot!(net.check_integrity(true).unwrap_err(), @"(VR1 0000000 00000001) points to (VR2 0000000 00000001), which points to (VR2 0000000 00000001)");
+}
+
+#[test]
+fn test_check_redirection() {
+  // The first port holds a redirection, which is only reported on normal nets
+  let (mut net, loc) = normal_id();
+  net.set_cell(loc, P1, Ptr::new(run::RD2, 0, loc));
+  assert_eq!(net.check_integrity(false), Ok(1));
+  assert_snapshot!(net.check_integrity(true).unwrap_err(), @r###"
+    dangling redirection at (VR1 0000000 00000001)
+    dangling redirection at (VR1 0000000 00000001)
+  "###);
+}
+
+#[test]
+fn test_check_lock() {
+  // A locked cell left on an unreachable node, found by the heap scan
+  let (mut net, loc) = normal_id();
+  net.set_cell(loc + 1, P1, run::LOCK);
+  assert_eq!(net.check_integrity(false), Ok(1));
+  assert_snapshot!(net.check_integrity(true).unwrap_err(), @"locked cell at (VR1 0000000 00000002)");
+}
+
+// Corrupts a back-pointer of a normal net, then runs it again in debug mode
+fn run_corrupted(run: impl FnOnce(&mut run::Net, &run::Book)) {
+  let book = book_to_runtime(&do_parse_book("@main = (a a)"));
+  let (mut net, loc) = normal_id();
+  net.set_cell(loc, P2, Ptr::new(run::VR2, 0, loc));
+  net.set_check(true);
+  net.set_threads(2);
+  run(&mut net, &book);
+}
+
+// All modes panic with the report, rather than exiting
+#[test]
+#[should_panic(expected = "integrity check failed after epoch")]
+fn test_check_epoch() {
+  run_corrupted(|net, book| net.normal(book));
+}
+
+#[test]
+#[should_panic(expected = "integrity check failed after epoch")]
+fn test_check_epoch_parallel() {
+  run_corrupted(|net, book| net.parallel_normal(book));
+}
+
+#[test]
+#[should_panic(expected = "integrity check failed after epoch")]
+fn test_check_epoch_simulated() {
+  run_corrupted(|net, book| net.simulated_normal(book, 0));
+}
+
+#[test]
+fn test_collect_garbage() {
+  // Applying the identity to 'x' and passing it its own result as 'x' leaves a DUP whose principal
+  // port is wired to its own aux port: a cycle that no ERA can reach
+  let book = book_to_runtime(&do_parse_book("@main = * & (x {2 x *}) ~ (z z)"));
+  let mut net = run::Net::new(1 << 10, false);
+  net.normal(&book);
+  assert_eq!(net.check_integrity(true), Ok(0));
+  assert_eq!(net.get_usage().live(), 1);
+  assert_eq!(net.collect_garbage(false), 1);
+  assert_eq!(net.get_usage().live(), 1);
+  assert_eq!(net.collect_garbage(true), 1);
+  assert_eq!(net.get_usage().live(), 0);
+  assert_eq!(net.collect_garbage(false), 0);
+  assert_eq!(net.check_integrity(true), Ok(0));
+
+  // Nothing leaks on a net whose nodes are all reachable
+  let (mut net, _) = normal_id();
+  assert_eq!(net.collect_garbage(true), 0);
+  assert_eq!(net.get_usage().live(), 1);
+}
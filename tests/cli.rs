@@ -0,0 +1,118 @@
+#![cfg(feature = "hvm_cli_options")]
+
+use insta::assert_snapshot;
+use std::{fs, path::Path, process::{Command, Output}};
+
+// Runs the 'hvmc' binary on the given directory
+fn hvmc_at(dir: &Path, args: &[&str]) -> Output {
+  Command::new(env!("CARGO_BIN_EXE_hvmc")).args(args).current_dir(dir).output().unwrap()
+}
+
+// Runs the 'hvmc' binary from the crate root, returning its stdout and whether it succeeded
+fn hvmc(args: &[&str]) -> (String, bool) {
+  let out = hvmc_at(Path::new(env!("CARGO_MANIFEST_DIR")), args);
+  (String::from_utf8(out.stdout).unwrap(), out.status.success())
+}
+
+// Replaces the value of each timing line ('KEY : value' or '"key": value,'), which varies by run
+fn mask_times(text: &str, keys: &[&str]) -> String {
+  text.lines().map(|line| {
+    match keys.iter().find(|key| line.trim_start().starts_with(*key)) {
+      Some(key) => {
+        let (head, _) = line.split_at(line.find(key).unwrap() + key.len());
+        format!("{} _{}", head, if line.ends_with(',') { "," } else { "" })
+      }
+      None => line.to_string(),
+    }
+  }).collect::<Vec<_>>().join("\n")
+}
+
+#[test]
+fn test_stats_json() {
+  let json = std::env::temp_dir().join(format!("hvmc-stats-{}.json", std::process::id()));
+  let (_, ok) = hvmc(&["run", "examples/church_encoding/church.hvmc", "-1", "--stats-json", json.to_str().unwrap()]);
+  assert!(ok);
+  let stats = fs::read_to_string(&json).unwrap();
+  fs::remove_file(&json).unwrap();
+  assert_snapshot!(mask_times(&stats, &["\"wall_time\":", "\"cpu_time\":", "\"mrps\":"]), @r###"
+    {
+      "rwts": { "total": 37, "anni": 25, "comm": 1, "eras": 0, "dref": 11, "oper": 0 },
+      "wall_time": _,
+      "cpu_time": _,
+      "mrps": _,
+      "heap_size": 268435456,
+      "live_nodes": 18,
+      "peak_nodes": 32,
+      "peak_nodes_exact": true,
+      "threads": 1,
+      "epochs": 1,
+      "splits": 0,
+      "thread_rwts": [37]
+    }
+  "###);
+}
+
+#[test]
+fn test_run_stats() {
+  let (out, ok) = hvmc(&["run", "examples/church_encoding/church.hvmc", "-1", "-s"]);
+  assert!(ok);
+  assert_snapshot!(mask_times(&out, &["TIME   :", "RPS    :"]), @r###"
+    ({2 {2 {2 {2 {2 * (a b)} (b c)} (c d)} (d e)} {2 ({2 e f} g) {3 (g h) (h {2 f i})}}} (a i))
+    RWTS   : 37
+    - ANNI : 25
+    - COMM : 1
+    - ERAS : 0
+    - DREF : 11
+    - OPER : 0
+    LIVE   : 18 nodes
+    PEAK   : 32 nodes
+    TIME   : _
+    RPS    : _
+  "###);
+  let (out, ok) = hvmc(&["run", "examples/church_encoding/church.hvmc", "-s", "--threads", "2"]);
+  assert!(ok);
+  assert!(out.contains("PEAK   : at most "));
+}
+
+#[test]
+fn test_eval_parse_error() {
+  let out = hvmc_at(Path::new(env!("CARGO_MANIFEST_DIR")), &["eval", "examples/church_encoding/church.hvmc", "(a"]);
+  assert_eq!(out.status.code(), Some(1));
+  assert_snapshot!(String::from_utf8(out.stderr).unwrap(), @"Can't parse: At 1:3: Expected a name character, found None");
+}
+
+#[test]
+fn test_option_values() {
+  // The value of '--threads' isn't taken for the net to evaluate
+  let (out, ok) = hvmc(&["eval", "examples/church_encoding/church.hvmc", "--threads", "1", "@c2", "-1"]);
+  assert!(ok);
+  assert_snapshot!(out, @"({2 (a b) (b c)} (a c))");
+
+  // '--stats-json' given as the value of '--profile-json' is a file name, not an option
+  let dir = std::env::temp_dir().join(format!("hvmc-opts-{}", std::process::id()));
+  fs::create_dir_all(&dir).unwrap();
+  let file = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/church_encoding/church.hvmc");
+  let out = hvmc_at(&dir, &["run", file.to_str().unwrap(), "-1", "--profile-json", "--stats-json", "stats.json"]);
+  assert!(out.status.success());
+  assert!(fs::read_to_string(dir.join("--stats-json")).unwrap().contains("\"defs\""));
+  assert!(!dir.join("stats.json").exists());
+  fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_prune_entries() {
+  let dir = std::env::temp_dir().join(format!("hvmc-prune-{}", std::process::id()));
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(dir.join("main.hvmc"), "@main = (@a *)\n@a = *\n@b = @c\n@c = *\n@d = *").unwrap();
+  let out = hvmc_at(&dir, &["prune", "main.hvmc", "--entry", "main,b"]);
+  assert!(out.status.success());
+  assert_eq!(String::from_utf8(out.stderr).unwrap(), "Pruned 1 of 5 definitions.\n");
+  assert_eq!(String::from_utf8(out.stdout).unwrap(), "@a = *\n@b = @c\n@c = *\n@main = (@a *)\n");
+
+  // An entry point that isn't defined is likely a typo, rather than a request to drop everything
+  let out = hvmc_at(&dir, &["prune", "main.hvmc", "--entry", "main,missing"]);
+  fs::remove_dir_all(&dir).unwrap();
+  assert!(!out.status.success());
+  assert_eq!(String::from_utf8(out.stderr).unwrap(), "Unknown definition @missing\n");
+  assert_eq!(out.stdout, b"");
+}
@@ -0,0 +1,105 @@
+use hvmc::{ast::*, cuda, run};
+use insta::{assert_debug_snapshot, assert_snapshot};
+
+fn cuda_book(code: &str) -> Result<cuda::Book, String> {
+  cuda::Book::new(&book_to_runtime(&do_parse_book(code)))
+}
+
+#[test]
+fn test_encode_ptr() {
+  let names = run::Names::default();
+  assert_debug_snapshot!(cuda::encode_ptr(run::Ptr::new(run::LAM, 2, 5), &names), @r###"
+    Ok(
+        92,
+    )
+  "###);
+  assert_debug_snapshot!(cuda::encode_ptr(run::Ptr::big(run::NUM, 1 << 28), &names), @r###"
+    Err(
+        "number #268435456 doesn't fit in 28 bits",
+    )
+  "###);
+  assert_debug_snapshot!(cuda::encode_ptr(run::Ptr::new(run::DUP, 5, 0), &names), @r###"
+    Err(
+        "constructor label 5 is too large, since only labels 0 to 4 are supported",
+    )
+  "###);
+  assert_debug_snapshot!(cuda::encode_ptr(run::Ptr::new(run::OP2, run::ADD, 0), &names), @r###"
+    Err(
+        "numeric operation '+' can't be encoded, since CUDA pointers have no room for operators",
+    )
+  "###);
+  assert_debug_snapshot!(cuda::encode_ptr(run::Ptr::big(run::REF, name_to_val("hello")), &names), @r###"
+    Err(
+        "reference @hello has id 0x2ba2fbf2, which doesn't fit the 24-bit jump table",
+    )
+  "###);
+}
+
+#[test]
+fn test_book_limits() {
+  assert_debug_snapshot!(cuda_book("@main = @hello\n@hello = *").map(|_| ()), @r###"
+    Err(
+        "@main: reference @hello has id 0x2ba2fbf2, which doesn't fit the 24-bit jump table",
+    )
+  "###);
+  assert_debug_snapshot!(cuda_book("@main = r & #1 ~ <+ #2 r>").map(|_| ()), @r###"
+    Err(
+        "@main: numeric operation '+' can't be encoded, since CUDA pointers have no room for operators",
+    )
+  "###);
+  assert_debug_snapshot!(cuda_book("@main = {9 a a}").map(|_| ()), @r###"
+    Err(
+        "@main: constructor label 9 is too large, since only labels 0 to 4 are supported",
+    )
+  "###);
+}
+
+#[test]
+fn test_book_output() {
+  let book = cuda_book("@L.id = (a a)\n@main = r & @L.id ~ (#1 r)").unwrap();
+  assert_snapshot!(book.to_c(), @r###"
+    const u32 F__57fb27 = 0x57fb27;
+    const u32 F_main = 0xc24b31;
+
+    u32 BOOK_DATA[] = {
+      // @L.id
+      // .nlen
+      0x00000002,
+      // .rlen
+      0x00000000,
+      // .node
+      0x00000000, 0x0000001A,  0x00000011, 0x00000010,
+      // .rdex
+      // @main
+      // .nlen
+      0x00000002,
+      // .rlen
+      0x00000001,
+      // .node
+      0x00000000, 0x00000011,  0x00000016, 0x00000001,
+      // .rdex
+      0x057FB274, 0x0000001A,
+    };
+
+    u32 JUMP_DATA[] = {
+      0x0057FB27, 0x00000000, // @L.id
+      0x00C24B31, 0x00000006, // @main
+    };
+  "###);
+  assert_snapshot!(book.to_json(), @r###"
+    {
+      "jump": [
+        { "name": "L.id", "fid": 5765927, "init": 0 },
+        { "name": "main", "fid": 12733233, "init": 6 }
+      ],
+      "book": [2, 0, 0, 26, 17, 16, 2, 1, 0, 17, 22, 1, 92254836, 26]
+    }
+  "###);
+  let bin = book.to_bin();
+  let words = bin.chunks(4).map(|word| u32::from_le_bytes(word.try_into().unwrap())).collect::<Vec<_>>();
+  assert_eq!(words[0], cuda::MAGIC);
+  assert_eq!(words[1] as usize, book.jump.len());
+  assert_eq!(words[2] as usize, book.book.len());
+  assert_eq!(words[3 + book.jump.len() * 2 ..], book.book[..]);
+  assert_snapshot!(format!("{:x?}", words), @"[434d5648, 2, e, 57fb27, 0, c24b31, 6, 2, 0, 0, 1a, 11, 10, 2, 1, 0, 11, 16, 1, 57fb274, 1a]");
+}
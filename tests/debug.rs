@@ -0,0 +1,50 @@
+use hvmc::{ast::*, debug::{alloc_nodes, Debugger}};
+use insta::assert_snapshot;
+
+const BOOK: &str = "
+  @true = (a (* a))
+  @fals = (* (a a))
+  @and  = ((a (@fals b)) (a b))
+  @main = root & @and ~ (@true (@fals root))
+";
+
+fn exec(dbg: &mut Debugger, line: &str) -> Result<String, String> {
+  dbg.exec(line).expect("debugger quit")
+}
+
+#[test]
+fn test_debug_commands() {
+  let book = book_to_runtime(&do_parse_book(BOOK));
+  let nodes = alloc_nodes(1 << 12);
+  let mut dbg = Debugger::new(&nodes, &book, name_to_val("main"));
+  assert_snapshot!(exec(&mut dbg, "r").unwrap(), @r###"No redexes. Next step expands: (REF "main")"###);
+  assert_snapshot!(exec(&mut dbg, "step 2").unwrap(), @"Stopped at step 2.");
+  assert_snapshot!(exec(&mut dbg, "t").unwrap(), @"STEP: 2 | RWTS: 2 (ANNI 0, COMM 0, ERAS 0, DREF 2, OPER 0) | RDEX: 1");
+  assert_snapshot!(exec(&mut dbg, "b @fals").unwrap(), @"Breakpoint set on @fals.");
+  assert_snapshot!(exec(&mut dbg, "l").unwrap(), @"@fals");
+  assert_snapshot!(exec(&mut dbg, "c").unwrap(), @"Breakpoint @fals hit at step 7.");
+  assert!(dbg.held);
+  assert_snapshot!(exec(&mut dbg, "r").unwrap(), @r###"No redexes. Next step expands: (REF "fals")"###);
+  assert_snapshot!(exec(&mut dbg, "d fals").unwrap(), @"Breakpoint on @fals removed.");
+  assert_snapshot!(exec(&mut dbg, "continue").unwrap(), @"Normal form reached at step 8.");
+  assert_snapshot!(exec(&mut dbg, "net").unwrap(), @"(* (a a))");
+  assert_snapshot!(exec(&mut dbg, "k").unwrap(), @"Heap is consistent (2 reachable nodes).");
+  assert_snapshot!(exec(&mut dbg, "").unwrap(), @"");
+  assert!(dbg.exec("q").is_none());
+  assert!(dbg.exec("quit").is_none());
+}
+
+#[test]
+fn test_debug_errors() {
+  let book = book_to_runtime(&do_parse_book(BOOK));
+  let nodes = alloc_nodes(1 << 12);
+  let mut dbg = Debugger::new(&nodes, &book, name_to_val("main"));
+  assert_snapshot!(exec(&mut dbg, "step x").unwrap_err(), @"invalid step count 'x'");
+  assert_snapshot!(exec(&mut dbg, "inspect zz").unwrap_err(), @"invalid location 'zz'");
+  assert_snapshot!(exec(&mut dbg, "inspect").unwrap_err(), @"missing location");
+  assert_snapshot!(exec(&mut dbg, "break").unwrap_err(), @"missing definition name");
+  assert_snapshot!(exec(&mut dbg, "break @nope").unwrap_err(), @"unknown definition @nope");
+  assert_snapshot!(exec(&mut dbg, "delete @true").unwrap_err(), @"no breakpoint on @true");
+  assert_snapshot!(exec(&mut dbg, "frobnicate").unwrap_err(), @"unknown command 'frobnicate' (try 'help')");
+  assert_eq!(dbg.steps, 0);
+}
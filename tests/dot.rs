@@ -0,0 +1,48 @@
+use hvmc::{ast::*, debug::{alloc_nodes, Debugger}};
+use insta::assert_snapshot;
+
+const BOOK: &str = "
+  @id   = (a a)
+  @main = r & @id ~ ({2 x x} r)
+";
+
+#[test]
+fn test_dot_def() {
+  let book = do_parse_book(BOOK);
+  assert_snapshot!(net_to_dot(&book["main"]), @r###"
+    graph {
+      node [shape=record, fontname=monospace];
+      root [label="ROOT", shape=plaintext];
+      n0 [label="{<p0> |@id}", color=red, penwidth=2];
+      n1 [label="{<p0> |CON|{<p1> |<p2> }}", color=red, penwidth=2];
+      n2 [label="{<p0> |DUP 2|{<p1> |<p2> }}"];
+      n2:p1 -- n2:p2 [style=dashed, label="x"];
+      n1:p1 -- n2:p0;
+      root -- n1:p2 [style=dashed, label="r"];
+      n0:p0 -- n1:p0 [color=red, penwidth=2];
+    }
+  "###);
+}
+
+#[test]
+fn test_dot_after() {
+  // Like 'hvmc dot --after 2': @main is expanded, then @id is
+  let book = book_to_runtime(&do_parse_book(BOOK));
+  let nodes = alloc_nodes(1 << 12);
+  let mut dbg = Debugger::new(&nodes, &book, name_to_val("main"));
+  dbg.run(2);
+  assert_snapshot!(net_to_dot_in(&dbg.to_net(), &book.names), @r###"
+    graph {
+      node [shape=record, fontname=monospace];
+      root [label="ROOT", shape=plaintext];
+      n0 [label="{<p0> |CON|{<p1> |<p2> }}", color=red, penwidth=2];
+      n0:p1 -- n0:p2 [style=dashed, label="b"];
+      n1 [label="{<p0> |CON|{<p1> |<p2> }}", color=red, penwidth=2];
+      n2 [label="{<p0> |DUP 2|{<p1> |<p2> }}"];
+      n2:p1 -- n2:p2 [style=dashed, label="c"];
+      n1:p1 -- n2:p0;
+      root -- n1:p2 [style=dashed, label="a"];
+      n0:p0 -- n1:p0 [color=red, penwidth=2];
+    }
+  "###);
+}
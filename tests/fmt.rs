@@ -0,0 +1,56 @@
+use hvmc::fmt::{format, WIDTH};
+use insta::assert_snapshot;
+
+// Formats code, checking that formatting the result again changes nothing.
+fn format_twice(code: &str, width: usize) -> String {
+  let once = format(code, width).unwrap();
+  let twice = format(&once, width).unwrap();
+  assert_eq!(once, twice);
+  once
+}
+
+#[test]
+fn test_fmt_comments() {
+  let code = format_twice(
+    "// Church numerals
+@c2 = ({3 (a b) (b c)} (a c)) // two
+
+// applies c2 to itself
+@main   =    r
+  // the call
+  & @c2 ~ (@c2 r)
+  & * ~ *    // nothing
+// end
+",
+    WIDTH,
+  );
+  assert_snapshot!(code, @r###"
+  // Church numerals
+  @c2 = ({3 (a b) (b c)} (a c)) // two
+
+  // applies c2 to itself
+  @main = r
+    // the call
+    & @c2 ~ (@c2 r)
+    & * ~ * // nothing
+  // end
+  "###);
+}
+
+#[test]
+fn test_fmt_break() {
+  let code = format_twice("@main = (long_name_a (long_name_b (long_name_c long_name_d))) & (long_name_c long_name_b) ~ (long_name_a long_name_d)\n", 40);
+  assert_snapshot!(code, @r###"
+  @main = (
+    long_name_a
+    (
+      long_name_b
+      (long_name_c long_name_d)
+    )
+  )
+    & (long_name_c long_name_b) ~ (
+      long_name_a
+      long_name_d
+    )
+  "###);
+}
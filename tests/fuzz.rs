@@ -0,0 +1,18 @@
+use hvmc::{ast::show_book, fuzz::{Fuzzer, Generator}, rng::Rng};
+
+#[test]
+fn test_fuzz_seeds() {
+  let fuzzer = Fuzzer { threads: vec![1, 2, 3], size: 1 << 18, ..Fuzzer::default() };
+  match fuzzer.fuzz(0, 100) {
+    Ok(stats) => assert!(stats.books > 0),
+    Err(fail) => panic!("seed {} fails: {}\n{}", fail.seed, fail.error, show_book(&fail.book)),
+  }
+}
+
+#[test]
+fn test_generate_deterministic() {
+  let gen = Generator::default();
+  for seed in 0 .. 10 {
+    assert_eq!(gen.generate(&mut Rng::new(seed)), gen.generate(&mut Rng::new(seed)));
+  }
+}
@@ -0,0 +1,53 @@
+use hvmc::{ast::*, jit::compile_term};
+use insta::assert_snapshot;
+
+fn compile(code: &str) -> String {
+  let book = book_to_runtime(&do_parse_book(code));
+  compile_term(&book, 0, name_to_val("foo"))
+}
+
+#[test]
+fn test_atom_atom_redex() {
+  assert_snapshot!(compile("@foo = * & * ~ #1"), @r###"
+  pub fn L_foo(&mut self, lab: Lab) -> bool {
+    return false;
+  }
+  pub fn F_foo(&mut self, ptr: Ptr, trg: Trg) -> bool {
+    if self.get(trg).is_dup() && !self.L_foo(self.get(trg).lab()) {
+      self.copy(self.swap(trg, NULL), ptr);
+      return true;
+    }
+    self.safe_link(Trg::Ptr(Ptr::new(ERA, 0x0, 0x0)), Trg::Ptr(Ptr::new(NUM, 0x1, 0x0)));
+    // fast erase
+    if self.get(trg).is_skp() {
+      self.swap(trg, NULL);
+      self.rwts.eras += 1;
+    } else {
+      self.safe_link(trg, Trg::Ptr(Ptr::new(ERA, 0x0, 0x0)));
+    }
+    return true;
+  }
+  "###);
+}
+
+#[test]
+fn test_term_term_redex() {
+  assert_snapshot!(compile("@foo = r & (a a) ~ (r *)"), @r###"
+  pub fn L_foo(&mut self, lab: Lab) -> bool {
+    return false;
+  }
+  pub fn F_foo(&mut self, ptr: Ptr, trg: Trg) -> bool {
+    if self.get(trg).is_dup() && !self.L_foo(self.get(trg).lab()) {
+      self.copy(self.swap(trg, NULL), ptr);
+      return true;
+    }
+    let k1 = self.alloc();
+    self.safe_link(Trg::Ptr(Ptr::new(VR1, 0, k1)), Trg::Ptr(Ptr::new(VR2, 0, k1)));
+    let k2 = self.alloc();
+    self.safe_link(Trg::Ptr(Ptr::new(VR2, 0, k2)), Trg::Ptr(Ptr::new(ERA, 0x0, 0x0)));
+    self.safe_link(Trg::Ptr(Ptr::new(LAM, 0, k1)), Trg::Ptr(Ptr::new(LAM, 0, k2)));
+    self.safe_link(trg, Trg::Ptr(Ptr::new(VR1, 0, k2)));
+    return true;
+  }
+  "###);
+}
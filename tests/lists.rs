@@ -29,7 +29,7 @@ fn test_list_got() {
   ] {
     let mut book = list_got(index);
     let (rnet, _, _) = hvm_lang_normal(&mut book, 2048);
-    rwts.push(rnet.rewrites())
+    rwts.push(rnet.get_rewrites().total())
   }
 
   assert_debug_snapshot!(rwts[0], @"306");
@@ -60,7 +60,7 @@ fn test_list_put() {
   for (index, value) in [(0, 2), (1, 4), (3, 8), (7, 16), (15, 32)] {
     let mut book = list_put(index, value);
     let (rnet, _, _) = hvm_lang_normal(&mut book, 2048);
-    rwts.push(rnet.rewrites())
+    rwts.push(rnet.get_rewrites().total())
   }
 
   assert_debug_snapshot!(rwts[0], @"295");
@@ -41,18 +41,16 @@ pub fn hvm_lang_readback(net: &Net, book: &DefinitionBook, id_map: HashMap<run::
   (term_display.to_string(), readback_errs.is_empty())
 }
 
-pub fn hvm_lang_normal<'a>(book: &mut DefinitionBook, size: usize) -> (run::Net<'a>, Net, HashMap<run::Val, DefId>) {
+pub fn hvm_lang_normal(book: &mut DefinitionBook, size: usize) -> (run::Net, Net, HashMap<run::Val, DefId>) {
   let result = hvml::compile_book(book, hvml::OptimizationLevel::Heavy).unwrap();
   let (root, res_lnet) = normal(result.core_book, size);
   (root, res_lnet, result.hvmc_names.hvmc_name_to_id)
 }
 
 #[allow(unused_variables)]
-pub fn normal(book: Book, size: usize) -> (run::Net<'static>, Net) {
-  fn normal_cpu(book: run::Book, size: usize) -> run::Net<'static> {
-    let data = Box::leak(run::Heap::init(size));
-    let mut rnet = run::Net::new(data);
-    rnet.boot(name_to_val("main"));
+pub fn normal(book: Book, size: usize) -> (run::Net, Net) {
+  fn normal_cpu(book: run::Book, size: usize) -> run::Net {
+    let mut rnet = run::Net::new(size, false);
     rnet.normal(&book);
     rnet
   }
@@ -76,6 +74,17 @@ pub fn normal(book: Book, size: usize) -> (run::Net<'static>, Net) {
     }
   };
 
-  let net = net_from_runtime(&rnet);
+  let net = rnet.to_net();
+  (rnet, net)
+}
+
+// Like 'normal', but simulates the threads of a parallel run deterministically, in an order drawn
+// from 'seed', so that its heap and stats are the same on every run
+pub fn simulated_normal(book: Book, size: usize, threads: usize, seed: u64) -> (run::Net, Net) {
+  let book = book_to_runtime(&book);
+  let mut rnet = run::Net::new(size, false);
+  rnet.set_threads(threads);
+  rnet.simulated_normal(&book, seed);
+  let net = rnet.to_net();
   (rnet, net)
 }
@@ -0,0 +1,100 @@
+use hvmc::{ast::*, module::load_book, run};
+use insta::assert_snapshot;
+use std::{fs, path::PathBuf};
+
+// Writes files to a fresh temporary directory, returning its path
+fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!("hvmc-module-{}-{}", std::process::id(), test));
+  let _ = fs::remove_dir_all(&dir);
+  for (path, code) in files {
+    let path = dir.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, code).unwrap();
+  }
+  dir
+}
+
+fn load(dir: &PathBuf, file: &str) -> Result<Book, String> {
+  let book = load_book(dir.join(file).to_str().unwrap());
+  fs::remove_dir_all(dir).unwrap();
+  book
+}
+
+#[test]
+fn test_import_namespace() {
+  let dir = write_files("namespace", &[
+    ("main.hvmc", "import \"lib.hvmc\" as Lib\n@main = r & @Lib.app ~ (@Lib.id (#1 r))"),
+    ("lib.hvmc", "@id = (a a)\n@app = ((a b) (a b))\n@twice = (x y) & @app ~ (x (@id y))"),
+  ]);
+  let book = load(&dir, "main.hvmc").unwrap();
+  assert_snapshot!(show_book(&book), @r###"
+    @Lib.app = ((a b) (a b))
+    @Lib.id = (a a)
+    @Lib.twice = (x y)
+    & @Lib.app ~ (x (@Lib.id y))
+    @main = r
+    & @Lib.app ~ (@Lib.id (#1 r))
+  "###);
+  let book = book_to_runtime(&book);
+  let mut net = run::Net::new(1 << 12, false);
+  net.normal(&book);
+  assert_eq!(net.show(&book), "#1");
+}
+
+#[test]
+fn test_import_duplicates() {
+  // Both files define @id, the same up to variable names, so they merge
+  let dir = write_files("equivalent", &[
+    ("main.hvmc", "import \"lib.hvmc\"\n@id = (x x)\n@main = @id"),
+    ("lib.hvmc", "@id = (a a)"),
+  ]);
+  let book = load(&dir, "main.hvmc").unwrap();
+  assert_eq!(book.keys().collect::<Vec<_>>(), ["id", "main"]);
+
+  // A diamond import brings the same definitions twice
+  let dir = write_files("diamond", &[
+    ("main.hvmc", "import \"a.hvmc\"\nimport \"b.hvmc\"\n@main = @id"),
+    ("a.hvmc", "import \"id.hvmc\"\n@a = @id"),
+    ("b.hvmc", "import \"id.hvmc\"\n@b = @id"),
+    ("id.hvmc", "@id = (a a)"),
+  ]);
+  let book = load(&dir, "main.hvmc").unwrap();
+  assert_eq!(book.keys().collect::<Vec<_>>(), ["a", "b", "id", "main"]);
+
+  // A different @id is an error
+  let dir = write_files("different", &[
+    ("main.hvmc", "import \"lib.hvmc\"\n@id = (x x)\n@main = @id"),
+    ("lib.hvmc", "@id = (* (a a))"),
+  ]);
+  assert_snapshot!(load(&dir, "main.hvmc").unwrap_err().replace(dir.to_str().unwrap(), "$DIR"), @"On '$DIR/main.hvmc': Duplicate definition @id");
+}
+
+#[test]
+fn test_import_cycle() {
+  let dir = write_files("cycle", &[
+    ("main.hvmc", "import \"a.hvmc\"\n@main = @a"),
+    ("a.hvmc", "import \"b.hvmc\"\n@a = @b"),
+    ("b.hvmc", "import \"a.hvmc\"\n@b = *"),
+  ]);
+  assert_snapshot!(load(&dir, "main.hvmc").unwrap_err().replace(dir.to_str().unwrap(), "$DIR"), @"Import cycle through '$DIR/a.hvmc'");
+}
+
+#[test]
+fn test_import_relative() {
+  // Paths are relative to the importing file, not to the working directory
+  let dir = write_files("relative", &[
+    ("main.hvmc", "import \"lib/a.hvmc\"\n@main = @a"),
+    ("lib/a.hvmc", "import \"b.hvmc\"\nimport \"../c.hvmc\"\n@a = (@b @c)"),
+    ("lib/b.hvmc", "@b = *"),
+    ("c.hvmc", "@c = *"),
+  ]);
+  let book = load(&dir, "main.hvmc").unwrap();
+  assert_eq!(book.keys().collect::<Vec<_>>(), ["a", "b", "c", "main"]);
+
+  let dir = write_files("missing", &[
+    ("main.hvmc", "import \"lib/a.hvmc\"\n@main = @a"),
+    ("lib/a.hvmc", "import \"lib/b.hvmc\"\n@a = @b"),
+    ("lib/b.hvmc", "@b = *"),
+  ]);
+  assert!(load(&dir, "main.hvmc").unwrap_err().starts_with("Can't read '"));
+}
@@ -0,0 +1,78 @@
+use hvmc::{ast::*, module::load_book, repl::Repl, run};
+use std::fs;
+
+// A long name, a name that used to be packed at 10 characters, and a Unicode name
+const BOOK: &str = "
+  @a_long_name = (a a)
+  @tenletters = *
+  @λx = *
+  @main = (@a_long_name (@tenletters @λx))
+";
+
+fn normal(book: &Book) -> run::Net {
+  let mut net = run::Net::new(1 << 12, false);
+  net.normal(&book_to_runtime(book));
+  net
+}
+
+#[test]
+fn test_names_roundtrip_with_table() {
+  let book = do_parse_book(BOOK);
+  let net = normal(&book);
+  let shown = net.show(&book_to_runtime(&book));
+  assert_eq!(shown, "(@a_long_name (@tenletters @λx))");
+  let refs = ["a_long_name", "tenletters", "λx"].map(name_to_val);
+  assert!(refs.iter().all(|nam| is_interned(*nam)));
+  assert_eq!(net_refs(&do_parse_net(&shown)), refs);
+}
+
+#[test]
+fn test_names_roundtrip_without_table() {
+  // A net read back from the runtime has no meta, so interned ids are shown in hex
+  let book = do_parse_book(BOOK);
+  let shown = show_net(&normal(&book).to_net());
+  let refs = ["a_long_name", "tenletters", "λx"].map(name_to_val);
+  assert_eq!(shown, format!("(@0x{:x} (@0x{:x} @0x{:x}))", refs[0], refs[1], refs[2]));
+  assert_eq!(net_refs(&do_parse_net(&shown)), refs);
+  for nam in refs {
+    assert_eq!(name_to_val(&val_to_name(nam)), nam);
+  }
+
+  // Hex names that aren't an interned id, as shown, are interned as any other name
+  assert_ne!(name_to_val(&format!("0x{:X}", refs[0])), refs[0]);
+  assert_ne!(name_to_val(&format!("0x0{:x}", refs[0])), refs[0]);
+  assert!(is_interned(name_to_val("0x1")));
+  assert_ne!(name_to_val("0x1"), 1);
+
+  // And books using them still have working names
+  let book = do_parse_book(&format!("@main = (@0x{:x} @a_long_name)\n@a_long_name = *", refs[1]));
+  let rt_book = book_to_runtime(&book);
+  assert_eq!(rt_book.names.get(&refs[1]), None);
+  assert_eq!(show_name(refs[1], &[&rt_book.names]), format!("0x{:x}", refs[1]));
+  assert_eq!(show_name(refs[0], &[&rt_book.names]), "a_long_name");
+}
+
+#[test]
+fn test_names_collision() {
+  // Real collisions are unlikely, so one is faked with a reference to a different name
+  let mut book = do_parse_book("@a_long_name = *\n@main = @b_long_name\n@b_long_name = *");
+  let id = name_to_val("a_long_name");
+  book.get_mut("main").unwrap().meta.as_mut().unwrap().refs.insert(id, "other_name".to_string());
+  assert_eq!(book_names(&book).unwrap_err(), format!(
+    "Names @a_long_name and @other_name have the same id 0x{:x}. Please rename one of them.", id
+  ));
+
+  // A definition named by an interned id, in hex, collides with the name it stands for
+  let path = std::env::temp_dir().join(format!("hvmc-names-{}.hvmc", std::process::id()));
+  fs::write(&path, format!("@a_long_name = *\n@0x{:x} = (a a)\n@main = @a_long_name", id)).unwrap();
+  let err = load_book(path.to_str().unwrap()).unwrap_err();
+  fs::remove_file(&path).unwrap();
+  assert!(err.ends_with(&format!("Names @0x{:x} and @a_long_name have the same id 0x{:x}. Please rename one of them.", id, id)));
+
+  // And when defining it on the REPL, which then keeps its previous names
+  let mut repl = Repl::new(1 << 12);
+  repl.define(do_parse_book("@a_long_name = *")).unwrap();
+  let err = repl.define(book.into_iter().filter(|(name, _)| name == "main").collect()).unwrap_err();
+  assert!(err.starts_with("Names @a_long_name and @other_name"));
+  assert_eq!(show_name(id, &[&repl.defs.names]), "a_long_name");
+}
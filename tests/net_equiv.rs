@@ -0,0 +1,26 @@
+use hvmc::ast::{do_parse_net, net_equiv};
+use insta::assert_debug_snapshot;
+
+fn equiv(a: &str, b: &str) -> bool {
+  let (a, b) = (do_parse_net(a), do_parse_net(b));
+  assert_eq!(net_equiv(&a, &b), net_equiv(&b, &a));
+  net_equiv(&a, &b)
+}
+
+#[test]
+fn test_equiv_renamed() {
+  assert_debug_snapshot!(equiv("(a (b (a b)))", "(x (y (x y)))"), @"true");
+  assert_debug_snapshot!(equiv("r & (a a) ~ (#1 r) & @f ~ *", "s & * ~ @f & (#1 s) ~ (b b)"), @"true");
+}
+
+#[test]
+fn test_equiv_same_shape() {
+  // Same trees up to variables, wired differently.
+  assert_debug_snapshot!(equiv("(a (b (a b)))", "(a (b (b a)))"), @"false");
+  assert_debug_snapshot!(equiv("(a a)", "(a b) & * ~ (a b)"), @"false");
+  // Same wiring, different DUP labels or port order.
+  assert_debug_snapshot!(equiv("{2 a b}", "{3 a b}"), @"false");
+  assert_debug_snapshot!(equiv("(a {2 a b}) & b ~ *", "(a {2 b a}) & b ~ *"), @"false");
+  // Redexes connected across each other.
+  assert_debug_snapshot!(equiv("(a b) & (c d) ~ (a c) & (e f) ~ (b e)", "(a b) & (c d) ~ (a e) & (e f) ~ (b c)"), @"false");
+}
@@ -0,0 +1,46 @@
+use hvmc::{ast::*, opt::Optimizer};
+use insta::assert_snapshot;
+
+fn optimize(code: &str) -> (String, usize, usize) {
+  let (book, stats) = Optimizer::default().optimize(&do_parse_book(code));
+  (show_net(&book["main"]), stats.rwts, stats.inln)
+}
+
+#[test]
+fn test_inline_call() {
+  let (main, rwts, inln) = optimize(
+    "
+    @id   = (x x)
+    @main = r & @id ~ (#1 r)
+  ",
+  );
+  assert_snapshot!(main, @"#1");
+  assert_snapshot!(format!("{rwts} rewrites, {inln} inlined"), @"2 rewrites, 1 inlined");
+}
+
+#[test]
+fn test_ref_dup_copy() {
+  let (main, rwts, inln) = optimize(
+    "
+    @id   = (x x)
+    @main = (a b) & @id ~ {3 a b}
+  ",
+  );
+  assert_snapshot!(main, @"(@id @id)");
+  assert_snapshot!(format!("{rwts} rewrites, {inln} inlined"), @"1 rewrites, 0 inlined");
+}
+
+#[test]
+fn test_keep_recursive_call() {
+  let (main, rwts, inln) = optimize(
+    "
+    @loop = (x r) & @loop ~ (x r)
+    @main = r & @loop ~ (#1 r)
+  ",
+  );
+  assert_snapshot!(main, @r###"
+  r
+  & @loop ~ (#1 r)
+  "###);
+  assert_snapshot!(format!("{rwts} rewrites, {inln} inlined"), @"0 rewrites, 0 inlined");
+}
@@ -0,0 +1,54 @@
+use hvmc::{ast::*, run};
+use insta::assert_snapshot;
+use std::fs;
+
+fn load_example(file: &str) -> String {
+  fs::read_to_string(format!("{}/examples/{}", env!("CARGO_MANIFEST_DIR"), file)).unwrap()
+}
+
+fn profile(code: &str, parallel: bool) -> run::Net {
+  let book = book_to_runtime(&do_parse_book(code));
+  let mut net = run::Net::new(1 << 16, false);
+  net.enable_profile(&book);
+  if parallel {
+    net.parallel_normal(&book);
+  } else {
+    net.normal(&book);
+  }
+  net
+}
+
+#[test]
+fn test_profile_totals() {
+  for code in [
+    load_example("church_encoding/church.hvmc"),
+    load_example("machine_u32/num_match.hvmc"),
+    // @c2 is copied through the DUPs of @c3, and ERA~ERA pairs are skipped
+    "@c2 = ({2 (a b) (b c)} (a c))\n@c3 = ({3 (a b) {3 (b c) (c d)}} (a d))\n@main = r & @c3 ~ (@c2 r)".to_string(),
+    "@main = * & * ~ *".to_string(),
+  ] {
+    for parallel in [false, true] {
+      let net = profile(&code, parallel);
+      let rwts = net.get_rewrites();
+      let entries = net.get_profile().unwrap().entries();
+      assert_eq!(entries.iter().map(|(_, stat)| stat.rwts).sum::<usize>(), rwts.total(), "{code}");
+      assert_eq!(entries.iter().map(|(_, stat)| stat.dref).sum::<usize>(), rwts.dref, "{code}");
+    }
+  }
+}
+
+#[test]
+fn test_profile_table() {
+  let net = profile(&load_example("church_encoding/church.hvmc"), false);
+  assert_snapshot!(net.get_profile().unwrap().show_table(), @r###"
+    NAME          DREF         NODE         LIVE         RWTS   %RWTS
+    @S               4           28            8           13  35.14%
+    @add             1            9            3            5  13.51%
+    @c2              1            9            2            5  13.51%
+    @c4              1            4            0            4  10.81%
+    @mul             1            7            0            4  10.81%
+    @main            1            4            0            3   8.11%
+    @Z               1            2            0            2   5.41%
+    @c3              1            7            5            1   2.70%
+  "###);
+}
@@ -0,0 +1,59 @@
+use hvmc::ast::*;
+
+const BOOK: &str = "
+  @main = (@a_long_name *)
+  @a_long_name = (@b *)
+  @b = (@c *)
+  @c = *
+  @other = @c
+  @loop_one = @loop_two
+  @loop_two = (@loop_one *)
+  @unused = *
+";
+
+// Prunes the book from the given entry points, returning how many were removed and which remain
+fn prune(entries: &[&str]) -> (usize, Vec<String>) {
+  let mut book = book_to_runtime(&do_parse_book(BOOK));
+  let gone = book.prune(&entries.iter().map(|name| name_to_val(name)).collect::<Vec<_>>());
+  let mut names = book.defs.keys().map(|fid| show_name(*fid, &[&book.names])).collect::<Vec<_>>();
+  names.sort();
+  (gone, names)
+}
+
+#[test]
+fn test_prune_transitive() {
+  // @c is only reachable through @a_long_name and @b
+  assert_eq!(prune(&["main"]), (4, vec!["a_long_name".to_string(), "b".into(), "c".into(), "main".into()]));
+}
+
+#[test]
+fn test_prune_cycle() {
+  // An unreachable cycle is removed, and a reachable one is kept whole
+  assert!(!prune(&["main"]).1.contains(&"loop_one".to_string()));
+  assert_eq!(prune(&["loop_two"]), (6, vec!["loop_one".to_string(), "loop_two".into()]));
+}
+
+#[test]
+fn test_prune_entries() {
+  // Definitions reachable from several entry points are kept once
+  assert_eq!(prune(&["other", "b"]), (5, vec!["b".to_string(), "c".into(), "other".into()]));
+  assert_eq!(prune(&["main", "other", "c"]), (3, vec!["a_long_name".to_string(), "b".into(), "c".into(), "main".into(), "other".into()]));
+}
+
+#[test]
+fn test_prune_missing_entry() {
+  // A missing entry point reaches nothing; the 'prune' command rejects it before pruning
+  assert_eq!(prune(&["missing", "other"]), (6, vec!["c".to_string(), "other".into()]));
+  assert_eq!(prune(&["missing"]), (8, vec![]));
+}
+
+#[test]
+fn test_prune_names() {
+  // The names of removed definitions are dropped along with them
+  let mut book = book_to_runtime(&do_parse_book(BOOK));
+  assert!(book.names.values().any(|name| name == "a_long_name"));
+  book.prune(&[name_to_val("other")]);
+  let mut names = book.names.values().cloned().collect::<Vec<_>>();
+  names.sort();
+  assert_eq!(names, ["c", "other"]);
+}
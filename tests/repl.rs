@@ -0,0 +1,52 @@
+use hvmc::repl::{Mode, Repl};
+use insta::assert_snapshot;
+
+fn exec(repl: &mut Repl, line: &str) -> Result<String, String> {
+  repl.exec(line).expect("repl quit")
+}
+
+#[test]
+fn test_repl_commands() {
+  let mut repl = Repl::new(1 << 12);
+  assert_snapshot!(exec(&mut repl, "@id = (a a)").unwrap(), @"Defined @id.");
+  assert_snapshot!(exec(&mut repl, "@app = ((a b) (a b))").unwrap(), @"Defined @app.");
+  assert_snapshot!(exec(&mut repl, "@main = r & @app ~ (@id (@nope r))").unwrap(), @"Defined @main (refers to undefined @nope).");
+  assert_snapshot!(exec(&mut repl, "r & @app ~ (@id (#1 r))").unwrap(), @"#1");
+  assert_snapshot!(exec(&mut repl, ":show @id").unwrap(), @"@id = (a a)");
+  assert_snapshot!(exec(&mut repl, ":show").unwrap(), @r###"
+    @app = ((a b) (a b))
+    @id = (a a)
+    @main = r
+    & @app ~ (@id (@nope r))
+  "###);
+  assert_snapshot!(exec(&mut repl, ":mode").unwrap(), @"Mode is eager.");
+  for mode in ["lazy", "parallel", "eager"] {
+    exec(&mut repl, &format!(":mode {}", mode)).unwrap();
+    assert_eq!(exec(&mut repl, "r & @id ~ (#2 r)").unwrap(), "#2");
+  }
+  assert_eq!(repl.mode, Mode::Eager);
+  assert_snapshot!(exec(&mut repl, ":stats on").unwrap(), @"Showing stats after each evaluation.");
+  let out = exec(&mut repl, "@id").unwrap();
+  assert!(out.starts_with("(a a)\nRWTS: 1 (ANNI 0, COMM 0, ERAS 0, DREF 1, OPER 0) | TIME: "), "{out}");
+  assert_snapshot!(exec(&mut repl, "  ").unwrap(), @"");
+  let file = format!("{}/examples/church_encoding/church.hvmc", env!("CARGO_MANIFEST_DIR"));
+  assert_eq!(exec(&mut repl, &format!(":load {}", file)).unwrap(), format!("Loaded 8 definitions from '{}'.", file));
+  assert!(repl.book.contains_key("c2"));
+  assert!(repl.exec(":q").is_none());
+  assert!(repl.exec(":quit").is_none());
+}
+
+#[test]
+fn test_repl_errors() {
+  let mut repl = Repl::new(1 << 12);
+  assert_snapshot!(exec(&mut repl, ":stats").unwrap(), @"Nothing evaluated yet.");
+  assert_snapshot!(exec(&mut repl, ":stats maybe").unwrap_err(), @"expected 'on' or 'off', found 'maybe'");
+  assert_snapshot!(exec(&mut repl, ":mode fast").unwrap_err(), @"unknown mode 'fast'");
+  assert_snapshot!(exec(&mut repl, ":show @nope").unwrap_err(), @"unknown definition @nope");
+  assert_snapshot!(exec(&mut repl, ":load").unwrap_err(), @"missing file name");
+  assert_snapshot!(exec(&mut repl, ":frobnicate").unwrap_err(), @"unknown command ':frobnicate' (try ':help')");
+  assert_snapshot!(exec(&mut repl, "(a").unwrap_err(), @"At 1:3: Expected a name character, found None");
+  assert_snapshot!(exec(&mut repl, "@bad = (a b)").unwrap_err(), @"on @bad: variable 'a' occurs 1 time, instead of twice (at 1:9)");
+  assert_snapshot!(exec(&mut repl, "@nope").unwrap_err(), @"unknown definition @nope");
+  assert!(repl.book.is_empty());
+}
@@ -1,6 +1,7 @@
-use hvmc::ast::show_net;
+use hvmc::{ast::*, cuda, run, sim};
 use insta::{assert_debug_snapshot, assert_snapshot};
 use loaders::*;
+use std::fs;
 
 mod loaders;
 
@@ -10,7 +11,7 @@ fn test_era_era() {
   let net = parse_core("@main = * & * ~ *");
   let (rnet, net) = normal(net, 16);
   assert_snapshot!(show_net(&net), @"*");
-  assert_debug_snapshot!(rnet.rewrites(), @"2");
+  assert_debug_snapshot!(rnet.get_rewrites().total(), @"2");
 }
 
 #[test]
@@ -18,7 +19,7 @@ fn test_era_era2() {
   let net = parse_core("@main = (* *) & * ~ *");
   let (rnet, net) = normal(net, 16);
   assert_snapshot!(show_net(&net), @"(* *)");
-  assert_debug_snapshot!(rnet.rewrites(), @"2");
+  assert_debug_snapshot!(rnet.get_rewrites().total(), @"2");
 }
 
 #[test]
@@ -26,7 +27,7 @@ fn test_commutation() {
   let net = parse_core("@main = root & (x x) ~ [* root]");
   let (rnet, net) = normal(net, 16);
   assert_snapshot!(show_net(&net), @"(a a)"); 
-  assert_debug_snapshot!(rnet.rewrites(), @"5");
+  assert_debug_snapshot!(rnet.get_rewrites().total(), @"5");
 }
 
 #[test]
@@ -42,7 +43,7 @@ fn test_bool_and() {
   let (rnet, net) = normal(book, 64);
 
   assert_snapshot!(show_net(&net), @"(* (a a))");
-  assert_debug_snapshot!(rnet.rewrites(), @"9");
+  assert_debug_snapshot!(rnet.get_rewrites().total(), @"9");
 }
 
 #[test]
@@ -54,7 +55,7 @@ fn test_church_mul() {
   assert!(valid_readback);
   assert_snapshot!(show_net(&net), @"({5 ({3 a b} c) {7 (c d) (d {3 b e})}} (a e))");
   assert_snapshot!(readback, @"λa λb (a (a (a (a (a (a b))))))");
-  assert_debug_snapshot!(rnet.rewrites(), @"11");
+  assert_debug_snapshot!(rnet.get_rewrites().total(), @"11");
 }
 
 #[test]
@@ -66,7 +67,7 @@ fn test_tree_alloc() {
   assert!(valid_readback);
   assert_snapshot!(show_net(&net), @"(a (* a))");
   assert_snapshot!(readback, @"λa λ* a");
-  assert_debug_snapshot!(rnet.rewrites(), @"97");
+  assert_debug_snapshot!(rnet.get_rewrites().total(), @"97");
 }
 
 #[test]
@@ -79,5 +80,81 @@ fn test_queue() {
   assert!(valid_readback);
   assert_snapshot!(show_net(&net), @"(((* (a a)) ((((b b) (((({3 (c d) (d e)} (c e)) ((* (f f)) g)) (* g)) h)) (* h)) i)) (* i))");
   assert_snapshot!(readback, @"λa λ* (a λ* λb b λc λ* (c λd d λe λ* (e λf λg (f (f g)) λ* λh h)))");
-  assert_debug_snapshot!(rnet.rewrites(), @"65");
+  assert_debug_snapshot!(rnet.get_rewrites().total(), @"65");
+}
+
+// Runs a book through 'sim.rs', the CPU emulation of the GPU algorithm, checks its heap, and
+// compares its result with 'run.rs'. Returns both rewrite counts.
+fn sim_and_run(book: Book) -> ([usize; 5], [usize; 5]) {
+  let book = book_to_runtime(&book);
+  let snet = sim::Net::new(&cuda::Book::new(&book).unwrap(), name_to_val("main") as cuda::Val, 24).unwrap();
+  snet.normal();
+  snet.check().unwrap();
+  let mut rnet = run::Net::new(1 << 20, false);
+  rnet.normal(&book);
+  assert!(net_equiv(&snet.to_net().unwrap(), &rnet.to_net()));
+  let (s, r) = (snet.get_rewrites(), rnet.get_rewrites());
+  ([s.anni, s.comm, s.eras, s.dref, s.oper], [r.anni, r.comm, r.eras, r.dref, r.oper])
+}
+
+#[test]
+fn test_sim_rewrites() {
+  let church = fs::read_to_string(format!("{}/examples/church_encoding/church.hvmc", env!("CARGO_MANIFEST_DIR"))).unwrap();
+  for code in [
+    "@main = root & (x x) ~ [* root]",
+    "@true = (a (* a))\n@fals = (* (a a))\n@and = ((a (@fals b)) (a b))\n@main = root & @and ~ (@true (@fals root))",
+    &church,
+  ] {
+    let (sim, run) = sim_and_run(parse_core(code));
+    assert_eq!(sim, run, "{code}");
+  }
+}
+
+#[test]
+fn test_sim_ref_dup() {
+  // 'run.rs' copies a REF that meets a DUP whose label isn't in its definition, while the GPU
+  // algorithm expands it and lets the DUP commute through it. The results agree, the counts don't.
+  let book = parse_core("@c2 = ({2 (a b) (b c)} (a c))\n@c3 = ({3 (a b) {3 (b c) (c d)}} (a d))\n@main = r & @c3 ~ (@c2 r)");
+  let (sim, run) = sim_and_run(book);
+  assert_eq!(sim, [14, 12, 0, 3, 0]);
+  assert_eq!(run, [8, 4, 0, 7, 0]);
+}
+
+#[test]
+fn test_usage() {
+  let code = fs::read_to_string(format!("{}/examples/church_encoding/church.hvmc", env!("CARGO_MANIFEST_DIR"))).unwrap();
+  let book = book_to_runtime(&parse_core(&code));
+  let mut net = run::Net::new(1 << 16, false);
+  net.normal(&book);
+  // The normal form has 18 nodes, all of them allocated by the run
+  let usage = net.get_usage();
+  assert_eq!((usage.live(), usage.peak(), usage.bound), (18, 32, false));
+  // Parallel peaks are summed over threads, so they're only an upper bound
+  let mut net = run::Net::new(1 << 16, false);
+  net.set_threads(2);
+  net.parallel_normal(&book);
+  assert_eq!(net.get_usage().live(), 18);
+  assert!(net.get_usage().bound);
+}
+
+#[test]
+fn test_heap_reuse() {
+  let code = fs::read_to_string(format!("{}/examples/church_encoding/church.hvmc", env!("CARGO_MANIFEST_DIR"))).unwrap();
+  let book = book_to_runtime(&parse_core(&code));
+  for lazy in [false, true] {
+    let mut net = run::Net::new(1 << 16, lazy);
+    let mut first = None;
+    for _ in 0 .. 4 {
+      net.boot_with(&book, "main", &[]).unwrap();
+      net.normal(&book);
+      let rwts = net.get_rewrites();
+      let usage = net.get_usage();
+      let run = (net.show(&book), [rwts.anni, rwts.comm, rwts.eras, rwts.dref, rwts.oper], usage.live(), usage.peak());
+      assert_eq!(&run, first.get_or_insert_with(|| run.clone()));
+      net.reset();
+      let usage = net.get_usage();
+      assert_eq!((usage.alloc, usage.freed, usage.peak(), net.get_rewrites().total()), (0, 0, 0, 0));
+      assert_eq!(net.collect_garbage(false), 0);
+    }
+  }
 }
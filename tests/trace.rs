@@ -0,0 +1,45 @@
+use hvmc::{ast::*, run, trace};
+use std::fs;
+
+fn load_church() -> run::Book {
+  let code = fs::read_to_string(format!("{}/examples/church_encoding/church.hvmc", env!("CARGO_MANIFEST_DIR"))).unwrap();
+  book_to_runtime(&do_parse_book(&code))
+}
+
+#[test]
+fn test_log_roundtrip() {
+  let book = load_church();
+  let mut event = trace::Event::new(3, trace::Rule::Comm, run::Ptr::new(run::LAM, 0, 0x10), run::Ptr::new(run::LAM, 1, 0x20));
+  event.locs = vec![0x30, 0x31];
+  let events = vec![
+    event,
+    trace::Event::new(0, trace::Rule::Dref, run::Ptr::big(run::REF, name_to_val("c2")), run::Ptr::new(run::VR1, 0, 0x40)),
+    trace::Event::new(1, trace::Rule::Void, run::Ptr::new(run::ERA, 0, 0), run::Ptr::big(run::NUM, 7)),
+  ];
+  let log = trace::show_log(&events, &book.names);
+  assert_eq!(trace::parse_log(&log), Ok(events));
+  assert!(trace::parse_log("0 NOPE 0 0 -").is_err());
+  assert!(trace::parse_log("0 ANNI 0 0").is_err());
+}
+
+#[test]
+#[cfg(feature = "trace")]
+fn test_record_replay() {
+  let book = load_church();
+  let mut net = run::Net::new(1 << 16, false);
+  net.normal(&book);
+  let log = trace::show_log(net.get_trace(), &book.names);
+  let events = trace::parse_log(&log).unwrap();
+
+  // Each rewrite is logged under its rule
+  let rwts = net.get_rewrites();
+  let count = |rule| events.iter().filter(|event| event.rule == rule).count();
+  assert_eq!(count(trace::Rule::Anni), rwts.anni);
+  assert_eq!(count(trace::Rule::Dref), rwts.dref);
+  assert_eq!(events.len(), rwts.total());
+
+  // A sequential replay performs the same interactions, on the same locations
+  let mut replay = run::Net::new(1 << 16, false);
+  replay.normal(&book);
+  assert_eq!(trace::find_diff(&events, replay.get_trace()), None);
+}
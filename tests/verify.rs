@@ -0,0 +1,40 @@
+use hvmc::{ast, run, verify::{self, Outcome, Strategy}};
+use insta::assert_debug_snapshot;
+use std::fs;
+
+fn load_example(file: &str) -> run::Book {
+  let path = format!("{}/examples/{}", env!("CARGO_MANIFEST_DIR"), file);
+  ast::book_to_runtime(&ast::do_parse_book(&fs::read_to_string(path).unwrap()))
+}
+
+fn verify_example(file: &str) -> Option<(String, String)> {
+  let strategies = [Strategy::Sequential, Strategy::Parallel, Strategy::Lazy];
+  verify::verify(&load_example(file), &["main".to_string()], &strategies, 1 << 20).unwrap()
+}
+
+fn outcome(strategy: Strategy, code: &str) -> Outcome {
+  Outcome { strategy, norm: ast::do_parse_net(code), rwts: None }
+}
+
+#[test]
+fn test_verify_church() {
+  assert_debug_snapshot!(verify_example("church_encoding/church.hvmc"), @"None");
+}
+
+#[test]
+fn test_verify_num_match() {
+  assert_debug_snapshot!(verify_example("machine_u32/num_match.hvmc"), @"None");
+}
+
+#[test]
+fn test_compare_redexes() {
+  let book = run::Book::new();
+  // Exact strategies must leave the same redexes.
+  let outs = [outcome(Strategy::Sequential, "a & @f ~ (a *)"), outcome(Strategy::Parallel, "a & @f ~ (* a)")];
+  assert!(verify::compare(&book, &outs).is_some());
+  let outs = [outcome(Strategy::Sequential, "a & @f ~ (a *)"), outcome(Strategy::Parallel, "b & (b *) ~ @f")];
+  assert!(verify::compare(&book, &outs).is_none());
+  // The lazy one only has to agree on the root.
+  let outs = [outcome(Strategy::Sequential, "#1 & @f ~ *"), outcome(Strategy::Lazy, "#1")];
+  assert!(verify::compare(&book, &outs).is_none());
+}